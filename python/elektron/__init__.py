@@ -1,8 +1,9 @@
-from typing import Dict
+from typing import Dict, List, Optional
 import numpy as np
 
 from elektron.elektron import Draw  as RDraw # , ElementType
-from elektron.elektron import get_bom, schema_plot, schema_netlist, search
+from elektron.elektron import Circuit, OperatingPoint, Schematic, SexpParser
+from elektron.elektron import get_bom, schema_diff, schema_plot, schema_netlist, search, symbol_plot
 
 
 class LogicException(Exception):
@@ -173,5 +174,6 @@ class Draw():
     def plot(self, filename, border: bool, scale: float):
         self.schema.plot(filename, border, scale)
 
-    def circuit(self):
-        return self.schema.circuit()
+    def circuit(self, spice_pathlist: Optional[List[str]] = None):
+        """Create the spice circuit, spice_pathlist is searched before the configured model paths."""
+        return self.schema.circuit(spice_pathlist)
@@ -17,6 +17,10 @@ def main() -> int:
                         help='Dump the Schema netlist.')
     parser.add_argument('--search', dest='action', action='append_const', const='search',
                         help='search in the symbol library.')
+    parser.add_argument('--diff', dest='action', action='append_const', const='diff',
+                        help='Compare the input with the old schema.')
+    parser.add_argument('--symbol', dest='action', action='append_const', const='symbol',
+                        help='Plot the library symbol given with --term.')
     parser.add_argument('--erc', dest='action', action='append_const', const='erc',
                         help='Run ERC test on schema.')
     parser.add_argument('--pcb', dest='action', action='append_const', const='pcb',
@@ -25,20 +29,35 @@ def main() -> int:
                         help='The input filename.')
     parser.add_argument('--output', dest='output',
                         help='The output filename.')
+    parser.add_argument('--old', dest='old',
+                        help='The old schema for the diff, defaults to the input.')
+    parser.add_argument('--revision', dest='revision',
+                        help='Read the old schema from this git revision.')
+    parser.add_argument('--format', dest='format',
+                        help='The output format, svg, pdf, png or html for plots and text, json or svg for the diff.')
     parser.add_argument('--plotter', dest='plotter',
                         help='Select the ploter backend', default='PlotSvgWrite')
+    parser.add_argument('--theme', dest='theme',
+                        help='The plot theme, light, dark, monochrome or a KiCad color theme file.')
     parser.add_argument("--term", dest='term', nargs="?")
+    parser.add_argument('--spice-path', dest='spice_path', action='append',
+                        help='Add a spice model path, can be used more then once.')
 
     args = parser.parse_args()
 
     if 'search' in args.action:
         elektron.search(args.term, ['/usr/share/kicad/symbols'])
+    if 'symbol' in args.action:
+        elektron.symbol_plot(args.term, args.output, ['/usr/share/kicad/symbols'], None, None, 1, None, args.theme)
     if 'bom' in args.action:
         elektron.get_bom(args.input, args.output, True)
     if 'plot' in args.action:
-        elektron.schema_plot(args.input, args.output, True, 1)
+        elektron.schema_plot(args.input, args.output, True, 1, None, args.format, None, args.theme)
     if 'netlist' in args.action:
-        elektron.schema_netlist(args.input, args.output)
+        elektron.schema_netlist(args.input, args.output, args.spice_path)
+    if 'diff' in args.action:
+        elektron.schema_diff(args.old or args.input, args.input, args.revision, None,
+                             args.output, args.format)
 
     return 0
 
@@ -1,23 +1,22 @@
-use crate::{Error, ngspice::{NgSpice, Callbacks, ComplexSlice}};
-use std::{fs::{self, File}, fmt::Display, io::Write};
+use crate::{Error, ngspice::{NgSpice, Callbacks, ComplexSlice, Simulator}};
+use std::{fs::{self, File}, fmt::Display, io::Write, path::Path};
 use ngspice_sys::*;
 use std::ffi::{CString, CStr, c_void};
 use std::os::raw::{c_char, c_int};
 use libloading::library_filename;
-use lazy_static::lazy_static;
-use regex::Regex;
 use pyo3::prelude::*;
 
 extern crate plotly;
 use plotly::common::Mode;
 use plotly::{Plot, Scatter};
 
-lazy_static! {
-    pub static ref RE_SUBCKT: regex::Regex =
-        Regex::new(r"(?i:\.SUBCKT) ([a-zA-Z0-9]*) .*").unwrap();
-    pub static ref RE_MODEL: regex::Regex = Regex::new(r"(?i:\.model) ([a-zA-Z0-9]*) .*").unwrap();
-    pub static ref RE_INCLUDE: regex::Regex = Regex::new(r"(?i:\.include) (.*)").unwrap();
-}
+pub mod models;
+pub mod op;
+pub mod parser;
+
+use models::{ModelInclude, ModelLibrary};
+pub use op::OperatingPoint;
+
 extern "C" fn controlled_exit(_arg1: c_int, _arg2: bool, _arg3: bool, _arg4: c_int, _arg5: *mut c_void) -> c_int {
     return 0;
 }
@@ -46,14 +45,85 @@ enum CircuitItem {
     Q(String, String, String, String, String),
     X(String, Vec<String>, String),
     V(String, String, String, String),
+    Param(String, String),
+    Subckt(String, Vec<String>, Vec<CircuitItem>),
+    Control(Vec<String>),
+    Other(String),
+}
+
+impl CircuitItem {
+    /// The spice name of the element, like `R1` or `XU1`.
+    fn name(&self) -> Option<String> {
+        match self {
+            CircuitItem::R(reference, ..) => Some(format!("R{}", reference)),
+            CircuitItem::C(reference, ..) => Some(format!("C{}", reference)),
+            CircuitItem::D(reference, ..) => Some(reference.to_string()),
+            CircuitItem::Q(reference, ..) => Some(format!("Q{}", reference)),
+            CircuitItem::X(reference, ..) => Some(format!("X{}", reference)),
+            CircuitItem::V(reference, ..) => Some(format!("V{}", reference)),
+            CircuitItem::Other(line) if !line.starts_with('.') => {
+                line.split_whitespace().next().map(|name| name.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Write the netlist lines of the item.
+    fn lines(&self, res: &mut Vec<String>) {
+        match self {
+            CircuitItem::R(reference, n0, n1, value) => {
+                res.push(format!("R{} {} {} {}", reference, n0, n1, value));
+            },
+            CircuitItem::C(reference, n0, n1, value) => {
+                res.push(format!("C{} {} {} {}", reference, n0, n1, value));
+            },
+            CircuitItem::D(reference, n0, n1, value) => {
+                res.push(format!("{} {} {} {}", reference, n0, n1, value));
+            },
+            CircuitItem::Q(reference, n0, n1, n2, value) => {
+                res.push(format!("Q{} {} {} {} {}", reference, n0, n1, n2, value));
+            },
+            CircuitItem::X(reference, n, value) => {
+                let mut nodes: String = String::new();
+                for _n in n {
+                    nodes += _n;
+                    nodes += " ";
+                };
+                res.push(format!("X{} {}{}", reference, nodes, value));
+            },
+            CircuitItem::V(reference, n0, n1, value) => {
+                res.push(format!("V{} {} {} {}", reference, n0, n1, value));
+            },
+            CircuitItem::Param(name, value) => {
+                res.push(format!(".param {}={}", name, value));
+            },
+            CircuitItem::Subckt(name, nodes, items) => {
+                res.push(format!(".subckt {} {}", name, nodes.join(" ")));
+                for item in items {
+                    item.lines(res);
+                }
+                res.push(format!(".ends {}", name));
+            },
+            CircuitItem::Control(lines) => {
+                res.push(String::from(".control"));
+                res.extend(lines.iter().cloned());
+                res.push(String::from(".endc"));
+            },
+            CircuitItem::Other(line) => {
+                res.push(line.to_string());
+            },
+        }
+    }
 }
 
 #[pyclass]
 pub struct Circuit {
     pathlist: Vec<String>,
-    libraries: Vec<String>,
+    title: Option<String>,
+    index: Option<std::sync::Arc<ModelLibrary>>,
+    libraries: Vec<ModelInclude>,
     items: Vec<CircuitItem>,
-    ngspice: std::sync::Arc<NgSpice<Cb>>,
+    ngspice: Option<std::sync::Arc<NgSpice<Cb>>>,
 }
 
 #[pymethods]
@@ -77,21 +147,17 @@ impl Circuit {
         self.items.push(CircuitItem::C(reference, n0, n1, value));
     }
 
-    pub fn diode(&mut self, reference: String, n0: String, n1: String, value: String) {
-        /* if value in self.subcircuits:
-            pass
-        else:
-            get_includes(value, self.includes, self.spice_models) */
+    pub fn diode(&mut self, reference: String, n0: String, n1: String, value: String) -> Result<(), Error> {
+        self.optional_includes(&value)?;
         self.items.push(CircuitItem::D(reference, n0, n1, value));
+        Ok(())
     }
 
-    pub fn bjt(&mut self, reference: String, n0: String, n1: String, n2: String, value: String) {
-        /* if value in self.subcircuits:
-            pass
-        else:
-            get_includes(value, self.includes, self.spice_models) */
+    pub fn bjt(&mut self, reference: String, n0: String, n1: String, n2: String, value: String) -> Result<(), Error> {
+        self.optional_includes(&value)?;
         self.items
             .push(CircuitItem::Q(reference, n0, n1, n2, value));
+        Ok(())
     }
 
     pub fn circuit(&mut self, reference: String, n: Vec<String>, value: String) -> Result<(), Error>{
@@ -106,26 +172,86 @@ impl Circuit {
         self.items.push(CircuitItem::V(reference, n1, n2, value));
     }
 
-    fn tran(&self) {
-        unsafe {
-            let mut circ = self.to_str().unwrap();
-            self.ngspice.circuit(circ);
-            self.ngspice.command("tran 10u 10ms").unwrap();
-            let plot = self.ngspice.current_plot().unwrap();
-            let res = self.ngspice.all_vecs(plot.as_str());
-            let re = self.ngspice.vector_info("input");
-            println!("plot: {:?}", res);
-            println!("vec: {:?}", re);
-
-            /* let ng = ngspice::new(library_filename("ngspice")).unwrap();
-            ng.ngSpice_Init(Some(send_char), None, Some(controlled_exit), None, None, None, std::ptr::null_mut());
-            let s = CString::new("tran 10u 10ms").expect("CString::new failed");
-            let mut circ = self.to_str().unwrap();
-            ng.ngSpice_Circ(circ.as_mut_ptr());
-            ng.ngSpice_Command(s.into_raw());
-            assert_eq!(responses.last().unwrap_or(&String::new()), "stdout hello"); */
+    /// Load a spice netlist, the spice_pathlist is used to resolve models added later.
+    #[staticmethod]
+    pub fn from_file(filename: &str, spice_pathlist: Option<Vec<String>>) -> Result<Circuit, Error> {
+        Circuit::load(filename, spice_pathlist.unwrap_or_default())
+    }
+
+    /// Set the value or model of an element by its spice name, like `R1` or `XU1`.
+    pub fn set_value(&mut self, name: &str, value: String) -> Result<(), Error> {
+        let index = self
+            .items
+            .iter()
+            .position(|item| matches!(item.name(), Some(n) if n.eq_ignore_ascii_case(name)))
+            .ok_or_else(|| Error::SpiceElementNotFound(name.to_string()))?;
+        //the includes are resolved first, the element is unchanged when the model is missing
+        let model = value.split_whitespace().next().unwrap_or("").to_string();
+        match &self.items[index] {
+            CircuitItem::R(..) | CircuitItem::C(..) | CircuitItem::V(..) => {}
+            CircuitItem::D(..) | CircuitItem::Q(..) => self.optional_includes(&model)?,
+            CircuitItem::X(..) => {
+                if !self.has_subckt(&model) {
+                    self.get_includes(&model)?;
+                }
+            }
+            _ => return Err(Error::SpiceElementNotFound(name.to_string())),
+        }
+        match &mut self.items[index] {
+            CircuitItem::R(_, _, _, v)
+            | CircuitItem::C(_, _, _, v)
+            | CircuitItem::V(_, _, _, v)
+            | CircuitItem::D(_, _, _, v)
+            | CircuitItem::Q(_, _, _, _, v)
+            | CircuitItem::X(_, _, v) => *v = value,
+            _ => {}
         }
-        // assert!(NgSpice::new(Cb { strs: Vec::new() }).is_err());
+        Ok(())
+    }
+
+    /// Set or add a `.param` value.
+    pub fn set_param(&mut self, name: &str, value: String) {
+        for item in self.items.iter_mut() {
+            if let CircuitItem::Param(key, v) = item {
+                if key == name {
+                    *v = value;
+                    return;
+                }
+            }
+        }
+        self.items.insert(0, CircuitItem::Param(name.to_string(), value));
+    }
+
+    /// Remove an element by its spice name.
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        let len = self.items.len();
+        self.items
+            .retain(|item| !matches!(item.name(), Some(n) if n.eq_ignore_ascii_case(name)));
+        if self.items.len() == len {
+            Err(Error::SpiceElementNotFound(name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run an operating point analysis.
+    pub fn op(&mut self) -> Result<OperatingPoint, Error> {
+        let ngspice = self.simulator()?;
+        ngspice.circuit(self.to_str()?)?;
+        let result = ngspice.op()?;
+        Ok(OperatingPoint::from(&result))
+    }
+
+    fn tran(&mut self) -> Result<(), Error> {
+        let ngspice = self.simulator()?;
+        ngspice.circuit(self.to_str()?)?;
+        ngspice.command("tran 10u 10ms")?;
+        let plot = ngspice.current_plot()?;
+        let res = ngspice.all_vecs(plot.as_str());
+        let re = ngspice.vector_info("input");
+        println!("plot: {:?}", res);
+        println!("vec: {:?}", re);
+        Ok(())
     }
 
     fn save(&self, filename: Option<String>) -> PyResult<()> {
@@ -135,197 +261,192 @@ impl Circuit {
         } else {
             Box::new(std::io::stdout())
         };
-        for lib in &self.libraries {
-            writeln!(out, ".include {}", lib)?;
-        }
-        for item in &self.items {
-            match item {
-                CircuitItem::R(reference, n0, n1, value) => {
-                    writeln!(out, "R{} {} {} {}", reference, n0, n1, value)?;
-                },
-                CircuitItem::C(reference, n0, n1, value) => {
-                    writeln!(out, "C{} {} {} {}", reference, n0, n1, value)?;
-                },
-                CircuitItem::D(reference, n0, n1, value) => {
-                    writeln!(out, "{} {} {} {}", reference, n0, n1, value)?;
-                },
-                CircuitItem::Q(reference, n0, n1, n2, value) => {
-                    writeln!(out, "Q{} {} {} {} {}", reference, n0, n1, n2, value)?;
-                },
-                CircuitItem::X(reference, n, value) => {
-                    let mut nodes: String = String::new();
-                    for _n in n {
-                        nodes += _n;
-                        nodes += " ";
-                    };
-                    writeln!(out, "X{} {}{}", reference, nodes, value)?;
-                },
-                CircuitItem::V(reference, n0, n1, value) => {
-                    writeln!(out, "V{} {} {} {}", reference, n0, n1, value)?;
-                },
-            }
+        for line in self.lines() {
+            writeln!(out, "{}", line)?;
         }
         out.flush()?;
         Ok(())
     }
-    fn plot(&self, name: &str, filename: Option<&str>) {
-
-        let plot = self.ngspice.current_plot().unwrap();
-        let vecs = self.ngspice.all_vecs(&plot).unwrap();
+    /// Plot the vector over the time, the plot is shown in the browser without a filename.
+    fn plot(&mut self, name: &str, filename: Option<&str>) -> Result<(), Error> {
+        let ngspice = self.simulator()?;
+        let plot = ngspice.current_plot()?;
+        let vecs = ngspice.all_vecs(&plot)?;
         for vec in vecs {
-            if let Ok(vecinfo) = self.ngspice.vector_info(&format!("{}.{}", plot, vec)) {
+            if let Ok(vecinfo) = ngspice.vector_info(&format!("{}.{}", plot, vec)) {
                 println!("{} {:?}", vec, vecinfo);
             }
         }
-        let re = self.ngspice.vector_info("time").unwrap();
+        let re = ngspice.vector_info("time")?;
         let data1 = match re.data {
             ComplexSlice::Real(list) => {
                 list
             },
-            ComplexSlice::Complex(list) => {
+            ComplexSlice::Complex(_) => {
                 //list.into_iter().map(|f| f.parse::<f64>()).collect()
                 &[0.0]
             }
         };
-        let re = self.ngspice.vector_info("input").unwrap();
+        let re = ngspice.vector_info(name)?;
         let data2 = match re.data {
             ComplexSlice::Real(list) => {
                 list
             },
-            ComplexSlice::Complex(list) => {
+            ComplexSlice::Complex(_) => {
                 //list.into_iter().map(|f| f.parse::<f64>()).collect()
                 &[0.0]
             }
         };
         let trace1 = Scatter::new(data1, data2)
-            .name("trace1")
+            .name(name)
             .mode(Mode::Markers);
 
         let mut plot = Plot::new();
         plot.add_trace(trace1);
-        /* plot.add_trace(trace2);
-        plot.add_trace(trace3); */
-        plot.show();
+        match filename {
+            Some(filename) => plot.to_html(filename),
+            None => plot.show(),
+        }
+        Ok(())
     }
 }
 
 impl Circuit {
     pub fn new(pathlist: Vec<String>) -> Self {
-
-        let c =  Cb { strs: Vec::new() };
         Self {
             pathlist,
+            title: None,
+            index: None,
             libraries: Vec::new(),
             items: Vec::new(),
-            ngspice: NgSpice::new(c).unwrap(),
+            ngspice: None,
         }
     }
-    fn get_includes(&mut self, key: &String) -> Result<(), Error> {
-        for path in &self.pathlist {
-            for entry in fs::read_dir(path).unwrap() {
-                let dir = entry.unwrap();
-                if dir.path().is_file() {
-                    let content = fs::read_to_string(dir.path())?;
-                    let captures = RE_SUBCKT.captures(&content);
-                    if let Some(caps) = captures {
-                        let text1 = caps.get(1).map_or("", |m| m.as_str());
-                        if text1 == key {
-                            self.libraries.push(dir.path().to_str().unwrap().to_string());
-                            let captures = RE_INCLUDE.captures(&content);
-                            if let Some(caps) = captures {
-                                for cap in caps.iter().skip(1) {
-                                    let text1 = cap.map_or("", |m| m.as_str());
-                                    if !text1.contains("/") { //when there is no slash i could be
-                                                              //a relative path.
-                                        let mut parent = dir.path().parent().unwrap().to_str().unwrap().to_string();
-                                        parent += "/";
-                                        parent += &text1.to_string();
-                                        self.libraries.push(parent.to_string());
-                                    } else {
-                                        self.libraries.push(text1.to_string());
-                                    }
-                                }
-                            }
-                            return Ok(());
-                        }
-                    }
-                }
+
+    /// Get the simulator, it is created for the first analysis.
+    fn simulator(&mut self) -> Result<std::sync::Arc<NgSpice<Cb>>, Error> {
+        if self.ngspice.is_none() {
+            self.ngspice = Some(NgSpice::new(Cb { strs: Vec::new() })?);
+        }
+        Ok(self.ngspice.clone().unwrap())
+    }
+    /// Add the includes for a spice model to the circuit.
+    ///
+    /// The model index is shared by the circuits with the same pathlist and
+    /// cached on disk.
+    fn get_includes(&mut self, key: &str) -> Result<(), Error> {
+        if self.index.is_none() {
+            self.index = Some(ModelLibrary::shared(&self.pathlist)?);
+        }
+        let includes = self.index.as_ref().unwrap().includes(key)?;
+        for include in includes {
+            if !self.libraries.contains(&include) {
+                self.libraries.push(include);
             }
         }
-        Err(Error::SpiceModelNotFound(key.to_string()))
+        Ok(())
     }
 
-    fn to_str(&self) -> Result<Vec<String>, Error> {
+    /// Add the includes for a model that can also be defined by the user.
+    ///
+    /// Only a missing model is ignored, other errors are returned.
+    fn optional_includes(&mut self, key: &str) -> Result<(), Error> {
+        match self.get_includes(key) {
+            Err(Error::SpiceModelNotFound(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Load a spice netlist.
+    pub fn load(filename: &str, pathlist: Vec<String>) -> Result<Self, Error> {
+        let content = fs::read_to_string(filename)?;
+        let dir = Path::new(filename).parent().unwrap_or_else(|| Path::new(""));
+        let netlist = parser::parse(&content, dir)?;
+        let mut circuit = Circuit::new(pathlist);
+        circuit.title = netlist.title;
+        circuit.libraries = netlist.includes;
+        circuit.items = netlist.items;
+        Ok(circuit)
+    }
+
+    /// The netlist lines without the `.end` statement.
+    fn lines(&self) -> Vec<String> {
         let mut res = Vec::new();
+        if let Some(title) = &self.title {
+            res.push(title.to_string());
+        }
         for lib in &self.libraries {
-            res.push(format!(".include {}", lib));
+            res.push(lib.to_string());
         }
         for item in &self.items {
-            match item {
-                CircuitItem::R(reference, n0, n1, value) => {
-                    res.push(format!("R{} {} {} {}", reference, n0, n1, value));
-                },
-                CircuitItem::C(reference, n0, n1, value) => {
-                    res.push(format!("C{} {} {} {}", reference, n0, n1, value));
-                },
-                CircuitItem::D(reference, n0, n1, value) => {
-                    res.push(format!("{} {} {} {}", reference, n0, n1, value));
-                },
-                CircuitItem::Q(reference, n0, n1, n2, value) => {
-                    res.push(format!("Q{} {} {} {} {}", reference, n0, n1, n2, value));
-                },
-                CircuitItem::X(reference, n, value) => {
-                    let mut nodes: String = String::new();
-                    for _n in n {
-                        nodes += _n;
-                        nodes += " ";
-                    };
-                    res.push(format!("X{} {}{}", reference, nodes, value));
-                },
-                CircuitItem::V(reference, n0, n1, value) => {
-                    res.push(format!("V{} {} {} {}", reference, n0, n1, value));
-                },
-            }
+            item.lines(&mut res);
         }
+        res
+    }
+
+    fn to_str(&self) -> Result<Vec<String>, Error> {
+        let mut res = self.lines();
         res.push(String::from(".end"));
-        /* res.push(CString::new("").unwrap().into_raw());
-        res.push(std::ptr::null_mut()); */
         Ok(res)
     }
+
+    /// Check if the subcircuit is defined in this circuit.
+    fn has_subckt(&self, name: &str) -> bool {
+        self.items.iter().any(|item| {
+            matches!(item, CircuitItem::Subckt(subckt, ..) if subckt.eq_ignore_ascii_case(name))
+        })
+    }
 }
 
 impl Display for Circuit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for lib in &self.libraries {
-            writeln!(f, ".include {}", lib)?;
-        }
-        for item in &self.items {
-            match item {
-                CircuitItem::R(reference, n0, n1, value) => {
-                    writeln!(f, "R{} {} {} {}", reference, n0, n1, value)?;
-                },
-                CircuitItem::C(reference, n0, n1, value) => {
-                    writeln!(f, "C{} {} {} {}", reference, n0, n1, value)?;
-                },
-                CircuitItem::D(reference, n0, n1, value) => {
-                    writeln!(f, "{} {} {} {}", reference, n0, n1, value)?;
-                },
-                CircuitItem::Q(reference, n0, n1, n2, value) => {
-                    writeln!(f, "Q{} {} {} {} {}", reference, n0, n1, n2, value)?;
-                },
-                CircuitItem::X(reference, n, value) => {
-                    let mut nodes: String = String::new();
-                    for _n in n {
-                        nodes += _n;
-                        nodes += " ";
-                    };
-                    writeln!(f, "X{} {}{}", reference, nodes, value)?;
-                },
-                CircuitItem::V(reference, n0, n1, value) => {
-                    writeln!(f, "V{} {} {} {}", reference, n0, n1, value)?;
-                },
-            }
+        for line in self.lines() {
+            writeln!(f, "{}", line)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit() -> Circuit {
+        let netlist = parser::parse("edit\n.param gain=10\nR1 in out 10k\nXU1 in out TL072c\n", Path::new(".")).unwrap();
+        let mut circuit = Circuit::new(vec![String::from("samples/files/spice")]);
+        circuit.title = netlist.title;
+        circuit.items = netlist.items;
+        circuit
+    }
+
+    #[test]
+    fn set_value() {
+        let mut circuit = circuit();
+        circuit.set_value("r1", String::from("22k")).unwrap();
+        assert!(circuit.lines().contains(&String::from("R1 in out 22k")));
+        //the includes of the new model are added
+        circuit.set_value("XU1", String::from("TL072c")).unwrap();
+        assert!(circuit.libraries.iter().any(|include| include.path.ends_with("TL072.lib")));
+        assert!(matches!(circuit.set_value("R2", String::from("1k")), Err(Error::SpiceElementNotFound(_))));
+        assert!(matches!(circuit.set_value("XU1", String::from("LM741")), Err(Error::SpiceModelNotFound(_))));
+        //the old model is kept after the error
+        assert!(circuit.lines().iter().any(|line| line.starts_with("XU1 ") && line.ends_with(" TL072c")));
+        assert!(!circuit.lines().iter().any(|line| line.contains("LM741")));
+    }
+    #[test]
+    fn set_param() {
+        let mut circuit = circuit();
+        circuit.set_param("gain", String::from("20"));
+        circuit.set_param("rin", String::from("10k"));
+        let lines = circuit.lines();
+        assert_eq!(lines[1], ".param rin=10k");
+        assert_eq!(lines[2], ".param gain=20");
+    }
+    #[test]
+    fn remove() {
+        let mut circuit = circuit();
+        circuit.remove("R1").unwrap();
+        assert!(!circuit.lines().iter().any(|line| line.starts_with("R1")));
+        assert!(matches!(circuit.remove("R1"), Err(Error::SpiceElementNotFound(_))));
+    }
+}
@@ -0,0 +1,597 @@
+use crate::Error;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use lazy_static::lazy_static;
+
+/// File extensions that are scanned for spice models.
+const EXTENSIONS: [&str; 3] = ["lib", "mod", "cir"];
+/// Version of the cache file format.
+const CACHE_VERSION: usize = 2;
+
+lazy_static! {
+    /// The indexes of the pathlists that are already scanned by this process.
+    static ref SHARED: Mutex<HashMap<Vec<String>, Arc<ModelLibrary>>> = Mutex::new(HashMap::new());
+}
+
+/// The kind of an indexed model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    Subckt,
+    Model,
+}
+
+impl ModelKind {
+    fn name(&self) -> &'static str {
+        match self {
+            ModelKind::Subckt => "subckt",
+            ModelKind::Model => "model",
+        }
+    }
+    fn from_name(name: &str) -> Option<ModelKind> {
+        match name {
+            "subckt" => Some(ModelKind::Subckt),
+            "model" => Some(ModelKind::Model),
+            _ => None,
+        }
+    }
+}
+
+/// A file that must be included in the netlist, optionally only a `.lib` section of it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModelInclude {
+    pub path: PathBuf,
+    pub section: Option<String>,
+}
+
+impl ModelInclude {
+    pub fn new(path: PathBuf, section: Option<String>) -> Self {
+        Self { path, section }
+    }
+}
+
+impl Display for ModelInclude {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, ".lib {} {}", self.path.display(), section),
+            None => write!(f, ".include {}", self.path.display()),
+        }
+    }
+}
+
+/// A `.subckt` or `.model` definition found in the search path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelEntry {
+    pub name: String,
+    pub kind: ModelKind,
+    pub include: ModelInclude,
+}
+
+/// The definitions and references of a single model file.
+///
+/// The entries and includes have the `.lib` section they are defined in.
+#[derive(Debug, Clone, Default)]
+struct ModelFile {
+    modified: u128,
+    entries: Vec<(String, ModelKind, Option<String>)>,
+    includes: Vec<(ModelInclude, Option<String>)>,
+}
+
+impl ModelFile {
+    /// The includes of the file or of a section, the includes outside of the sections are always used.
+    fn includes<'a>(&'a self, section: &'a Option<String>) -> impl DoubleEndedIterator<Item = &'a ModelInclude> {
+        self.includes
+            .iter()
+            .filter(move |(_, scope)| scope.is_none() || scope == section)
+            .map(|(include, _)| include)
+    }
+}
+
+/// Index of all the spice models and subcircuits in the model search path.
+///
+/// The index is built once by scanning the search path recursively. The
+/// model names are case insensitive, like in spice. When a name is defined
+/// more than once, the first definition in the search path wins and the
+/// others are reported by `duplicates`.
+#[derive(Debug, Default)]
+pub struct ModelLibrary {
+    files: HashMap<PathBuf, ModelFile>,
+    models: HashMap<String, ModelEntry>,
+    duplicates: HashMap<String, Vec<ModelEntry>>,
+    cache_error: Option<Error>,
+}
+
+impl ModelLibrary {
+    /// Scan the pathlist and create the index.
+    pub fn new(pathlist: &[String]) -> Result<Self, Error> {
+        Self::build(pathlist, &mut HashMap::new())
+    }
+
+    /// Create the index and reuse the unchanged files from the cache file.
+    ///
+    /// The updated index is merged into the cache file, the files of other
+    /// pathlists are kept. Writing the cache is optional, a failure does not
+    /// fail the index and is returned by `cache_error`.
+    pub fn cached(pathlist: &[String], cache: &Path) -> Result<Self, Error> {
+        let mut cached = if cache.is_file() {
+            read_cache(cache).unwrap_or_default()
+        } else {
+            HashMap::new()
+        };
+        let mut library = Self::build(pathlist, &mut cached)?;
+        //the remaining files are from other pathlists
+        cached.retain(|path, _| path.is_file());
+        if let Err(err) = library.write_cache(cache, &cached) {
+            library.cache_error = Some(Error::IoError(format!("{}: {}", cache.display(), err)));
+        }
+        Ok(library)
+    }
+
+    /// Get the index of the pathlist, it is shared by the circuits of this process.
+    ///
+    /// The index uses the cache file when there is one. It is created again
+    /// when a model file in the pathlist was added, removed or modified.
+    pub fn shared(pathlist: &[String]) -> Result<Arc<Self>, Error> {
+        Self::shared_in(pathlist, cache_file())
+    }
+
+    fn shared_in(pathlist: &[String], cache: Option<PathBuf>) -> Result<Arc<Self>, Error> {
+        let mut shared = SHARED.lock().map_err(|err| Error::IoError(err.to_string()))?;
+        if let Some(library) = shared.get(pathlist) {
+            if library.is_current(pathlist)? {
+                return Ok(library.clone());
+            }
+        }
+        let library = Arc::new(match cache {
+            Some(cache) => Self::cached(pathlist, &cache)?,
+            None => Self::new(pathlist)?,
+        });
+        shared.insert(pathlist.to_vec(), library.clone());
+        Ok(library)
+    }
+
+    /// The error of writing the cache file.
+    pub fn cache_error(&self) -> Option<&Error> {
+        self.cache_error.as_ref()
+    }
+
+    /// Get the model definition by name.
+    pub fn get(&self, name: &str) -> Option<&ModelEntry> {
+        self.models.get(&name.to_lowercase())
+    }
+
+    /// Get the models that are defined in more than one place.
+    ///
+    /// The key is the lowercase model name, the values are all the definitions
+    /// in search order. The first one is used.
+    pub fn duplicates(&self) -> &HashMap<String, Vec<ModelEntry>> {
+        &self.duplicates
+    }
+
+    /// Iterate over all indexed models.
+    pub fn models(&self) -> impl Iterator<Item = &ModelEntry> {
+        self.models.values()
+    }
+
+    /// Get the includes for a model, including the files the model file includes itself.
+    pub fn includes(&self, name: &str) -> Result<Vec<ModelInclude>, Error> {
+        let entry = match self.get(name) {
+            Some(entry) => entry,
+            None => return Err(Error::SpiceModelNotFound(name.to_string())),
+        };
+        let mut result: Vec<ModelInclude> = Vec::new();
+        //every section of a file is visited once
+        let mut visited: HashSet<ModelInclude> = HashSet::new();
+        let mut queue = vec![entry.include.clone()];
+        while let Some(include) = queue.pop() {
+            if !visited.insert(include.clone()) {
+                continue;
+            }
+            let file = match self.files.get(&include.path) {
+                Some(file) => file.clone(),
+                None => parse(&include.path)?,
+            };
+            for child in file.includes(&include.section).rev() {
+                queue.push(child.clone());
+            }
+            result.push(include);
+        }
+        Ok(result)
+    }
+
+    /// Are the model files of the pathlist unchanged since the index was created.
+    fn is_current(&self, pathlist: &[String]) -> Result<bool, Error> {
+        let mut files = Vec::new();
+        for path in pathlist {
+            collect_files(Path::new(path), &mut files)?;
+        }
+        files.sort();
+        files.dedup();
+        if files.len() != self.files.len() {
+            return Ok(false);
+        }
+        for filename in files {
+            match self.files.get(&filename) {
+                Some(file) if file.modified == modified(&filename)? => {}
+                _ => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    fn build(pathlist: &[String], cached: &mut HashMap<PathBuf, ModelFile>) -> Result<Self, Error> {
+        let mut library = ModelLibrary::default();
+        for path in pathlist {
+            let mut files = Vec::new();
+            collect_files(Path::new(path), &mut files)?;
+            for filename in files {
+                if library.files.contains_key(&filename) {
+                    continue;
+                }
+                let modified = modified(&filename)?;
+                let file = match cached.remove(&filename) {
+                    Some(file) if file.modified == modified => file,
+                    _ => parse(&filename)?,
+                };
+                for (name, kind, section) in &file.entries {
+                    library.insert(ModelEntry {
+                        name: name.to_string(),
+                        kind: *kind,
+                        include: ModelInclude::new(filename.clone(), section.clone()),
+                    });
+                }
+                library.files.insert(filename, file);
+            }
+        }
+        Ok(library)
+    }
+
+    fn insert(&mut self, entry: ModelEntry) {
+        let key = entry.name.to_lowercase();
+        if let Some(first) = self.models.get(&key) {
+            self.duplicates
+                .entry(key)
+                .or_insert_with(|| vec![first.clone()])
+                .push(entry);
+        } else {
+            self.models.insert(key, entry);
+        }
+    }
+
+    fn write_cache(&self, cache: &Path, others: &HashMap<PathBuf, ModelFile>) -> Result<(), Error> {
+        let mut files = json::JsonValue::new_array();
+        let mut paths: Vec<(&PathBuf, &ModelFile)> = self.files.iter().chain(others.iter()).collect();
+        paths.sort_by(|a, b| a.0.cmp(b.0));
+        for (path, file) in paths {
+            let mut entries = json::JsonValue::new_array();
+            for (name, kind, section) in &file.entries {
+                entries.push(json::object! {
+                    name: name.as_str(),
+                    kind: kind.name(),
+                    section: section.clone(),
+                }).unwrap();
+            }
+            let mut includes = json::JsonValue::new_array();
+            for (include, scope) in &file.includes {
+                includes.push(json::object! {
+                    path: include.path.to_str(),
+                    section: include.section.clone(),
+                    scope: scope.clone(),
+                }).unwrap();
+            }
+            files.push(json::object! {
+                path: path.to_str(),
+                modified: file.modified.to_string(),
+                entries: entries,
+                includes: includes,
+            }).unwrap();
+        }
+        let data = json::object! {
+            version: CACHE_VERSION,
+            files: files,
+        };
+        if let Some(parent) = cache.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = File::create(cache)?;
+        data.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// The default location of the model index cache.
+///
+/// `$XDG_CACHE_HOME/elektron/spice_models.json` or `~/.cache/elektron/spice_models.json`.
+pub fn cache_file() -> Option<PathBuf> {
+    let base = if let Ok(cache) = std::env::var("XDG_CACHE_HOME") {
+        PathBuf::from(cache)
+    } else if let Ok(home) = std::env::var("HOME") {
+        PathBuf::from(home).join(".cache")
+    } else {
+        return None;
+    };
+    Some(base.join("elektron").join("spice_models.json"))
+}
+
+fn read_cache(cache: &Path) -> Option<HashMap<PathBuf, ModelFile>> {
+    let content = fs::read_to_string(cache).ok()?;
+    let data = json::parse(&content).ok()?;
+    if data["version"].as_usize()? != CACHE_VERSION {
+        return None;
+    }
+    let mut files = HashMap::new();
+    for file in data["files"].members() {
+        let mut entries = Vec::new();
+        for entry in file["entries"].members() {
+            entries.push((
+                entry["name"].as_str()?.to_string(),
+                ModelKind::from_name(entry["kind"].as_str()?)?,
+                entry["section"].as_str().map(|s| s.to_string()),
+            ));
+        }
+        let mut includes = Vec::new();
+        for include in file["includes"].members() {
+            includes.push((
+                ModelInclude::new(
+                    PathBuf::from(include["path"].as_str()?),
+                    include["section"].as_str().map(|s| s.to_string()),
+                ),
+                include["scope"].as_str().map(|s| s.to_string()),
+            ));
+        }
+        files.insert(
+            PathBuf::from(file["path"].as_str()?),
+            ModelFile {
+                modified: file["modified"].as_str()?.parse().ok()?,
+                entries,
+                includes,
+            },
+        );
+    }
+    Some(files)
+}
+
+/// Collect the model files in the directory and all subdirectories.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(|e| Error::IoError(format!("{}: {}", path.display(), e)))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else if let Some(ext) = entry.extension().and_then(|e| e.to_str()) {
+            if EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
+                files.push(entry);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn modified(path: &Path) -> Result<u128, Error> {
+    let modified = fs::metadata(path)?.modified()?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0))
+}
+
+/// Resolve a path from an include statement relative to the including file.
+fn resolve(parent: &Path, name: &str) -> PathBuf {
+    let name = name.trim_matches(|c| c == '"' || c == '\'');
+    let path = Path::new(name);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        parent.join(path)
+    }
+}
+
+/// Read the definitions and includes of a model file.
+///
+/// Definitions inside a subcircuit are local to the subcircuit and not indexed.
+fn parse(path: &Path) -> Result<ModelFile, Error> {
+    let content = fs::read(path)
+        .map_err(|e| Error::IoError(format!("{}: {}", path.display(), e)))?;
+    let content = String::from_utf8_lossy(&content);
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut file = ModelFile {
+        modified: modified(path)?,
+        ..Default::default()
+    };
+    let mut depth = 0;
+    let mut section: Option<String> = None;
+    for line in content.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let directive = match tokens.first() {
+            Some(token) if token.starts_with('.') => token.to_lowercase(),
+            _ => continue,
+        };
+        match directive.as_str() {
+            ".subckt" => {
+                if depth == 0 && tokens.len() > 1 {
+                    file.entries.push((tokens[1].to_string(), ModelKind::Subckt, section.clone()));
+                }
+                depth += 1;
+            }
+            ".ends" if depth > 0 => {
+                depth -= 1;
+            }
+            ".model" if depth == 0 && tokens.len() > 1 => {
+                //the type can follow the name without a space: "BC846B NPN(IS=..."
+                let name = tokens[1].split('(').next().unwrap_or(tokens[1]);
+                file.entries.push((name.to_string(), ModelKind::Model, section.clone()));
+            }
+            ".include" | ".inc" if tokens.len() > 1 => {
+                let include = (ModelInclude::new(resolve(parent, &tokens[1..].join(" ")), None), section.clone());
+                if !file.includes.contains(&include) {
+                    file.includes.push(include);
+                }
+            }
+            ".lib" => {
+                if tokens.len() == 2 {
+                    //the begin of a library section
+                    section = Some(tokens[1].to_string());
+                } else if tokens.len() > 2 {
+                    //a reference to a library section in another file
+                    let include = (
+                        ModelInclude::new(resolve(parent, tokens[1]), Some(tokens[2].to_string())),
+                        section.clone(),
+                    );
+                    if !file.includes.contains(&include) {
+                        file.includes.push(include);
+                    }
+                }
+            }
+            ".endl" => {
+                section = None;
+            }
+            _ => {}
+        }
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pathlist() -> Vec<String> {
+        vec![String::from("samples/files/spice")]
+    }
+
+    #[test]
+    fn index_subckt_and_models() {
+        let library = ModelLibrary::new(&pathlist()).unwrap();
+        let entry = library.get("TL072").unwrap();
+        assert_eq!(entry.kind, ModelKind::Subckt);
+        assert_eq!(entry.include.path, PathBuf::from("samples/files/spice/TL072.lib"));
+        assert_eq!(library.get("BC547B").unwrap().kind, ModelKind::Model);
+        //more then one model per file
+        assert!(library.get("BC846B").is_some());
+        assert!(library.get("BC556B").is_some());
+        //models inside a subcircuit are not global
+        assert!(library.get("CD4069BN").is_none());
+        assert!(library.get("DX").is_none());
+    }
+    #[test]
+    fn case_insensitive() {
+        let library = ModelLibrary::new(&pathlist()).unwrap();
+        assert_eq!(library.get("tl072c").unwrap().name, "TL072c");
+        assert_eq!(library.get("4069ub").unwrap().name, "4069UB");
+    }
+    #[test]
+    fn transitive_includes() {
+        let library = ModelLibrary::new(&pathlist()).unwrap();
+        let includes = library.includes("TL072c").unwrap();
+        assert_eq!(
+            includes,
+            vec![
+                ModelInclude::new(PathBuf::from("samples/files/spice/TL072-dual.lib"), None),
+                ModelInclude::new(PathBuf::from("samples/files/spice/TL072.lib"), None),
+            ]
+        );
+        assert_eq!(".include samples/files/spice/TL072.lib", includes[1].to_string());
+    }
+    #[test]
+    fn model_not_found() {
+        let library = ModelLibrary::new(&pathlist()).unwrap();
+        assert!(matches!(library.includes("LM741"), Err(Error::SpiceModelNotFound(_))));
+    }
+    #[test]
+    fn duplicates() {
+        let dir = std::env::temp_dir().join("elektron_test_duplicates");
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("samples/files/spice/TL072.lib", dir.join("tl072_copy.mod")).unwrap();
+        let pathlist = vec![String::from("samples/files/spice"), dir.to_str().unwrap().to_string()];
+        let library = ModelLibrary::new(&pathlist).unwrap();
+        let duplicates = library.duplicates().get("tl072").unwrap();
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].include.path, PathBuf::from("samples/files/spice/TL072.lib"));
+        assert_eq!(library.get("TL072").unwrap().include.path, PathBuf::from("samples/files/spice/TL072.lib"));
+    }
+    #[test]
+    fn cache() {
+        let cache = std::env::temp_dir().join("elektron_test_models").join("spice_models.json");
+        let _ = fs::remove_file(&cache);
+        let library = ModelLibrary::cached(&pathlist(), &cache).unwrap();
+        assert!(cache.is_file());
+        let cached = ModelLibrary::cached(&pathlist(), &cache).unwrap();
+        assert_eq!(library.models().count(), cached.models().count());
+        assert_eq!(cached.includes("TL072c").unwrap(), library.includes("TL072c").unwrap());
+    }
+    #[test]
+    fn merge_cache() {
+        let dir = std::env::temp_dir().join("elektron_test_merge_cache");
+        let models = dir.join("models");
+        fs::create_dir_all(&models).unwrap();
+        fs::copy("samples/files/spice/BC547.mod", models.join("BC547.mod")).unwrap();
+        let cache = dir.join("spice_models.json");
+        let _ = fs::remove_file(&cache);
+        ModelLibrary::cached(&pathlist(), &cache).unwrap();
+        ModelLibrary::cached(&[models.to_str().unwrap().to_string()], &cache).unwrap();
+        //the files of both pathlists are in the cache
+        let files = read_cache(&cache).unwrap();
+        assert!(files.contains_key(&PathBuf::from("samples/files/spice/TL072.lib")));
+        assert!(files.contains_key(&models.join("BC547.mod")));
+    }
+    #[test]
+    fn readonly_cache() {
+        //the parent of the cache is a file, the cache can not be written
+        let cache = PathBuf::from("samples/files/spice/TL072.lib").join("spice_models.json");
+        let library = ModelLibrary::cached(&pathlist(), &cache).unwrap();
+        assert!(library.get("TL072").is_some());
+        assert!(matches!(library.cache_error(), Some(Error::IoError(_))));
+        let cache = std::env::temp_dir().join("elektron_test_cache_error").join("spice_models.json");
+        assert!(ModelLibrary::cached(&pathlist(), &cache).unwrap().cache_error().is_none());
+    }
+    #[test]
+    fn library_sections() {
+        let dir = std::env::temp_dir().join("elektron_test_sections");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("corners.lib"), ".lib fast\n.include fast.inc\n.endl\n.lib slow\n.include slow.inc\n.endl\n").unwrap();
+        fs::write(dir.join("fast.inc"), "").unwrap();
+        fs::write(dir.join("slow.inc"), "").unwrap();
+        fs::write(dir.join("top.cir"), ".subckt TOP a b\n.ends\n.lib corners.lib fast\n.lib corners.lib slow\n").unwrap();
+        let library = ModelLibrary::new(&[dir.to_str().unwrap().to_string()]).unwrap();
+        //both sections of the file are included, each with its own includes
+        assert_eq!(
+            library.includes("TOP").unwrap(),
+            vec![
+                ModelInclude::new(dir.join("top.cir"), None),
+                ModelInclude::new(dir.join("corners.lib"), Some(String::from("fast"))),
+                ModelInclude::new(dir.join("fast.inc"), None),
+                ModelInclude::new(dir.join("corners.lib"), Some(String::from("slow"))),
+                ModelInclude::new(dir.join("slow.inc"), None),
+            ]
+        );
+    }
+    #[test]
+    fn shared() {
+        let dir = std::env::temp_dir().join("elektron_test_shared");
+        let models = dir.join("models");
+        fs::create_dir_all(&models).unwrap();
+        let _ = fs::remove_file(models.join("TL072.lib"));
+        fs::copy("samples/files/spice/BC547.mod", models.join("BC547.mod")).unwrap();
+        let pathlist = vec![models.to_str().unwrap().to_string()];
+        let cache = Some(dir.join("spice_models.json"));
+        let first = ModelLibrary::shared_in(&pathlist, cache.clone()).unwrap();
+        let second = ModelLibrary::shared_in(&pathlist, cache.clone()).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(dir.join("spice_models.json").is_file());
+        //a new model file invalidates the shared index
+        fs::copy("samples/files/spice/TL072.lib", models.join("TL072.lib")).unwrap();
+        let third = ModelLibrary::shared_in(&pathlist, cache).unwrap();
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert!(third.get("TL072").is_some());
+    }
+}
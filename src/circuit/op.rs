@@ -0,0 +1,111 @@
+use crate::ngspice::{Callbacks, ComplexSlice, SimulationResult};
+use pyo3::prelude::*;
+
+use std::collections::HashMap;
+
+/// The result of an operating point analysis.
+///
+/// The vectors are copied from the simulation result, the names are lower case
+/// like ngspice reports them: node voltages by the net name (`out`, `v(out)`)
+/// and branch currents as `v1#branch` or `@r1[i]`.
+#[pyclass]
+#[derive(Debug, Clone, Default)]
+pub struct OperatingPoint {
+    values: HashMap<String, f64>,
+}
+
+impl OperatingPoint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a vector.
+    pub fn insert(&mut self, name: &str, value: f64) {
+        self.values.insert(name.to_lowercase(), value);
+    }
+
+    /// The voltage of a net.
+    pub fn voltage(&self, net: &str) -> Option<f64> {
+        let net = net.to_lowercase();
+        if net == "0" || net == "gnd" {
+            return self.values.get(&net).copied().or(Some(0.0));
+        }
+        self.values
+            .get(&net)
+            .or_else(|| self.values.get(&format!("v({})", net)))
+            .copied()
+    }
+
+    /// The current through an element.
+    ///
+    /// The element can be named by the schema reference (`R1`) or the spice name,
+    /// when the circuit is created from the schema the element type is prepended
+    /// to the reference (`RR1`).
+    pub fn current(&self, reference: &str) -> Option<f64> {
+        let reference = reference.to_lowercase();
+        let mut names = vec![reference.clone()];
+        if let Some(prefix) = reference.chars().next() {
+            names.push(format!("{}{}", prefix, reference));
+        }
+        names.iter().find_map(|name| {
+            self.values
+                .get(&format!("@{}[i]", name))
+                .or_else(|| self.values.get(&format!("{}#branch", name)))
+                .or_else(|| self.values.get(&format!("i({})", name)))
+                .copied()
+        })
+    }
+}
+
+#[pymethods]
+impl OperatingPoint {
+    /// Get the value of a vector.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values.get(&name.to_lowercase()).copied()
+    }
+
+    /// The names of all vectors.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+impl<C: Callbacks> From<&SimulationResult<'_, C>> for OperatingPoint {
+    fn from(result: &SimulationResult<'_, C>) -> Self {
+        let mut op = OperatingPoint::new();
+        for (name, vector) in &result.data {
+            let value = match vector.data {
+                ComplexSlice::Real(list) => list.first().copied(),
+                ComplexSlice::Complex(list) => list.first().map(|c| c.cx_real),
+            };
+            if let Some(value) = value {
+                op.insert(name, value);
+            }
+        }
+        op
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        let mut op = OperatingPoint::new();
+        op.insert("out", 2.5);
+        op.insert("V(/INVERTING)", 0.001);
+        op.insert("vv1#branch", -1e-3);
+        op.insert("@rr1[i]", 25e-6);
+        assert_eq!(op.voltage("OUT"), Some(2.5));
+        assert_eq!(op.voltage("/inverting"), Some(0.001));
+        assert_eq!(op.voltage("GND"), Some(0.0));
+        assert_eq!(op.voltage("IN_1"), None);
+        assert_eq!(op.current("V1"), Some(-1e-3));
+        assert_eq!(op.current("R1"), Some(25e-6));
+        assert_eq!(op.current("C1"), None);
+        assert_eq!(op.names(), vec!["@rr1[i]", "out", "v(/inverting)", "vv1#branch"]);
+    }
+}
@@ -0,0 +1,341 @@
+use crate::Error;
+use super::CircuitItem;
+use super::models::ModelInclude;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+lazy_static! {
+    pub static ref RE_PARAM: regex::Regex =
+        Regex::new(r"([A-Za-z_][A-Za-z0-9_]*)\s*=\s*(\{[^}]*\}|'[^']*'|[^\s=]+)").unwrap();
+}
+
+/// The content of a spice netlist.
+pub(super) struct SpiceNetlist {
+    pub title: Option<String>,
+    pub includes: Vec<ModelInclude>,
+    pub items: Vec<CircuitItem>,
+}
+
+/// Join the continuation lines and remove the comments.
+///
+/// Returns the logical lines with the line number in the file where they start.
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = strip_comment(line);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('*') {
+            continue;
+        }
+        if let Some(continuation) = trimmed.strip_prefix('+') {
+            if let Some(last) = lines.last_mut() {
+                last.1.push(' ');
+                last.1.push_str(continuation.trim());
+                continue;
+            }
+        }
+        lines.push((number + 1, trimmed.to_string()));
+    }
+    lines
+}
+
+/// Remove the inline comments, `;` and `$` when preceded by whitespace.
+///
+/// The comment characters inside of quotes and `{}` expressions are kept.
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut depth = 0;
+    let mut previous: Option<char> = None;
+    for (pos, ch) in line.char_indices() {
+        match (quote, ch) {
+            (Some(q), _) if ch == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(ch),
+            (None, '{') => depth += 1,
+            (None, '}') if depth > 0 => depth -= 1,
+            (None, ';') if depth == 0 => return &line[..pos],
+            (None, '$') if depth == 0 && matches!(previous, Some(' ') | Some('\t')) => return &line[..pos],
+            _ => {}
+        }
+        previous = Some(ch);
+    }
+    line
+}
+
+fn resolve(dir: &Path, name: &str) -> PathBuf {
+    let name = name.trim_matches(|c| c == '"' || c == '\'');
+    let path = Path::new(name);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        dir.join(path)
+    }
+}
+
+fn element(number: usize, line: &str) -> Result<CircuitItem, Error> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let expect = |count: usize| -> Result<(), Error> {
+        if tokens.len() < count {
+            Err(Error::SpiceParseError(number, format!("expected {} fields: {}", count, line)))
+        } else {
+            Ok(())
+        }
+    };
+    let name = tokens[0];
+    let mut chars = name.chars();
+    let first = chars.next();
+    let reference = chars.as_str().to_string();
+    match first.map(|c| c.to_ascii_uppercase()) {
+        Some('R') => {
+            expect(4)?;
+            Ok(CircuitItem::R(reference, tokens[1].to_string(), tokens[2].to_string(), tokens[3..].join(" ")))
+        }
+        Some('C') => {
+            expect(4)?;
+            Ok(CircuitItem::C(reference, tokens[1].to_string(), tokens[2].to_string(), tokens[3..].join(" ")))
+        }
+        Some('D') => {
+            expect(4)?;
+            Ok(CircuitItem::D(name.to_string(), tokens[1].to_string(), tokens[2].to_string(), tokens[3..].join(" ")))
+        }
+        Some('Q') => {
+            expect(5)?;
+            Ok(CircuitItem::Q(
+                reference,
+                tokens[1].to_string(),
+                tokens[2].to_string(),
+                tokens[3].to_string(),
+                tokens[4..].join(" "),
+            ))
+        }
+        Some('V') => {
+            expect(4)?;
+            Ok(CircuitItem::V(reference, tokens[1].to_string(), tokens[2].to_string(), tokens[3..].join(" ")))
+        }
+        Some('X') => {
+            expect(3)?;
+            //the model is the last token before the parameters
+            let model = tokens
+                .iter()
+                .rposition(|t| !t.contains('=') && !t.eq_ignore_ascii_case("params:"))
+                .unwrap();
+            if model < 2 {
+                return Err(Error::SpiceParseError(number, format!("no model found: {}", line)));
+            }
+            Ok(CircuitItem::X(
+                reference,
+                tokens[1..model].iter().map(|t| t.to_string()).collect(),
+                tokens[model..].join(" "),
+            ))
+        }
+        _ => Ok(CircuitItem::Other(line.to_string())),
+    }
+}
+
+fn params(line: &str) -> Vec<CircuitItem> {
+    RE_PARAM
+        .captures_iter(line)
+        .map(|caps| CircuitItem::Param(caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+/// Add the include to the netlist, an include in a subcircuit stays local to the subcircuit.
+fn add_include(items: &mut Vec<CircuitItem>, includes: &mut Vec<ModelInclude>, include: ModelInclude, subckt: Option<&str>) {
+    if subckt.is_some() {
+        items.push(CircuitItem::Other(include.to_string()));
+    } else if !includes.contains(&include) {
+        includes.push(include);
+    }
+}
+
+/// Parse the items until the end of the netlist or the end of the subcircuit.
+fn items(
+    lines: &mut std::vec::IntoIter<(usize, String)>,
+    dir: &Path,
+    includes: &mut Vec<ModelInclude>,
+    subckt: Option<&str>,
+) -> Result<Vec<CircuitItem>, Error> {
+    let mut items = Vec::new();
+    while let Some((number, line)) = lines.next() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let directive = tokens[0].to_lowercase();
+        match directive.as_str() {
+            ".end" => {
+                if let Some(name) = subckt {
+                    return Err(Error::SpiceParseError(number, format!("subcircuit {} not closed", name)));
+                }
+                return Ok(items);
+            }
+            ".ends" => {
+                if subckt.is_none() {
+                    return Err(Error::SpiceParseError(number, String::from(".ends without .subckt")));
+                }
+                return Ok(items);
+            }
+            ".subckt" => {
+                if tokens.len() < 2 {
+                    return Err(Error::SpiceParseError(number, String::from("subcircuit without name")));
+                }
+                let name = tokens[1].to_string();
+                let nodes = tokens[2..].iter().map(|t| t.to_string()).collect();
+                let sub_items = self::items(lines, dir, includes, Some(&name))?;
+                items.push(CircuitItem::Subckt(name, nodes, sub_items));
+            }
+            ".include" | ".inc" => {
+                if tokens.len() < 2 {
+                    return Err(Error::SpiceParseError(number, String::from("include without filename")));
+                }
+                let include = ModelInclude::new(resolve(dir, &tokens[1..].join(" ")), None);
+                add_include(&mut items, includes, include, subckt);
+            }
+            ".lib" if tokens.len() == 3 => {
+                let include = ModelInclude::new(resolve(dir, tokens[1]), Some(tokens[2].to_string()));
+                add_include(&mut items, includes, include, subckt);
+            }
+            ".param" => {
+                items.append(&mut params(&line[tokens[0].len()..]));
+            }
+            ".control" => {
+                let mut control = Vec::new();
+                loop {
+                    match lines.next() {
+                        Some((_, line)) if line.to_lowercase().starts_with(".endc") => break,
+                        Some((_, line)) => control.push(line),
+                        None => {
+                            return Err(Error::SpiceParseError(number, String::from(".control without .endc")));
+                        }
+                    }
+                }
+                items.push(CircuitItem::Control(control));
+            }
+            _ if directive.starts_with('.') => {
+                items.push(CircuitItem::Other(line));
+            }
+            _ => {
+                items.push(element(number, &line)?);
+            }
+        }
+    }
+    if let Some(name) = subckt {
+        return Err(Error::SpiceParseError(0, format!("subcircuit {} not closed", name)));
+    }
+    Ok(items)
+}
+
+/// Parse a spice netlist.
+///
+/// The first line of a spice netlist is always the title. Relative includes
+/// are resolved against `dir`.
+pub(super) fn parse(content: &str, dir: &Path) -> Result<SpiceNetlist, Error> {
+    let mut content_lines = content.lines();
+    let title = content_lines
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty());
+    let rest: Vec<&str> = content_lines.collect();
+    let mut lines = logical_lines(&rest.join("\n"))
+        .into_iter()
+        .map(|(number, line)| (number + 1, line))
+        .collect::<Vec<(usize, String)>>()
+        .into_iter();
+    let mut includes = Vec::new();
+    let items = items(&mut lines, dir, &mut includes, None)?;
+    Ok(SpiceNetlist { title, includes, items })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETLIST: &str = "Test bench for the summing amplifier
+* a comment line
+.include TL072-dual.lib
+.param gain=10 rin = {100k/gain}
+R1 IN_1 /inverting 100k ; inline comment
+C1 /inverting
++ OUT 47n
+XU1 OUT /inverting GND -15V
++ 0 0 0 +15V TL072c
+V1 IN_1 GND DC 1 AC 1 SIN(0 1 1k)
+L1 a b 10m
+.subckt divider in out gnd
+R1 in out 10k
+R2 out gnd 10k
+.ends divider
+.tran 10u 10ms
+.control
+run
+plot v(out)
+.endc
+.end
+";
+
+    #[test]
+    fn parse_netlist() {
+        let netlist = parse(NETLIST, Path::new("samples/files/spice")).unwrap();
+        assert_eq!(netlist.title, Some(String::from("Test bench for the summing amplifier")));
+        assert_eq!(
+            netlist.includes,
+            vec![ModelInclude::new(PathBuf::from("samples/files/spice/TL072-dual.lib"), None)]
+        );
+        assert_eq!(netlist.items.len(), 10);
+        assert!(matches!(&netlist.items[0], CircuitItem::Param(name, value) if name == "gain" && value == "10"));
+        assert!(matches!(&netlist.items[1], CircuitItem::Param(name, value) if name == "rin" && value == "{100k/gain}"));
+        assert!(matches!(&netlist.items[2], CircuitItem::R(r, _, _, value) if r == "1" && value == "100k"));
+        assert!(matches!(&netlist.items[3], CircuitItem::C(_, _, n1, value) if n1 == "OUT" && value == "47n"));
+        assert!(matches!(&netlist.items[4], CircuitItem::X(_, nodes, model) if nodes.len() == 8 && model == "TL072c"));
+        assert!(matches!(&netlist.items[5], CircuitItem::V(_, _, _, value) if value == "DC 1 AC 1 SIN(0 1 1k)"));
+        assert!(matches!(&netlist.items[6], CircuitItem::Other(line) if line == "L1 a b 10m"));
+        assert!(matches!(&netlist.items[7], CircuitItem::Subckt(name, nodes, items) if name == "divider" && nodes.len() == 3 && items.len() == 2));
+        assert!(matches!(&netlist.items[8], CircuitItem::Other(line) if line == ".tran 10u 10ms"));
+        assert!(matches!(&netlist.items[9], CircuitItem::Control(lines) if lines.len() == 2));
+    }
+    #[test]
+    fn inline_comments() {
+        assert_eq!(strip_comment("R1 a b 10k ; comment"), "R1 a b 10k ");
+        assert_eq!(strip_comment("R1 a b 10k $ comment"), "R1 a b 10k ");
+        assert_eq!(strip_comment("R1 a b 10k$"), "R1 a b 10k$");
+        assert_eq!(strip_comment(".param x={a;b} ; comment"), ".param x={a;b} ");
+        assert_eq!(strip_comment(".include \"models;v2.lib\" $ comment"), ".include \"models;v2.lib\" ");
+        assert_eq!(strip_comment("B1 a b V='v(x) $ 2'"), "B1 a b V='v(x) $ 2'");
+    }
+    #[test]
+    fn parse_subckt_params() {
+        let netlist = parse("title\nX1 a b pot params: r=10k w=0.5\n", Path::new(".")).unwrap();
+        assert!(matches!(&netlist.items[0], CircuitItem::X(_, nodes, model) if nodes.len() == 2 && model == "pot params: r=10k w=0.5"));
+    }
+    #[test]
+    fn parse_utf8_names() {
+        let netlist = parse("title\nRµ1 a b 10k\nµ1 a b\n", Path::new(".")).unwrap();
+        assert!(matches!(&netlist.items[0], CircuitItem::R(r, _, _, _) if r == "µ1"));
+        assert!(matches!(&netlist.items[1], CircuitItem::Other(line) if line == "µ1 a b"));
+    }
+    #[test]
+    fn subckt_include() {
+        let netlist = parse(
+            "title\n.subckt amp in out\n.include TL072.lib\nXU1 in out TL072c\n.ends\n",
+            Path::new("samples/files/spice"),
+        )
+        .unwrap();
+        assert!(netlist.includes.is_empty());
+        assert!(matches!(&netlist.items[0], CircuitItem::Subckt(_, _, items)
+            if matches!(&items[0], CircuitItem::Other(line) if line == ".include samples/files/spice/TL072.lib")));
+    }
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            parse("title\n.subckt divider in out\nR1 in out 10k\n", Path::new(".")),
+            Err(Error::SpiceParseError(_, _))
+        ));
+        assert!(matches!(
+            parse("title\nR1 in\n", Path::new(".")),
+            Err(Error::SpiceParseError(2, _))
+        ));
+        assert!(matches!(
+            parse("title\n.control\nrun\n", Path::new(".")),
+            Err(Error::SpiceParseError(2, _))
+        ));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::themes;
+use crate::Error;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+lazy_static! {
+    pub static ref RE_VARIABLE: regex::Regex = Regex::new(r"\$\{([^}]+)\}").unwrap();
+}
+
+/// Environment variable with additional spice model paths.
+pub const SPICE_PATH_ENV: &str = "ELEKTRON_SPICE_PATH";
+/// Environment variable with the plot theme.
+pub const THEME_ENV: &str = "ELEKTRON_THEME";
+/// Name of the elektron project configuration file.
+pub const CONFIG_FILE: &str = "elektron.toml";
+
+/// The project configuration.
+///
+/// The configuration is collected from the `elektron.toml` file in the project
+/// directory and the `elektron` section of the KiCad project file:
+///
+/// ```toml
+/// [spice]
+/// pathlist = ["${KIPRJMOD}/spice", "/usr/share/elektron/spice"]
+///
+/// [plot]
+/// drawing_sheet = "${KIPRJMOD}/company.kicad_wks"
+/// theme = "dark"
+/// force_theme = false
+/// ```
+///
+/// ```json
+/// "elektron": { "spice": { "pathlist": ["${KIPRJMOD}/spice"] } }
+/// ```
+///
+/// The drawing sheet defaults to the one of the KiCad schematic settings.
+/// Paths can contain KiCad style variables, `${KIPRJMOD}` is the project
+/// directory, other variables are taken from the `text_variables` of the
+/// KiCad project and the environment.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    project_dir: PathBuf,
+    variables: HashMap<String, String>,
+    spice_pathlist: Vec<String>,
+    drawing_sheet: Option<String>,
+    theme: Option<String>,
+    force_theme: bool,
+}
+
+impl Config {
+    /// Create an empty configuration for the project directory.
+    pub fn new(project_dir: &Path) -> Self {
+        let mut variables = HashMap::new();
+        variables.insert(
+            String::from("KIPRJMOD"),
+            project_dir.to_str().unwrap_or("").to_string(),
+        );
+        Self {
+            project_dir: project_dir.to_path_buf(),
+            variables,
+            spice_pathlist: Vec::new(),
+            drawing_sheet: None,
+            theme: None,
+            force_theme: false,
+        }
+    }
+
+    /// Load the configuration for the project the schema file belongs to.
+    pub fn load(schema: &str) -> Result<Self, Error> {
+        let schema = Path::new(schema);
+        let project_dir = match schema.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut config = Self::new(&project_dir);
+
+        let project_file = schema.with_extension("kicad_pro");
+        if project_file.is_file() {
+            config.load_project(&project_file)?;
+        }
+        config.load_config_file()?;
+        Ok(config)
+    }
+
+    /// Load the configuration of a project directory without a schema file.
+    pub fn load_dir(project_dir: &Path) -> Result<Self, Error> {
+        let mut config = Self::new(project_dir);
+        config.load_config_file()?;
+        Ok(config)
+    }
+
+    /// The project directory.
+    pub fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+
+    /// Get the value of a text variable.
+    pub fn variable(&self, name: &str) -> Option<&String> {
+        self.variables.get(name)
+    }
+
+    /// All the text variables of the project.
+    pub fn variables(&self) -> &HashMap<String, String> {
+        &self.variables
+    }
+
+    /// Get the spice model pathlist.
+    ///
+    /// The pathlist is in the order of precedence: the `pathlist` argument,
+    /// the paths from the `ELEKTRON_SPICE_PATH` environment variable and
+    /// finally the paths from the project configuration.
+    pub fn spice_pathlist(&self, pathlist: Option<Vec<String>>) -> Vec<String> {
+        self.pathlist(pathlist, std::env::var_os(SPICE_PATH_ENV))
+    }
+
+    /// Get the drawing sheet file, relative paths are in the project directory.
+    pub fn drawing_sheet(&self) -> Option<PathBuf> {
+        let path = PathBuf::from(self.substitute(self.drawing_sheet.as_ref()?));
+        if path.is_relative() {
+            Some(self.project_dir.join(path))
+        } else {
+            Some(path)
+        }
+    }
+
+    /// Get the plot theme, the name of a built-in theme or the theme file.
+    ///
+    /// The `ELEKTRON_THEME` environment variable takes precedence over the
+    /// project configuration, relative theme files are in the project directory.
+    pub fn theme(&self) -> Option<String> {
+        self.select_theme(std::env::var(THEME_ENV).ok())
+    }
+
+    /// Use the values of the theme instead of the ones set in the schematic.
+    pub fn force_theme(&self) -> bool {
+        self.force_theme
+    }
+
+    /// Substitute the `${VAR}` variables in the text.
+    ///
+    /// Project variables are resolved before environment variables,
+    /// unknown variables are not replaced.
+    pub fn substitute(&self, text: &str) -> String {
+        RE_VARIABLE
+            .replace_all(text, |caps: &regex::Captures<'_>| {
+                if let Some(value) = self.variables.get(&caps[1]) {
+                    value.to_string()
+                } else if let Ok(value) = std::env::var(&caps[1]) {
+                    value
+                } else {
+                    caps[0].to_string()
+                }
+            })
+            .to_string()
+    }
+
+    fn pathlist(&self, pathlist: Option<Vec<String>>, env: Option<OsString>) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        let mut push = |path: String| {
+            let path = self.substitute(&path);
+            if !path.is_empty() && !result.contains(&path) {
+                result.push(path);
+            }
+        };
+        if let Some(pathlist) = pathlist {
+            pathlist.into_iter().for_each(&mut push);
+        }
+        if let Some(env) = env {
+            for path in std::env::split_paths(&env) {
+                push(path.to_str().unwrap_or("").to_string());
+            }
+        }
+        self.spice_pathlist.iter().cloned().for_each(&mut push);
+        result
+    }
+
+    fn select_theme(&self, env: Option<String>) -> Option<String> {
+        if let Some(theme) = env.filter(|theme| !theme.is_empty()) {
+            return Some(theme);
+        }
+        let theme = self.substitute(self.theme.as_ref()?);
+        if themes::THEMES.contains(&theme.as_str()) || Path::new(&theme).is_absolute() {
+            Some(theme)
+        } else {
+            Some(self.project_dir.join(theme).to_string_lossy().to_string())
+        }
+    }
+
+    fn load_config_file(&mut self) -> Result<(), Error> {
+        let config_file = self.project_dir.join(CONFIG_FILE);
+        if config_file.is_file() {
+            self.load_toml(&config_file)?;
+        }
+        Ok(())
+    }
+
+    fn load_project(&mut self, filename: &Path) -> Result<(), Error> {
+        let content = fs::read_to_string(filename)?;
+        let project = json::parse(&content)
+            .map_err(|e| Error::ConfigError(format!("{}: {}", filename.display(), e)))?;
+        for (key, value) in project["text_variables"].entries() {
+            if let Some(value) = value.as_str() {
+                self.variables.insert(key.to_string(), value.to_string());
+            }
+        }
+        for path in project["elektron"]["spice"]["pathlist"].members() {
+            if let Some(path) = path.as_str() {
+                self.spice_pathlist.push(path.to_string());
+            }
+        }
+        if let Some(path) = project["schematic"]["page_layout_descr_file"].as_str() {
+            if !path.is_empty() {
+                self.drawing_sheet = Some(path.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn load_toml(&mut self, filename: &Path) -> Result<(), Error> {
+        let content = fs::read_to_string(filename)?;
+        let config: toml::Value = content
+            .parse()
+            .map_err(|e| Error::ConfigError(format!("{}: {}", filename.display(), e)))?;
+        if let Some(pathlist) = config
+            .get("spice")
+            .and_then(|spice| spice.get("pathlist"))
+            .and_then(|pathlist| pathlist.as_array())
+        {
+            //paths from elektron.toml take precedence over the kicad project.
+            let mut paths: Vec<String> = pathlist
+                .iter()
+                .filter_map(|path| path.as_str().map(|p| p.to_string()))
+                .collect();
+            paths.append(&mut self.spice_pathlist);
+            self.spice_pathlist = paths;
+        }
+        if let Some(path) = config
+            .get("plot")
+            .and_then(|plot| plot.get("drawing_sheet"))
+            .and_then(|path| path.as_str())
+        {
+            self.drawing_sheet = Some(path.to_string());
+        }
+        if let Some(theme) = config
+            .get("plot")
+            .and_then(|plot| plot.get("theme"))
+            .and_then(|theme| theme.as_str())
+        {
+            self.theme = Some(theme.to_string());
+        }
+        if let Some(force) = config
+            .get("plot")
+            .and_then(|plot| plot.get("force_theme"))
+            .and_then(|force| force.as_bool())
+        {
+            self.force_theme = force;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_kicad_project() {
+        let config = Config::load("samples/files/summe/summe.kicad_sch").unwrap();
+        assert_eq!(config.project_dir(), Path::new("samples/files/summe"));
+        assert_eq!(config.variable("KIPRJMOD").unwrap(), "samples/files/summe");
+        assert!(config.pathlist(None, None).is_empty());
+        assert_eq!(config.drawing_sheet(), None);
+    }
+    #[test]
+    fn substitute_variables() {
+        let config = Config::new(Path::new("/projects/summe"));
+        assert_eq!(config.substitute("${KIPRJMOD}/spice"), "/projects/summe/spice");
+        assert_eq!(config.substitute("${UNKNOWN_VARIABLE_XYZ}/spice"), "${UNKNOWN_VARIABLE_XYZ}/spice");
+    }
+    #[test]
+    fn pathlist_precedence() {
+        let dir = std::env::temp_dir().join("elektron_test_config");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("project.kicad_pro"),
+            r#"{"text_variables": {"MODELS": "/opt/models"}, "elektron": {"spice": {"pathlist": ["${MODELS}/spice"]}}}"#,
+        ).unwrap();
+        fs::write(
+            dir.join(CONFIG_FILE),
+            "[spice]\npathlist = [\"${KIPRJMOD}/spice\"]\n",
+        ).unwrap();
+        let config = Config::load(dir.join("project.kicad_sch").to_str().unwrap()).unwrap();
+        let pathlist = config.pathlist(
+            Some(vec![String::from("/args/spice")]),
+            Some(OsString::from("/env/spice")),
+        );
+        assert_eq!(
+            pathlist,
+            vec![
+                String::from("/args/spice"),
+                String::from("/env/spice"),
+                format!("{}/spice", dir.to_str().unwrap()),
+                String::from("/opt/models/spice"),
+            ]
+        );
+    }
+    #[test]
+    fn load_dir() {
+        let dir = std::env::temp_dir().join("elektron_test_load_dir");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(CONFIG_FILE), "[spice]\npathlist = [\"${KIPRJMOD}/spice\"]\n").unwrap();
+        let config = Config::load_dir(&dir).unwrap();
+        assert_eq!(config.pathlist(None, None), vec![format!("{}/spice", dir.to_str().unwrap())]);
+    }
+    #[test]
+    fn drawing_sheet() {
+        let dir = std::env::temp_dir().join("elektron_test_drawing_sheet");
+        fs::create_dir_all(&dir).unwrap();
+        let _ = fs::remove_file(dir.join(CONFIG_FILE));
+        fs::write(
+            dir.join("project.kicad_pro"),
+            r#"{"schematic": {"page_layout_descr_file": "kicad.kicad_wks"}}"#,
+        ).unwrap();
+        let schema = dir.join("project.kicad_sch");
+        let config = Config::load(schema.to_str().unwrap()).unwrap();
+        assert_eq!(config.drawing_sheet(), Some(dir.join("kicad.kicad_wks")));
+
+        fs::write(dir.join(CONFIG_FILE), "[plot]\ndrawing_sheet = \"${KIPRJMOD}/company.kicad_wks\"\n").unwrap();
+        let config = Config::load(schema.to_str().unwrap()).unwrap();
+        assert_eq!(config.drawing_sheet(), Some(dir.join("company.kicad_wks")));
+    }
+    #[test]
+    fn theme() {
+        let dir = std::env::temp_dir().join("elektron_test_theme");
+        fs::create_dir_all(&dir).unwrap();
+        let schema = dir.join("project.kicad_sch");
+        let _ = fs::remove_file(dir.join(CONFIG_FILE));
+        let config = Config::load(schema.to_str().unwrap()).unwrap();
+        assert_eq!(config.select_theme(None), None);
+        assert!(!config.force_theme());
+        assert_eq!(config.select_theme(Some(String::from("monochrome"))), Some(String::from("monochrome")));
+
+        fs::write(dir.join(CONFIG_FILE), "[plot]\ntheme = \"dark\"\nforce_theme = true\n").unwrap();
+        let config = Config::load(schema.to_str().unwrap()).unwrap();
+        assert_eq!(config.select_theme(None), Some(String::from("dark")));
+        assert!(config.force_theme());
+        assert_eq!(config.select_theme(Some(String::new())), Some(String::from("dark")));
+
+        fs::write(dir.join(CONFIG_FILE), "[plot]\ntheme = \"colors/blueprint.json\"\n").unwrap();
+        let config = Config::load(schema.to_str().unwrap()).unwrap();
+        let path = dir.join("colors/blueprint.json").to_str().unwrap().to_string();
+        assert_eq!(config.select_theme(None), Some(path));
+    }
+}
@@ -0,0 +1,628 @@
+//! Semantic comparison of two schematics.
+//!
+//! The symbols are matched by the uuid, symbols without a matching uuid are
+//! matched by the reference and unit. The connectivity is compared with the
+//! pins that share a net, so renumbered unnamed nets are not reported.
+use crate::plotter::{Backend, Circle, DisplayList, PlotItem, Plotter, Rectangle};
+use crate::netlist::Netlist;
+use crate::plot::{plot_items, unit_outline};
+use crate::schema::symbol::SymbolInstance;
+use crate::schema::Schematic;
+use crate::sexp::parser::SexpParser;
+use crate::sexp::{Color, LineType, Sexp};
+use crate::themes::Style;
+use crate::Error;
+
+use ndarray::{arr2, Array1, Array2};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::process::Command;
+
+/// The position, angle and mirror of a symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub at: Array1<f64>,
+    pub angle: f64,
+    pub mirror: Option<String>,
+}
+
+impl From<&SymbolInstance> for Placement {
+    fn from(symbol: &SymbolInstance) -> Self {
+        Placement {
+            at: symbol.at.clone(),
+            angle: symbol.angle,
+            mirror: symbol.mirror.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}) {}°", self.at[0], self.at[1], self.angle)?;
+        if let Some(mirror) = &self.mirror {
+            write!(f, " mirror {}", mirror)?;
+        }
+        Ok(())
+    }
+}
+
+/// A difference between the old and the new schematic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    SymbolAdded { reference: String, unit: usize, lib_id: String, uuid: Option<String> },
+    SymbolRemoved { reference: String, unit: usize, lib_id: String, uuid: Option<String> },
+    SymbolMoved { reference: String, unit: usize, uuid: Option<String>, from: Placement, to: Placement },
+    LibIdChanged { reference: String, old: String, new: String },
+    PropertyChanged { reference: String, key: String, old: Option<String>, new: Option<String> },
+    /// The pin is connected to other pins or the net was renamed.
+    ConnectionChanged {
+        reference: String,
+        pin: String,
+        old_net: Option<String>,
+        new_net: Option<String>,
+        old_pins: Vec<String>,
+        new_pins: Vec<String>,
+        at: Array1<f64>,
+    },
+    LibrarySymbolAdded(String),
+    LibrarySymbolRemoved(String),
+    LibrarySymbolChanged(String),
+}
+
+impl Change {
+    /// The change type as it is written to the JSON output.
+    pub fn kind(&self) -> &str {
+        match self {
+            Change::SymbolAdded { .. } => "symbol_added",
+            Change::SymbolRemoved { .. } => "symbol_removed",
+            Change::SymbolMoved { .. } => "symbol_moved",
+            Change::LibIdChanged { .. } => "lib_id_changed",
+            Change::PropertyChanged { .. } => "property_changed",
+            Change::ConnectionChanged { .. } => "connection_changed",
+            Change::LibrarySymbolAdded(_) => "library_symbol_added",
+            Change::LibrarySymbolRemoved(_) => "library_symbol_removed",
+            Change::LibrarySymbolChanged(_) => "library_symbol_changed",
+        }
+    }
+
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut data = match self {
+            Change::SymbolAdded { reference, unit, lib_id, uuid }
+            | Change::SymbolRemoved { reference, unit, lib_id, uuid } => json::object! {
+                reference: reference.clone(),
+                unit: *unit,
+                lib_id: lib_id.clone(),
+                uuid: uuid.clone(),
+            },
+            Change::SymbolMoved { reference, unit, uuid, from, to } => json::object! {
+                reference: reference.clone(),
+                unit: *unit,
+                uuid: uuid.clone(),
+                from: placement(from),
+                to: placement(to),
+            },
+            Change::LibIdChanged { reference, old, new } => json::object! {
+                reference: reference.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            },
+            Change::PropertyChanged { reference, key, old, new } => json::object! {
+                reference: reference.clone(),
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            },
+            Change::ConnectionChanged { reference, pin, old_net, new_net, old_pins, new_pins, at } => json::object! {
+                reference: reference.clone(),
+                pin: pin.clone(),
+                old_net: old_net.clone(),
+                new_net: new_net.clone(),
+                old_pins: old_pins.clone(),
+                new_pins: new_pins.clone(),
+                at: at.to_vec(),
+            },
+            Change::LibrarySymbolAdded(name)
+            | Change::LibrarySymbolRemoved(name)
+            | Change::LibrarySymbolChanged(name) => json::object! {
+                name: name.clone(),
+            },
+        };
+        data["type"] = self.kind().into();
+        data
+    }
+}
+
+fn placement(placement: &Placement) -> json::JsonValue {
+    json::object! {
+        at: placement.at.to_vec(),
+        angle: placement.angle,
+        mirror: placement.mirror.clone(),
+    }
+}
+
+/// Write the reference with the unit, the unit is omitted for single unit symbols.
+fn unit_name(reference: &str, unit: usize) -> String {
+    if unit > 1 {
+        format!("{} unit {}", reference, unit)
+    } else {
+        reference.to_string()
+    }
+}
+
+fn option(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("<none>")
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::SymbolAdded { reference, unit, lib_id, .. } => {
+                write!(f, "+ symbol {} ({})", unit_name(reference, *unit), lib_id)
+            }
+            Change::SymbolRemoved { reference, unit, lib_id, .. } => {
+                write!(f, "- symbol {} ({})", unit_name(reference, *unit), lib_id)
+            }
+            Change::SymbolMoved { reference, unit, from, to, .. } => {
+                write!(f, "~ symbol {} moved from {} to {}", unit_name(reference, *unit), from, to)
+            }
+            Change::LibIdChanged { reference, old, new } => {
+                write!(f, "~ symbol {} lib_id: {} -> {}", reference, old, new)
+            }
+            Change::PropertyChanged { reference, key, old, new } => {
+                write!(f, "~ symbol {} {}: {} -> {}", reference, key, option(old), option(new))
+            }
+            Change::ConnectionChanged { reference, pin, old_net, new_net, old_pins, new_pins, .. } => {
+                write!(f, "~ pin {}:{}", reference, pin)?;
+                if old_net != new_net {
+                    write!(f, " net: {} -> {}", option(old_net), option(new_net))?;
+                }
+                if old_pins != new_pins {
+                    write!(f, " connected to: [{}] -> [{}]", old_pins.join(", "), new_pins.join(", "))?;
+                }
+                Ok(())
+            }
+            Change::LibrarySymbolAdded(name) => write!(f, "+ library symbol {}", name),
+            Change::LibrarySymbolRemoved(name) => write!(f, "- library symbol {}", name),
+            Change::LibrarySymbolChanged(name) => write!(f, "~ library symbol {}", name),
+        }
+    }
+}
+
+/// The net of a pin and the other pins in the net.
+struct Connection {
+    net: Option<String>,
+    pins: Vec<String>,
+    at: Array1<f64>,
+}
+
+/// Get the connection of every symbol pin, the key is `reference:pin`.
+fn connections(parser: &SexpParser) -> Result<BTreeMap<String, (String, String, Connection)>, Error> {
+    let netlist = Netlist::from(parser)?;
+    let pins = netlist.pin_nets()?;
+    //pins with the same net name are connected, unnamed nets are identified by the index.
+    let key = |net: &Option<String>, index: usize| match net {
+        Some(net) => net.clone(),
+        None => format!("#{}", index),
+    };
+    let mut nets: HashMap<String, Vec<String>> = HashMap::new();
+    for pin in &pins {
+        nets.entry(key(&pin.net, pin.index))
+            .or_default()
+            .push(format!("{}:{}", pin.reference, pin.pin));
+    }
+    let mut result = BTreeMap::new();
+    for pin in pins {
+        let name = format!("{}:{}", pin.reference, pin.pin);
+        let mut connected: Vec<String> = nets[&key(&pin.net, pin.index)]
+            .iter()
+            .filter(|other| **other != name)
+            .cloned()
+            .collect();
+        connected.sort();
+        connected.dedup();
+        result.insert(
+            name,
+            (pin.reference, pin.pin, Connection { net: pin.net, pins: connected, at: pin.at }),
+        );
+    }
+    Ok(result)
+}
+
+/// The semantic differences between two schematics.
+pub struct SchematicDiff {
+    pub changes: Vec<Change>,
+    old: Schematic,
+    new: Schematic,
+}
+
+impl SchematicDiff {
+    /// Compare the old with the new schematic.
+    pub fn new(old: &SexpParser, new: &SexpParser) -> Result<Self, Error> {
+        let old_schema = Schematic::try_from(old.root())?;
+        let new_schema = Schematic::try_from(new.root())?;
+        let mut changes = Vec::new();
+
+        //library symbols
+        for symbol in &new_schema.lib_symbols {
+            match old_schema.lib_symbol(&symbol.name) {
+                None => changes.push(Change::LibrarySymbolAdded(symbol.name.clone())),
+                Some(old_symbol) if old_symbol != symbol => {
+                    changes.push(Change::LibrarySymbolChanged(symbol.name.clone()))
+                }
+                _ => {}
+            }
+        }
+        for symbol in &old_schema.lib_symbols {
+            if new_schema.lib_symbol(&symbol.name).is_none() {
+                changes.push(Change::LibrarySymbolRemoved(symbol.name.clone()));
+            }
+        }
+
+        //symbols
+        let old_symbols: Vec<&SymbolInstance> = old_schema.symbols().collect();
+        let mut matched = vec![false; old_symbols.len()];
+        let mut compared: HashSet<String> = HashSet::new();
+        for symbol in new_schema.symbols() {
+            let reference = symbol.reference().unwrap_or_default().to_string();
+            let index = symbol
+                .uuid
+                .as_ref()
+                .and_then(|uuid| {
+                    (0..old_symbols.len()).find(|i| !matched[*i] && old_symbols[*i].uuid.as_ref() == Some(uuid))
+                })
+                .or_else(|| {
+                    (0..old_symbols.len()).find(|i| {
+                        !matched[*i]
+                            && old_symbols[*i].reference() == symbol.reference()
+                            && old_symbols[*i].unit == symbol.unit
+                    })
+                });
+            let old_symbol = match index {
+                Some(index) => {
+                    matched[index] = true;
+                    old_symbols[index]
+                }
+                None => {
+                    changes.push(Change::SymbolAdded {
+                        reference,
+                        unit: symbol.unit,
+                        lib_id: symbol.lib_id.clone(),
+                        uuid: symbol.uuid.clone(),
+                    });
+                    continue;
+                }
+            };
+            let (from, to) = (Placement::from(old_symbol), Placement::from(symbol));
+            if from != to {
+                changes.push(Change::SymbolMoved {
+                    reference: reference.clone(),
+                    unit: symbol.unit,
+                    uuid: symbol.uuid.clone(),
+                    from,
+                    to,
+                });
+            }
+            //the properties are the same for all units
+            if !compared.insert(reference.clone()) {
+                continue;
+            }
+            if old_symbol.lib_id != symbol.lib_id {
+                changes.push(Change::LibIdChanged {
+                    reference: reference.clone(),
+                    old: old_symbol.lib_id.clone(),
+                    new: symbol.lib_id.clone(),
+                });
+            }
+            let mut keys: Vec<&str> = old_symbol.properties.iter().map(|p| p.key.as_str()).collect();
+            for property in &symbol.properties {
+                if !keys.contains(&property.key.as_str()) {
+                    keys.push(&property.key);
+                }
+            }
+            for key in keys {
+                let (old_value, new_value) = (old_symbol.property(key), symbol.property(key));
+                if old_value != new_value {
+                    changes.push(Change::PropertyChanged {
+                        reference: reference.clone(),
+                        key: key.to_string(),
+                        old: old_value.map(String::from),
+                        new: new_value.map(String::from),
+                    });
+                }
+            }
+        }
+        for (index, symbol) in old_symbols.iter().enumerate() {
+            if !matched[index] {
+                changes.push(Change::SymbolRemoved {
+                    reference: symbol.reference().unwrap_or_default().to_string(),
+                    unit: symbol.unit,
+                    lib_id: symbol.lib_id.clone(),
+                    uuid: symbol.uuid.clone(),
+                });
+            }
+        }
+
+        //connectivity, pins of added or removed symbols are already reported.
+        let old_connections = connections(old)?;
+        for (name, (reference, pin, connection)) in connections(new)? {
+            if let Some((_, _, old_connection)) = old_connections.get(&name) {
+                let renamed = old_connection.net.is_some() && connection.net.is_some() && old_connection.net != connection.net;
+                if renamed || old_connection.pins != connection.pins {
+                    changes.push(Change::ConnectionChanged {
+                        reference,
+                        pin,
+                        old_net: old_connection.net.clone(),
+                        new_net: connection.net,
+                        old_pins: old_connection.pins.clone(),
+                        new_pins: connection.pins,
+                        at: connection.at,
+                    });
+                }
+            }
+        }
+
+        Ok(SchematicDiff { changes, old: old_schema, new: new_schema })
+    }
+
+    /// There are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut data = json::JsonValue::new_array();
+        for change in &self.changes {
+            data.push(change.to_json()).unwrap();
+        }
+        data
+    }
+
+    /// Get the areas to highlight and the color for each change.
+    ///
+    /// Symbols are marked in the new schematic, removed symbols at the
+    /// position in the old schematic.
+    pub fn highlights(&self) -> Result<Vec<(Array2<f64>, Color)>, Error> {
+        let added = Color { r: 0.0, g: 0.6, b: 0.0, a: 1.0 };
+        let removed = Color { r: 0.8, g: 0.0, b: 0.0, a: 1.0 };
+        let changed = Color { r: 1.0, g: 0.5, b: 0.0, a: 1.0 };
+        let connection = Color { r: 0.0, g: 0.3, b: 1.0, a: 1.0 };
+
+        let mut highlights = Vec::new();
+        for change in &self.changes {
+            match change {
+                Change::SymbolAdded { reference, unit, uuid, .. } => {
+                    for symbol in find(&self.new, reference, *unit, uuid) {
+                        highlights.push((outline(&self.new, symbol)?, added.clone()));
+                    }
+                }
+                Change::SymbolRemoved { reference, unit, uuid, .. } => {
+                    for symbol in find(&self.old, reference, *unit, uuid) {
+                        highlights.push((outline(&self.old, symbol)?, removed.clone()));
+                    }
+                }
+                Change::SymbolMoved { reference, unit, uuid, .. } => {
+                    for symbol in find(&self.new, reference, *unit, uuid) {
+                        highlights.push((outline(&self.new, symbol)?, changed.clone()));
+                    }
+                }
+                Change::LibIdChanged { reference, .. } | Change::PropertyChanged { reference, .. } => {
+                    for symbol in self.new.symbol(reference) {
+                        highlights.push((outline(&self.new, symbol)?, changed.clone()));
+                    }
+                }
+                Change::ConnectionChanged { at, .. } => {
+                    highlights.push((arr2(&[[at[0], at[1]], [at[0], at[1]]]), connection.clone()));
+                }
+                Change::LibrarySymbolChanged(name) => {
+                    for symbol in self.new.symbols().filter(|symbol| &symbol.lib_id == name) {
+                        highlights.push((outline(&self.new, symbol)?, changed.clone()));
+                    }
+                }
+                Change::LibrarySymbolAdded(_) | Change::LibrarySymbolRemoved(_) => {}
+            }
+        }
+        Ok(highlights)
+    }
+
+    /// Plot the new schematic and highlight the changes.
+    ///
+    /// Changed pins are marked with a circle, symbols with a rectangle.
+    pub fn plot(
+        &self,
+        backend: &dyn Backend,
+        new: &SexpParser,
+        filename: &str,
+        border: bool,
+        style: Style,
+    ) -> Result<(), Error> {
+        let mut display_list = DisplayList::new();
+        display_list.set_net_names(backend.net_names());
+        display_list.background(style.background());
+        plot_items(&mut display_list, new, border, &style, None)?;
+        display_list.start_group(&[(String::from("type"), String::from("highlight"))]);
+        for (area, color) in self.highlights()? {
+            if area.row(0) == area.row(1) {
+                display_list.push(PlotItem::CircleItem(Circle::new(
+                    area.row(0).to_owned(),
+                    1.0,
+                    0.35,
+                    LineType::Solid,
+                    color,
+                    None,
+                )));
+            } else {
+                let fill = Color { a: 0.15, ..color.clone() };
+                display_list.push(PlotItem::RectangleItem(Rectangle::new(
+                    area,
+                    color,
+                    0.35,
+                    LineType::Dash,
+                    Some(fill),
+                )));
+            }
+        }
+        display_list.end_group();
+        backend.write(&display_list, Box::new(File::create(filename)?), border, 1.0)
+    }
+}
+
+impl fmt::Display for SchematicDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the symbol units of a change, by the uuid or by the reference and unit.
+fn find<'a>(schema: &'a Schematic, reference: &str, unit: usize, uuid: &Option<String>) -> Vec<&'a SymbolInstance> {
+    schema
+        .symbols()
+        .filter(|symbol| match uuid {
+            Some(uuid) => symbol.uuid.as_ref() == Some(uuid),
+            None => symbol.reference() == Some(reference) && symbol.unit == unit,
+        })
+        .collect()
+}
+
+/// The outline of a placed symbol with a margin, the position when the library symbol has no graphics.
+fn outline(schema: &Schematic, symbol: &SymbolInstance) -> Result<Array2<f64>, Error> {
+    let margin = 1.27;
+    let bounds = match schema.lib_symbol(&symbol.lib_id) {
+        Some(library) => unit_outline(&Sexp::from(symbol), &Sexp::from(library))?,
+        None => arr2(&[[symbol.at[0], symbol.at[1]], [symbol.at[0], symbol.at[1]]]),
+    };
+    let margin = if bounds.row(0) == bounds.row(1) { 2.0 * margin } else { margin };
+    Ok(arr2(&[
+        [bounds[[0, 0]] - margin, bounds[[0, 1]] - margin],
+        [bounds[[1, 0]] + margin, bounds[[1, 1]] + margin],
+    ]))
+}
+
+/// Load a schematic from the file or from a git revision of the file.
+pub fn load(filename: &str, revision: Option<&str>) -> Result<SexpParser, Error> {
+    let revision = match revision {
+        Some(revision) => revision,
+        None => return SexpParser::load(filename),
+    };
+    let path = Path::new(filename);
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::IoError(format!("not a file: {}", filename)))?
+        .to_string_lossy();
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .arg("show")
+        .arg(format!("{}:./{}", revision, name))
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::IoError(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    SexpParser::from_reader(output.stdout.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr1;
+
+    const SUMME: &str = "samples/files/summe/summe.kicad_sch";
+
+    fn parse(schema: &Schematic) -> SexpParser {
+        let mut content = Vec::new();
+        schema.save(&mut content).unwrap();
+        String::from_utf8(content).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn same_schematic() {
+        let old = SexpParser::load(SUMME).unwrap();
+        let new = SexpParser::load(SUMME).unwrap();
+        let diff = SchematicDiff::new(&old, &new).unwrap();
+        assert!(diff.is_empty(), "{}", diff);
+        assert_eq!(diff.to_string(), "");
+    }
+
+    #[test]
+    fn symbol_changes() {
+        let old = SexpParser::load(SUMME).unwrap();
+        let mut schema = Schematic::try_from(old.root()).unwrap();
+        schema.symbol_mut("R5")[0].translate(&arr1(&[2.54, 0.0]));
+        schema.symbol_mut("R3")[0].set_property("Value", "22k");
+        assert_eq!(schema.remove_symbol("R4"), 1);
+        let new = parse(&schema);
+
+        let diff = SchematicDiff::new(&old, &new).unwrap();
+        let text = diff.to_string();
+        assert!(diff.changes.iter().any(|change| matches!(change,
+            Change::SymbolMoved { reference, from, to, .. } if reference == "R5" && (to.at[0] - from.at[0] - 2.54).abs() < 1e-9)), "{}", text);
+        assert!(diff.changes.contains(&Change::PropertyChanged {
+            reference: String::from("R3"),
+            key: String::from("Value"),
+            old: Some(String::from("100k")),
+            new: Some(String::from("22k")),
+        }), "{}", text);
+        assert!(diff.changes.iter().any(|change| matches!(change,
+            Change::SymbolRemoved { reference, .. } if reference == "R4")), "{}", text);
+        assert!(!diff.changes.iter().any(|change| matches!(change, Change::SymbolAdded { .. })), "{}", text);
+        assert!(text.contains("~ symbol R3 Value: 100k -> 22k"), "{}", text);
+        assert!(text.contains("- symbol R4 (Device:R)"), "{}", text);
+
+        //the moved resistor is disconnected
+        assert!(diff.changes.iter().any(|change| matches!(change,
+            Change::ConnectionChanged { reference, new_pins, .. } if reference == "R5" && new_pins.is_empty())), "{}", text);
+
+        let json = diff.to_json();
+        assert_eq!(json.len(), diff.changes.len());
+        assert!(json.members().any(|change| change["type"] == "symbol_removed" && change["reference"] == "R4"));
+
+        let highlights = diff.highlights().unwrap();
+        assert!(!highlights.is_empty());
+        assert!(highlights.iter().all(|(area, _)| area[[0, 0]] <= area[[1, 0]] && area[[0, 1]] <= area[[1, 1]]));
+    }
+
+    #[test]
+    fn symbol_outline() {
+        let doc: SexpParser = r#"(kicad_sch (version 20211123) (generator eeschema)
+  (lib_symbols
+    (symbol "Test:Ring" (in_bom yes) (on_board yes)
+      (symbol "Ring_0_1" (circle (center 0 0) (radius 2) (stroke (width 0)) (fill (type none))))
+      (symbol "Ring_2_1" (polyline (pts (xy 0 0) (xy 20 0)) (stroke (width 0)) (fill (type none))))
+    )
+  )
+  (symbol (lib_id "Test:Ring") (at 10 10 0) (unit 1) (in_bom yes) (on_board yes)
+    (uuid 5b1d1ea4-7a36-4d22-a1f1-4c9e2a3b8c01)
+    (property "Reference" "X1" (id 0) (at 10 5 0) (effects (font (size 1.27 1.27))))
+  )
+)"#
+        .parse()
+        .unwrap();
+        let schema = Schematic::try_from(doc.root()).unwrap();
+        let symbol = &schema.symbol("X1")[0];
+        assert_eq!(outline(&schema, symbol).unwrap(), arr2(&[[6.73, 6.73], [13.27, 13.27]]));
+    }
+
+    #[test]
+    fn renamed_net() {
+        let old = SexpParser::load(SUMME).unwrap();
+        let mut schema = Schematic::try_from(old.root()).unwrap();
+        assert!(schema.rename_net("IN_1", "IN_A") > 0);
+        let new = parse(&schema);
+
+        let diff = SchematicDiff::new(&old, &new).unwrap();
+        assert!(!diff.is_empty());
+        assert!(diff.changes.iter().all(|change| matches!(change,
+            Change::ConnectionChanged { old_net, new_net, old_pins, new_pins, .. }
+                if old_net.as_deref() == Some("IN_1") && new_net.as_deref() == Some("IN_A") && old_pins == new_pins)),
+            "{}", diff);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::Error;
 use crate::circuit::Circuit;
+use crate::config::Config;
 use crate::libraries::Libraries;
 use crate::shape::{Shape, Transform, Bounds};
 use crate::sexp::{
@@ -12,6 +13,7 @@ use pyo3::prelude::*;
 use std::collections::HashMap;
 use std::io::Write;
 use std::fs::File;
+use std::path::Path;
 
 use ndarray::{arr1, arr2, Array1};
 use uuid::Uuid;
@@ -29,6 +31,7 @@ pub struct Draw {
     sheet_instance: Vec<Sexp>,
     symbol_instance: Vec<Sexp>,
     libs: Libraries,
+    filename: Option<String>,
 }
 
 #[pymethods]
@@ -49,31 +52,29 @@ impl Draw {
             sheet_instance: vec![sheet!("/", "1")],
             symbol_instance: Vec::<Sexp>::new(),
             libs: Libraries::new(library_path),
+            filename: None,
         }
     }
 
-    fn pin_pos(&mut self, reference: &str, pin: usize) -> Vec<f64> {
-        let symbol = self.get_symbol(reference, 1).unwrap();
-        let lib_name: String = get!(&symbol, "lib_id", 0);
-        let library = self.get_library(&lib_name).unwrap();
-        let libs: Vec<&Sexp> =  library.get("symbol").unwrap();
+    fn pin_pos(&mut self, reference: &str, pin: usize) -> Result<Vec<f64>, Error> {
+        let symbol = self.get_symbol(reference, 1)?;
+        let lib_name: String = get!(&symbol, "lib_id", 0)?;
+        let library = self.get_library(&lib_name)?;
+        let libs: Vec<&Sexp> = library.get("symbol")?;
         for _unit in libs {
-            let number: usize = get_unit(_unit).unwrap(); //get!(_pin, "unit", 0);
-            let sym_pins: Vec<&Sexp> = _unit.get("pin").unwrap();
-            if let p = sym_pins {
-                for _pin in p {
-                    let number_node: Vec<&Sexp> = _pin.get("number").unwrap();
-                    let _pin_number: usize = number_node.get(0).unwrap().get(0).unwrap();
-                    if _pin_number == pin {
-                        let pin_pos: Array1<f64> = get!(_pin, "at").unwrap();
-                        let _lib_instance = self.get_symbol(reference, number).unwrap();
-                        let at: Array1<f64> = Shape::transform(&_lib_instance, &pin_pos);
-                        return vec![at[0], at[1]];
-                    }
+            let number: usize = get_unit(_unit)?;
+            let sym_pins: Vec<&Sexp> = _unit.get("pin")?;
+            for _pin in sym_pins {
+                let _pin_number: usize = get!(_pin, "number", 0)?;
+                if _pin_number == pin {
+                    let pin_pos: Array1<f64> = get!(_pin, "at")?;
+                    let _lib_instance = self.get_symbol(reference, number)?;
+                    let at: Array1<f64> = Shape::transform(&_lib_instance, &pin_pos)?;
+                    return Ok(vec![at[0], at[1]]);
                 }
             }
         }
-        panic!("pin not found {}:{}", reference, pin); //TODO return error
+        Err(Error::PinNotFound(pin))
     }
     pub fn wire(&mut self, pts: Vec<f64>, end: Vec<f64>) {
         self.elements
@@ -98,13 +99,13 @@ impl Draw {
         mirror: String,
         end_pos: Option<f64>,
         properties: HashMap<String, String>,
-    ) {
-        let lib_symbol = self.get_library(library).unwrap();
+    ) -> Result<(), Error> {
+        let lib_symbol = self.get_library(library)?;
         let uuid = Uuid::new_v4();
 
         // println!("load pin for {}:{:?}", reference, &lib_symbol);
-        let sym_pin = get_pin(&lib_symbol, pin).unwrap();
-        let pin_pos: Array1<f64> = get!(sym_pin, "at").unwrap();
+        let sym_pin = get_pin(&lib_symbol, pin)?;
+        let pin_pos: Array1<f64> = get!(sym_pin, "at")?;
         // transform pin pos
         let theta = -angle.to_radians();
         let rot = arr2(&[[theta.cos(), -theta.sin()], [theta.sin(), theta.cos()]]);
@@ -115,12 +116,12 @@ impl Draw {
         verts = arr1(&[pos[0], pos[1]]) - &verts;
 
         if let Some(end_pos) = end_pos {
-            let pins = get_pins(&lib_symbol, Option::from(unit)).unwrap();
+            let pins = get_pins(&lib_symbol, Option::from(unit))?;
             if pins.len() == 2 {
                 for p in pins {
-                    let pin_number: usize = get!(p, "number", 0);
+                    let pin_number: usize = get!(p, "number", 0)?;
                     if pin_number != unit {
-                        let other_pos: Array1<f64> = get!(p, "at").unwrap();
+                        let other_pos: Array1<f64> = get!(p, "at")?;
                         let mut verts2: Array1<f64> = other_pos.dot(&rot);
                         verts2 = verts2.mapv_into(|v| format!("{:.2}", v).parse::<f64>().unwrap());
                         //TODO verts = verts.dot(sexp::MIRROR.get(mirror.as_str()).unwrap());
@@ -145,10 +146,10 @@ impl Draw {
 
         //copy the properties from the library to the symbol
         let mut footprint: Option<String> = None;
-        let props: Vec<&Sexp> = lib_symbol.get("property").unwrap();
+        let props: Vec<&Sexp> = lib_symbol.get("property")?;
         if let Sexp::Node(_, ref mut values) = symbol {
             for prop in props {
-                let name: String = get!(prop, 0).unwrap();
+                let name: String = get!(prop, 0)?;
                 //skip properties with ki_
                 if name.starts_with("ki_") {
                     break;
@@ -175,10 +176,12 @@ impl Draw {
         self.elements.push(symbol);
         self.symbol_instance
             .push(symbol_instance!(uuid, reference, value, unit, footprint));
+        Ok(())
     }
 
     pub fn write(&mut self, filename: Option<&str>) -> Result<(), Error> {
         let mut out: Box<dyn Write> = if let Some(filename) = filename {
+            self.filename = Some(filename.to_string());
             Box::new(File::create(filename).unwrap())
         } else {
             Box::new(std::io::stdout())
@@ -202,44 +205,43 @@ impl Draw {
             }
         }
     } */
-    pub fn circuit(&mut self) -> Circuit {
-        let mut circuit: Circuit = Circuit::new(vec!["/home/etienne/elektron/samples/files/spice".to_string()]); //TODO
-        match self._write() {
-            Ok(doc) => {
-                let mut netlist = Box::new(Netlist::from(&doc));
-                netlist.dump(&mut circuit).unwrap();
-                return circuit;
-            }
-            Err(err) => {
-                println!("{:?}", err);
-            }
-        }
-        // Err(Error::ParseError)
-        panic!();
+    /// Create the spice circuit.
+    ///
+    /// The configuration is loaded from the directory of the schematic after it
+    /// was written, otherwise from the current directory.
+    pub fn circuit(&mut self, spice_pathlist: Option<Vec<String>>) -> Result<Circuit, Error> {
+        let config = match &self.filename {
+            Some(filename) => Config::load(filename)?,
+            None => Config::load_dir(Path::new("."))?,
+        };
+        let mut circuit: Circuit = Circuit::new(config.spice_pathlist(spice_pathlist));
+        let doc = self._write()?;
+        let mut netlist = Box::new(Netlist::from(&doc)?);
+        netlist.dump(&mut circuit)?;
+        Ok(circuit)
     }
 }
 
 impl Draw {
 
     //check if this schema has a library symbol.
-    fn has_library(&mut self, name: &str) -> bool {
+    fn has_library(&mut self, name: &str) -> Result<bool, Error> {
         for l in &self.libraries {
-            let lib_name: String = get!(l, 0).unwrap();
+            let lib_name: String = get!(l, 0)?;
             if name == lib_name {
-                return true;
+                return Ok(true);
             }
         }
-        false
+        Ok(false)
     }
 
     /// return a library symbol when it exists or load it from the libraries.
     fn get_library(&mut self, name: &str) -> Result<Sexp, Error> {
-        if !self.has_library(name) { //load the library symbol
-            let mut lib_symbol = self.libs.get(name).unwrap();
+        if !self.has_library(name)? { //load the library symbol
+            let mut lib_symbol = self.libs.get(name)?;
             //update the name with full path XX:yy
             if let Sexp::Node(_, ref mut values) = lib_symbol {
-                let sym_name: &mut Sexp = values.get_mut(0).unwrap();
-                if let Sexp::Text(ref mut value) = sym_name {
+                if let Some(Sexp::Text(ref mut value)) = values.get_mut(0) {
                     *value = name.to_string();
                 } else {
                     println!("symbol value is not a value node");
@@ -252,7 +254,7 @@ impl Draw {
 
         } else { //get the existing library symbol
             for l in &self.libraries {
-                let lib_name: String = get!(l, 0).unwrap();
+                let lib_name: String = get!(l, 0)?;
                 if name == lib_name {
                     return Ok(l.clone());
                 }
@@ -264,13 +266,12 @@ impl Draw {
     /// get the symbol by reference and unit from this schema.
     fn get_symbol(&mut self, reference: &str, unit: usize) -> Result<Sexp, Error> {
         for l in &self.elements {
-            if let Sexp::Node(name, values) = l {
+            if let Sexp::Node(name, _) = l {
             if name == "symbol" {
-                if let _ref = get_property(l, "Reference").unwrap() {
-                    let _unit = get_unit(&l).unwrap();
-                    if reference == _ref && unit == _unit {
-                        return Ok(l.clone());
-                    }
+                let _ref = get_property(l, "Reference")?;
+                let _unit = get_unit(l)?;
+                if reference == _ref && unit == _unit {
+                    return Ok(l.clone());
                 }
             }
             }
@@ -297,18 +298,20 @@ impl Draw {
 
     fn place_property(&mut self, symbol: &mut Sexp) -> Result<(), Error> {
         let pos: Array1<f64> = get!(symbol, "at")?;
-        let props: Vec<&Sexp> = symbol.get("property").unwrap();
-        let vis_field = props.iter().filter_map(|node| {
-            let effects: Vec<&Sexp> = get!(node, "effects").unwrap();
-            if !effects[0].has("hide") { Option::from(node) } else { None }
-        }).count();
-        let angle: f64 = get!(symbol, "at", 2);
-        let lib_name: String = get!(symbol, "lib_id", 0);
-        let lib = self.get_library(&lib_name).unwrap();
-        let _size = Shape::transform(symbol, &symbol.bounds(&lib).unwrap());
-        let positions = self.pin_position(&symbol, &lib);
+        let props: Vec<&Sexp> = symbol.get("property")?;
+        let mut vis_field = 0;
+        for node in &props {
+            let effects: Vec<&Sexp> = get!(node, "effects")?;
+            if !effects[0].has("hide") {
+                vis_field += 1;
+            }
+        }
+        let lib_name: String = get!(symbol, "lib_id", 0)?;
+        let lib = self.get_library(&lib_name)?;
+        let _size = Shape::transform(symbol, &symbol.bounds(&lib)?)?;
+        let positions = self.pin_position(symbol, &lib)?;
         let mut offset = 0.0;
-        let pins = get_pins(&lib, None).unwrap().len();
+        let pins = get_pins(&lib, None)?.len();
         if pins == 1 { //PINS!
             if positions[0] == 1 { //west
                 /* vis_fields[0].pos = (_size[1][0]+1.28, symbol.pos[1])
@@ -371,7 +374,7 @@ impl Draw {
                 _size[[1, 1]] - ((vis_field as f64-1.0) * 2.0) - 0.64
             };
             if positions[3] == 0 { //north
-                let mut props: Vec<&Sexp> = symbol.get("property").unwrap();
+                let mut props: Vec<&Sexp> = symbol.get("property")?;
                 /* for prop in &mut props {
                     if let Sexp::Node(name, values) = prop {
                         let effects: Vec<&Sexp> = get!(prop, "effects").unwrap();
@@ -442,16 +445,16 @@ impl Draw {
         Err(Error::ParseError)
     }
 
-    fn pin_position(&self, symbol: &Sexp, lib: &Sexp) -> Vec<usize> {
+    fn pin_position(&self, symbol: &Sexp, lib: &Sexp) -> Result<Vec<usize>, Error> {
         let mut position: Vec<usize> = vec![0; 4];
-        let symbol_angle: f64 = get!(symbol, "at", 2);
+        let symbol_angle: f64 = get!(symbol, "at", 2).unwrap_or(0.0);
         let symbol_shift: usize = (symbol_angle / 90.0).round() as usize;
         let mirror: String = if symbol.contains("mirror") {
-            get!(symbol, "mirror", 0)
+            get!(symbol, "mirror", 0).unwrap_or_default()
         } else { String::new() };
 
-        for pin in get_pins(lib, Option::from(get_unit(symbol).unwrap())).unwrap() {
-            let pin_angle: f64 = get!(pin, "at", 2);
+        for pin in get_pins(lib, Option::from(get_unit(symbol)?))? {
+            let pin_angle: f64 = get!(pin, "at", 2).unwrap_or(0.0);
             let lib_pos: usize = (pin_angle / 90.0).round() as usize;
             let pos: usize =
                 /* if mirror.contains("xy") {
@@ -478,7 +481,7 @@ impl Draw {
             position[pos] += 1;
         }
         position.rotate_right(symbol_shift);
-        position
+        Ok(position)
     }
 } 
 
@@ -0,0 +1,431 @@
+//! KiCad drawing sheets.
+//!
+//! The drawing sheet is the frame and the title block of a page, it is loaded
+//! from a `.kicad_wks` file. The item coordinates are relative to a corner of
+//! the page margins, the right bottom corner when the item does not name one.
+//! Texts can contain text variables like `${TITLE}` or `${#}`, they are
+//! replaced with the values of the page.
+use crate::plotter::{Line, PlotItem, Plotter, Polyline, Rectangle, Text};
+use crate::config::RE_VARIABLE;
+use crate::geometry::Orientation;
+use crate::plot::Page;
+use crate::sexp::get::{get, optional, Get};
+use crate::sexp::parser::SexpParser;
+use crate::sexp::test::Test;
+use crate::sexp::{Justify, Sexp};
+use crate::themes::Style;
+use crate::Error;
+
+use ndarray::{arr1, arr2, Array1, Array2, ArrayView};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How often the variables are substituted, values can contain variables themselves.
+const MAX_DEPTH: usize = 10;
+/// Tolerance for repeated items at the border of the page margins.
+const EPSILON: f64 = 1e-6;
+
+/// The defaults and the page margins of the drawing sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setup {
+    pub text_size: (f64, f64),
+    pub line_width: f64,
+    pub text_line_width: f64,
+    pub left_margin: f64,
+    pub right_margin: f64,
+    pub top_margin: f64,
+    pub bottom_margin: f64,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Setup {
+            text_size: (1.5, 1.5),
+            line_width: 0.15,
+            text_line_width: 0.15,
+            left_margin: 10.0,
+            right_margin: 10.0,
+            top_margin: 10.0,
+            bottom_margin: 10.0,
+        }
+    }
+}
+
+impl TryFrom<&Sexp> for Setup {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        let defaults = Setup::default();
+        let text_size = if node.contains("textsize") {
+            (get!(node, "textsize", 0)?, get!(node, "textsize", 1)?)
+        } else {
+            defaults.text_size
+        };
+        Ok(Setup {
+            text_size,
+            line_width: optional(node, "linewidth", 0)?.unwrap_or(defaults.line_width),
+            text_line_width: optional(node, "textlinewidth", 0)?.unwrap_or(defaults.text_line_width),
+            left_margin: optional(node, "left_margin", 0)?.unwrap_or(defaults.left_margin),
+            right_margin: optional(node, "right_margin", 0)?.unwrap_or(defaults.right_margin),
+            top_margin: optional(node, "top_margin", 0)?.unwrap_or(defaults.top_margin),
+            bottom_margin: optional(node, "bottom_margin", 0)?.unwrap_or(defaults.bottom_margin),
+        })
+    }
+}
+
+/// A drawing sheet with the items of a `.kicad_wks` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawingSheet {
+    pub setup: Setup,
+    items: Vec<Sexp>,
+}
+
+impl DrawingSheet {
+    /// Load the drawing sheet file.
+    pub fn load(filename: &Path) -> Result<Self, Error> {
+        let parser = SexpParser::load(&filename.to_string_lossy())?;
+        Self::try_from(parser.root())
+    }
+
+    /// Plot the drawing sheet items for the page.
+    ///
+    /// Repeated items are only drawn when they are inside the page margins.
+    pub fn plot(
+        &self,
+        plotter: &mut dyn Plotter,
+        paper_size: (f64, f64),
+        page: usize,
+        variables: &HashMap<String, String>,
+        style: &Style,
+    ) -> Result<(), Error> {
+        let stroke = style.schema_border();
+        let effects = style.schema_effects();
+        for item in &self.items {
+            let name = if let Sexp::Node(name, _) = item { name.as_str() } else { continue };
+            let option: Option<String> = optional(item, "option", 0)?;
+            match option.as_deref() {
+                Some("page1only") if page != 1 => continue,
+                Some("notonpage1") if page == 1 => continue,
+                _ => {}
+            }
+            let repeat: usize = optional(item, "repeat", 0)?.unwrap_or(1);
+            let incrx: f64 = optional(item, "incrx", 0)?.unwrap_or(0.0);
+            let incry: f64 = optional(item, "incry", 0)?.unwrap_or(0.0);
+            let incrlabel: i32 = optional(item, "incrlabel", 0)?.unwrap_or(1.0) as i32;
+            for index in 0..repeat.max(1) {
+                let offset = arr1(&[incrx * index as f64, incry * index as f64]);
+                match name {
+                    "line" | "rect" => {
+                        let start = self.point(item, "start", &offset, paper_size)?;
+                        let end = self.point(item, "end", &offset, paper_size)?;
+                        if index > 0 && !(self.inside(&start, paper_size) && self.inside(&end, paper_size)) {
+                            continue;
+                        }
+                        let width = self.line_width(item, self.setup.line_width)?;
+                        let pts = arr2(&[[start[0], start[1]], [end[0], end[1]]]);
+                        if name == "line" {
+                            plotter.push(PlotItem::LineItem(Line::new(
+                                pts,
+                                width,
+                                stroke.line_type.clone(),
+                                stroke.color.clone(),
+                            )));
+                        } else {
+                            plotter.push(PlotItem::RectangleItem(Rectangle::new(
+                                pts,
+                                stroke.color.clone(),
+                                width,
+                                stroke.line_type.clone(),
+                                None,
+                            )));
+                        }
+                    }
+                    "tbtext" => {
+                        let pos = self.point(item, "pos", &offset, paper_size)?;
+                        if index > 0 && !self.inside(&pos, paper_size) {
+                            continue;
+                        }
+                        let content: String = get!(item, 0)?;
+                        let content = if index > 0 {
+                            increment_label(&content, incrlabel * index as i32)
+                        } else {
+                            content
+                        };
+                        let fonts: Vec<&Sexp> = item.get("font")?;
+                        let (width, height) = match fonts.first() {
+                            Some(font) if font.contains("size") => (get!(*font, "size", 0)?, get!(*font, "size", 1)?),
+                            _ => self.setup.text_size,
+                        };
+                        let mut text = Text::new(
+                            pos,
+                            optional(item, "rotate", 0)?.unwrap_or(0.0),
+                            substitute(&content, variables),
+                            effects.color.clone(),
+                            height,
+                            effects.font.as_str(),
+                            justify(item)?,
+                        );
+                        text.fontwidth = width;
+                        if let Some(font) = fonts.first() {
+                            text.bold = font.has("bold");
+                            text.italic = font.has("italic");
+                        }
+                        text.thickness = match fonts.first() {
+                            Some(font) => self.line_width(font, self.setup.text_line_width)?,
+                            None => self.setup.text_line_width,
+                        };
+                        plotter.push(PlotItem::TextItem(text));
+                    }
+                    "polygon" => {
+                        let pos = self.point(item, "pos", &offset, paper_size)?;
+                        if index > 0 && !self.inside(&pos, paper_size) {
+                            continue;
+                        }
+                        let orientation = Orientation::new(optional(item, "rotate", 0)?.unwrap_or(0.0), None);
+                        let width = self.line_width(item, 0.0)?;
+                        let outlines: Vec<&Sexp> = item.get("pts")?;
+                        for outline in outlines {
+                            let mut pts: Array2<f64> = Array2::zeros((0, 2));
+                            let xy: Vec<&Sexp> = outline.get("xy")?;
+                            for xy in xy {
+                                let pt = &pos + &orientation.direction(&arr1(&[get!(xy, 0)?, get!(xy, 1)?]));
+                                pts.push_row(ArrayView::from(&[pt[0], pt[1]]))
+                                    .map_err(|err| Error::ValueParseError(String::from("polygon"), err.to_string()))?;
+                            }
+                            plotter.push(PlotItem::PolylineItem(Polyline::new(
+                                pts,
+                                stroke.color.clone(),
+                                width,
+                                stroke.line_type.clone(),
+                                Some(stroke.color.clone()),
+                            )));
+                        }
+                    }
+                    //bitmaps and the setup are not drawn
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The page coordinates of the point with the corner in the third value.
+    fn point(&self, node: &Sexp, key: &str, offset: &Array1<f64>, paper_size: (f64, f64)) -> Result<Array1<f64>, Error> {
+        let x: f64 = get!(node, key, 0)?;
+        let y: f64 = get!(node, key, 1)?;
+        let corner: Option<String> = optional(node, key, 2)?;
+        let (x, y) = (x + offset[0], y + offset[1]);
+        let left = self.setup.left_margin;
+        let top = self.setup.top_margin;
+        let right = paper_size.0 - self.setup.right_margin;
+        let bottom = paper_size.1 - self.setup.bottom_margin;
+        Ok(match corner.as_deref() {
+            Some("ltcorner") => arr1(&[left + x, top + y]),
+            Some("lbcorner") => arr1(&[left + x, bottom - y]),
+            Some("rtcorner") => arr1(&[right - x, top + y]),
+            _ => arr1(&[right - x, bottom - y]),
+        })
+    }
+
+    /// The point is inside the page margins.
+    fn inside(&self, pt: &Array1<f64>, paper_size: (f64, f64)) -> bool {
+        pt[0] >= self.setup.left_margin - EPSILON
+            && pt[0] <= paper_size.0 - self.setup.right_margin + EPSILON
+            && pt[1] >= self.setup.top_margin - EPSILON
+            && pt[1] <= paper_size.1 - self.setup.bottom_margin + EPSILON
+    }
+
+    /// The line width of the item, zero is the default width.
+    fn line_width(&self, node: &Sexp, default: f64) -> Result<f64, Error> {
+        let width: f64 = optional(node, "linewidth", 0)?.unwrap_or(0.0);
+        Ok(if width == 0.0 { default } else { width })
+    }
+}
+
+impl TryFrom<&Sexp> for DrawingSheet {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        let values = match node {
+            Sexp::Node(name, values) if name == "kicad_wks" || name == "page_layout" => values,
+            _ => return Err(Error::ValueError(String::from("not a drawing sheet"))),
+        };
+        let mut setup = Setup::default();
+        let mut items = Vec::new();
+        for value in values {
+            if let Sexp::Node(name, _) = value {
+                if name == "setup" {
+                    setup = Setup::try_from(value)?;
+                } else if name != "version" && name != "generator" {
+                    items.push(value.clone());
+                }
+            }
+        }
+        Ok(DrawingSheet { setup, items })
+    }
+}
+
+impl FromStr for DrawingSheet {
+    type Err = Error;
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let parser: SexpParser = content.parse()?;
+        Self::try_from(parser.root())
+    }
+}
+
+/// The justification of a text, texts are left aligned and vertically centered by default.
+fn justify(node: &Sexp) -> Result<Vec<Justify>, Error> {
+    let mut horizontal = Some(Justify::Left);
+    let mut vertical = None;
+    let nodes: Vec<&Sexp> = node.get("justify")?;
+    if let Some(Sexp::Node(_, values)) = nodes.first() {
+        for value in values {
+            match value {
+                Sexp::Value(value) if value == "left" => horizontal = Some(Justify::Left),
+                Sexp::Value(value) if value == "right" => horizontal = Some(Justify::Right),
+                Sexp::Value(value) if value == "center" => horizontal = None,
+                Sexp::Value(value) if value == "top" => vertical = Some(Justify::Top),
+                Sexp::Value(value) if value == "bottom" => vertical = Some(Justify::Bottom),
+                _ => return Err(Error::JustifyValueError),
+            }
+        }
+    }
+    Ok(horizontal.into_iter().chain(vertical).collect())
+}
+
+/// Increment the label of a repeated text.
+///
+/// A trailing number is incremented, like `9`, `10`, `11` for the columns of the
+/// frame. Otherwise the last character is replaced by the following character,
+/// like `A`, `B`, `C` for the rows.
+pub fn increment_label(text: &str, increment: i32) -> String {
+    let digits = text.len() - text.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (prefix, number) = text.split_at(text.len() - digits);
+    if let Ok(number) = number.parse::<i64>() {
+        return format!("{}{}", prefix, number + increment as i64);
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    match chars.pop() {
+        Some(last) => {
+            let next = char::from_u32((last as i32 + increment) as u32).unwrap_or(last);
+            format!("{}{}", chars.into_iter().collect::<String>(), next)
+        }
+        None => String::new(),
+    }
+}
+
+/// Substitute the `${VAR}` variables in the text, unknown variables are not replaced.
+pub fn substitute(text: &str, variables: &HashMap<String, String>) -> String {
+    let mut text = text.to_string();
+    for _ in 0..MAX_DEPTH {
+        let next = RE_VARIABLE
+            .replace_all(&text, |caps: &regex::Captures<'_>| {
+                variables.get(&caps[1]).cloned().unwrap_or_else(|| caps[0].to_string())
+            })
+            .to_string();
+        if next == text {
+            break;
+        }
+        text = next;
+    }
+    text
+}
+
+/// The text variables of a page.
+///
+/// The variables are taken from the title block and the page, they take
+/// precedence over the text variables of the project.
+pub fn variables(
+    title_block: Option<&Sexp>,
+    paper_name: &str,
+    page: &Page,
+    project: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut variables = project.clone();
+    if let Some(Sexp::Node(_, values)) = title_block {
+        for value in values {
+            if let Sexp::Node(name, _) = value {
+                let key = match name.as_str() {
+                    "title" => String::from("TITLE"),
+                    "date" => String::from("ISSUE_DATE"),
+                    "rev" => String::from("REVISION"),
+                    "company" => String::from("COMPANY"),
+                    "comment" => match Get::<usize, usize>::get(value, 0) {
+                        Ok(number) => format!("COMMENT{}", number),
+                        Err(_) => continue,
+                    },
+                    _ => continue,
+                };
+                let index = if name == "comment" { 1 } else { 0 };
+                if let Ok(text) = Get::<usize, String>::get(value, index) {
+                    variables.insert(key, text);
+                }
+            }
+        }
+    }
+    let filename = Path::new(&page.filename).file_name().map(|name| name.to_string_lossy().to_string());
+    variables.insert(String::from("FILENAME"), filename.unwrap_or_default());
+    variables.insert(String::from("SHEETNAME"), page.name.clone());
+    variables.insert(String::from("SHEETPATH"), page.path.clone());
+    variables.insert(String::from("PAPER"), paper_name.to_string());
+    variables.insert(String::from("#"), page.number.to_string());
+    variables.insert(String::from("##"), page.count.to_string());
+    variables.insert(String::from("KICAD_VERSION"), format!("elektron {}", env!("CARGO_PKG_VERSION")));
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_increment() {
+        assert_eq!(increment_label("1", 3), "4");
+        assert_eq!(increment_label("1", 10), "11");
+        assert_eq!(increment_label("9", 1), "10");
+        assert_eq!(increment_label("10", 1), "11");
+        assert_eq!(increment_label("Col 19", 3), "Col 22");
+        assert_eq!(increment_label("A", 2), "C");
+        assert_eq!(increment_label("Row a", 1), "Row b");
+        assert_eq!(increment_label("", 1), "");
+    }
+
+    #[test]
+    fn page_variables() {
+        let doc: SexpParser = r#"(kicad_sch (title_block (title "${PROJECT} board") (date "2022-05-01") (rev "B")
+    (company "elektron") (comment 1 "first") (comment 4 "fourth")))"#
+            .parse()
+            .unwrap();
+        let page = Page {
+            filename: String::from("project/power.kicad_sch"),
+            name: String::from("power"),
+            path: String::from("/power/"),
+            number: 2,
+            count: 3,
+        };
+        let mut project = HashMap::new();
+        project.insert(String::from("PROJECT"), String::from("summe"));
+        project.insert(String::from("TITLE"), String::from("overwritten"));
+        let variables = variables(doc.values().next(), "A3", &page, &project);
+
+        let text = "${TITLE} ${REVISION} ${ISSUE_DATE} ${COMPANY} ${COMMENT1} ${COMMENT4}";
+        assert_eq!(substitute(text, &variables), "summe board B 2022-05-01 elektron first fourth");
+        let text = "${SHEETNAME} ${SHEETPATH} ${FILENAME} ${PAPER} ${#}/${##}";
+        assert_eq!(substitute(text, &variables), "power /power/ power.kicad_sch A3 2/3");
+        assert_eq!(substitute("${COMMENT2}", &variables), "${COMMENT2}");
+    }
+
+    #[test]
+    fn load_setup() {
+        let sheet: DrawingSheet = r#"(kicad_wks (version 20210606) (generator pl_editor)
+  (setup (textsize 2 2.5) (linewidth 0.2) (left_margin 5) (right_margin 5))
+  (line (name "") (start 0 0) (end 10 0)))"#
+            .parse()
+            .unwrap();
+        assert_eq!(sheet.setup.text_size, (2.0, 2.5));
+        assert_eq!(sheet.setup.line_width, 0.2);
+        assert_eq!(sheet.setup.left_margin, 5.0);
+        assert_eq!(sheet.setup.top_margin, 10.0);
+        assert_eq!(sheet.items.len(), 1);
+        assert!(matches!("(kicad_sch)".parse::<DrawingSheet>(), Err(Error::ValueError(_))));
+    }
+}
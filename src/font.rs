@@ -0,0 +1,329 @@
+//! Built-in stroke font.
+//!
+//! The texts are drawn with the strokes of a Hershey style font, so the output
+//! does not depend on the fonts that are installed on the machine. The text can
+//! contain the KiCad markup for an overbar `~{RESET}`, a subscript `V_{CC}` and
+//! a superscript `10^{3}`, a new line starts the next text line.
+mod glyphs;
+
+use crate::sexp::Justify;
+
+use lazy_static::lazy_static;
+use ndarray::Array2;
+use std::collections::HashMap;
+
+/// The height of the capital letters in glyph units.
+const CAP_HEIGHT: f64 = 21.0;
+/// The baseline in glyph units.
+const BASELINE: f64 = 9.0;
+/// The distance of the text lines relative to the font height.
+const INTERLINE: f64 = 1.62;
+/// The size of subscripts and superscripts relative to the font height.
+const SCRIPT_SIZE: f64 = 0.7;
+/// The baseline of subscripts is moved down by this part of the font height.
+const SUBSCRIPT_OFFSET: f64 = 0.15;
+/// The baseline of superscripts is moved up by this part of the font height.
+const SUPERSCRIPT_OFFSET: f64 = 0.35;
+/// The height of the overbar relative to the font height.
+const OVERBAR_HEIGHT: f64 = 1.2;
+/// The slant of italic texts.
+const ITALIC_TILT: f64 = 0.125;
+
+/// A decoded glyph, the coordinates are in glyph units.
+struct Glyph {
+    left: f64,
+    advance: f64,
+    strokes: Vec<Vec<(f64, f64)>>,
+}
+
+impl Glyph {
+    fn decode(data: &str) -> Glyph {
+        let value = |c: u8| c as f64 - b'R' as f64;
+        let bytes = data.as_bytes();
+        let mut strokes = vec![Vec::new()];
+        for pair in bytes[2..].chunks(2) {
+            if pair == b" R" {
+                strokes.push(Vec::new());
+            } else if let (Some(stroke), [x, y]) = (strokes.last_mut(), pair) {
+                stroke.push((value(*x), value(*y)));
+            }
+        }
+        strokes.retain(|stroke| stroke.len() > 1);
+        Glyph {
+            left: value(bytes[0]),
+            advance: value(bytes[1]) - value(bytes[0]),
+            strokes,
+        }
+    }
+}
+
+lazy_static! {
+    static ref GLYPHS: HashMap<char, Glyph> = {
+        let mut result = HashMap::new();
+        for (c, data) in (' '..='~').zip(glyphs::ASCII.iter()) {
+            result.insert(c, Glyph::decode(data));
+        }
+        for (c, data) in glyphs::EXTRA.iter() {
+            result.insert(*c, Glyph::decode(data));
+        }
+        result
+    };
+}
+
+/// Get the glyph of the character, unknown characters are drawn as `?`.
+fn glyph(c: char) -> &'static Glyph {
+    GLYPHS.get(&c).unwrap_or_else(|| &GLYPHS[&'?'])
+}
+
+/// The font attributes of a text, the sizes are in mm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Font {
+    pub width: f64,
+    pub height: f64,
+    pub thickness: f64,
+    pub bold: bool,
+    pub italic: bool,
+    pub line_spacing: f64,
+}
+
+impl Font {
+    pub fn new(width: f64, height: f64) -> Self {
+        Font {
+            width,
+            height,
+            thickness: 0.0,
+            bold: false,
+            italic: false,
+            line_spacing: 1.0,
+        }
+    }
+
+    /// The pen width, without a thickness the width follows the size and the weight.
+    pub fn line_width(&self) -> f64 {
+        if self.thickness > 0.0 {
+            self.thickness
+        } else if self.bold {
+            self.height / 5.0
+        } else {
+            self.height / 8.0
+        }
+    }
+
+    /// The distance of the baselines of two text lines.
+    pub fn interline(&self) -> f64 {
+        self.height * INTERLINE * self.line_spacing
+    }
+}
+
+/// The strokes of a rendered text.
+///
+/// The points are relative to the top left corner of the text box, the box
+/// reaches from the top of the capital letters in the first line to the
+/// baseline of the last line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strokes {
+    pub lines: Vec<Array2<f64>>,
+    pub width: f64,
+    pub height: f64,
+    pub line_width: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+    Normal,
+    Subscript,
+    Superscript,
+}
+
+/// A character of the text with the markup applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Character {
+    c: char,
+    overbar: bool,
+    script: Script,
+}
+
+/// Apply the markup to the characters, groups that are not closed end with the text.
+fn parse(text: &str) -> Vec<Character> {
+    let mut result = Vec::new();
+    let mut groups = vec![(false, Script::Normal)];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let (overbar, script) = groups[groups.len() - 1];
+        if matches!(c, '~' | '_' | '^') && chars.peek() == Some(&'{') {
+            chars.next();
+            groups.push(match c {
+                '~' => (true, script),
+                '_' => (overbar, Script::Subscript),
+                _ => (overbar, Script::Superscript),
+            });
+        } else if c == '}' && groups.len() > 1 {
+            groups.pop();
+        } else {
+            result.push(Character { c, overbar, script });
+        }
+    }
+    result
+}
+
+/// The advance of the characters in mm.
+fn advance(character: &Character, font: &Font) -> f64 {
+    let scale = if character.script == Script::Normal { 1.0 } else { SCRIPT_SIZE };
+    glyph(character.c).advance * font.width * scale / CAP_HEIGHT
+}
+
+/// Render the text with the font.
+///
+/// The text lines are aligned in the text box by the horizontal justification,
+/// the vertical justification is applied to the text box by the plotter.
+pub fn render(text: &str, font: &Font, justify: &[Justify]) -> Strokes {
+    let characters = parse(text);
+    let text_lines: Vec<&[Character]> = characters.split(|character| character.c == '\n').collect();
+    let widths: Vec<f64> = text_lines
+        .iter()
+        .map(|line| line.iter().map(|character| advance(character, font)).sum())
+        .collect();
+    let width = widths.iter().cloned().fold(0.0, f64::max);
+    let height = font.height + (text_lines.len() - 1) as f64 * font.interline();
+
+    let mut lines = Vec::new();
+    for (index, text_line) in text_lines.iter().enumerate() {
+        let baseline = font.height + index as f64 * font.interline();
+        let tilt = |x: f64, y: f64| if font.italic { [x + (baseline - y) * ITALIC_TILT, y] } else { [x, y] };
+        let mut x = if justify.contains(&Justify::Right) {
+            width - widths[index]
+        } else if justify.contains(&Justify::Left) {
+            0.0
+        } else {
+            (width - widths[index]) / 2.0
+        };
+        //the start, the end and the height of the current overbar
+        let mut overbar: Option<(f64, f64, f64)> = None;
+        let finish = |overbar: Option<(f64, f64, f64)>, lines: &mut Vec<Array2<f64>>| {
+            if let Some((start, end, y)) = overbar {
+                lines.push(Array2::from(vec![tilt(start, y), tilt(end, y)]));
+            }
+        };
+        for character in text_line.iter() {
+            let glyph = glyph(character.c);
+            let (scale, offset) = match character.script {
+                Script::Normal => (1.0, 0.0),
+                Script::Subscript => (SCRIPT_SIZE, SUBSCRIPT_OFFSET * font.height),
+                Script::Superscript => (SCRIPT_SIZE, -SUPERSCRIPT_OFFSET * font.height),
+            };
+            let scale_x = font.width * scale / CAP_HEIGHT;
+            let scale_y = font.height * scale / CAP_HEIGHT;
+            for stroke in &glyph.strokes {
+                let pts: Vec<[f64; 2]> = stroke
+                    .iter()
+                    .map(|(gx, gy)| {
+                        tilt(x + (gx - glyph.left) * scale_x, baseline + offset + (gy - BASELINE) * scale_y)
+                    })
+                    .collect();
+                lines.push(Array2::from(pts));
+            }
+            let end = x + glyph.advance * scale_x;
+            if character.overbar {
+                let y = baseline + offset - font.height * scale * OVERBAR_HEIGHT;
+                overbar = match overbar {
+                    Some((start, _, bar)) if bar == y => Some((start, end, y)),
+                    other => {
+                        finish(other, &mut lines);
+                        Some((x, end, y))
+                    }
+                };
+            } else {
+                finish(overbar.take(), &mut lines);
+            }
+            x = end;
+        }
+        finish(overbar, &mut lines);
+    }
+    Strokes {
+        lines,
+        width,
+        height,
+        line_width: font.line_width(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_glyphs() {
+        assert_eq!(glyphs::ASCII.len(), ('~' as usize) - (' ' as usize) + 1);
+        let space = glyph(' ');
+        assert!(space.strokes.is_empty());
+        assert_eq!(space.advance, 16.0);
+        let a = glyph('A');
+        assert_eq!(a.strokes.len(), 2);
+        assert_eq!(a.strokes[0], vec![(-8.0, 9.0), (0.0, -12.0), (8.0, 9.0)]);
+        //the glyphs stay between the brackets
+        for glyph in GLYPHS.values() {
+            for (_, y) in glyph.strokes.iter().flatten() {
+                assert!((-16.0..=16.0).contains(y));
+            }
+        }
+        assert!(std::ptr::eq(glyph('\u{2603}'), glyph('?')));
+    }
+
+    #[test]
+    fn text_size() {
+        let font = Font::new(1.27, 1.27);
+        let strokes = render("IA", &font, &[Justify::Left]);
+        assert!((strokes.width - (8.0 + 18.0) * 1.27 / 21.0).abs() < 1e-9);
+        assert_eq!(strokes.height, 1.27);
+        //the A starts at the baseline and reaches the top of the box
+        assert!((strokes.lines[1][[1, 1]]).abs() < 1e-9);
+        assert!((strokes.lines[1][[0, 1]] - 1.27).abs() < 1e-9);
+        assert_eq!(strokes.line_width, 1.27 / 8.0);
+
+        let mut font = Font::new(2.0, 2.0);
+        font.line_spacing = 2.0;
+        font.bold = true;
+        let strokes = render("AB\nI", &font, &[Justify::Right]);
+        assert_eq!(strokes.height, 2.0 + 2.0 * 1.62 * 2.0);
+        assert_eq!(strokes.line_width, 0.4);
+        //the short second line is right aligned
+        let last = strokes.lines.last().unwrap();
+        assert!((last[[0, 0]] - (strokes.width - 4.0 * 2.0 / 21.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn markup() {
+        let font = Font::new(2.1, 2.1);
+        let plain = render("RESET", &font, &[]);
+        let overbar = render("~{RESET}", &font, &[]);
+        assert_eq!(overbar.width, plain.width);
+        assert_eq!(overbar.lines.len(), plain.lines.len() + 1);
+        let bar = overbar.lines.last().unwrap();
+        assert!((bar[[0, 1]] - (2.1 - 2.1 * 1.2)).abs() < 1e-9);
+        assert!((bar[[1, 0]] - bar[[0, 0]] - plain.width).abs() < 1e-9);
+
+        let sub = render("V_{CC}", &font, &[]);
+        assert!(sub.width < render("VCC", &font, &[]).width);
+        assert!(sub.lines.iter().skip(1).all(|line| line.column(1).iter().all(|y| *y > 2.1 - 0.7 * 2.1)));
+        let sup = render("10^{3}", &font, &[]);
+        assert!(sup.lines.last().unwrap().column(1).iter().all(|y| *y < 2.1));
+        //a brace without a group is a character
+        assert_eq!(parse("a}").len(), 2);
+        assert_eq!(parse("~{a_{b}c").iter().map(|c| (c.overbar, c.script)).collect::<Vec<_>>(), vec![
+            (true, Script::Normal),
+            (true, Script::Subscript),
+            (true, Script::Normal),
+        ]);
+    }
+
+    #[test]
+    fn italic() {
+        let mut font = Font::new(2.1, 2.1);
+        let upright = render("I", &font, &[Justify::Left]);
+        font.italic = true;
+        let italic = render("I", &font, &[Justify::Left]);
+        //the top is moved to the right, the baseline stays
+        assert!((italic.lines[0][[0, 0]] - upright.lines[0][[0, 0]] - 2.1 * ITALIC_TILT).abs() < 1e-9);
+        assert!((italic.lines[0][[1, 0]] - upright.lines[0][[1, 0]]).abs() < 1e-9);
+    }
+}
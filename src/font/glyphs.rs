@@ -0,0 +1,114 @@
+//! The glyphs of the stroke font.
+//!
+//! The glyphs are encoded like the Hershey fonts in KiCad. The first two
+//! characters are the left and the right limit of the glyph, the following
+//! pairs are the x and y coordinates of the strokes. The coordinates are
+//! relative to `R`, the y axis points down and a ` R` pair lifts the pen.
+//! The capital letters are 21 units high, from `F` to the baseline at `[`.
+
+/// The glyphs of the printable ASCII characters, starting with the space.
+pub const ASCII: [&str; 95] = [
+    "JZ", // ' '
+    "MWRFRT RRYQZR[SZRY", // '!'
+    "JZNFNM RVFVM", // '"'
+    "H]SFN[ RYFT[ RMN[N RLTZT", // '#'
+    "H\\RCR^ RXHVGTFQFOGMHLILKLMNOPPRPUPWRXSYUYWWYUZS[P[NZLX", // '$'
+    "F^[FI[ RPJPIPGOFMFLFKGJHJIJKJLKMLNMNONPMPKPJ RZWZVZTYSWSVSUTTUTVTXTYUZV[W[Y[ZZZXZW", // '%'
+    "F^\\[ONMKMHOFRFTHTKQNJSIVJYM[R[VYZS", // '&'
+    "MWRFRL", // '\''
+    "KYUBSDQHPLPPPTQXS\\U^", // '('
+    "KYOBQDSHTLTPTTSXQ\\O^", // ')'
+    "JZRFRR RMIWO RWIMO", // '*'
+    "E_RIR[ RIR[R", // '+'
+    "MWSZR[QZRYSZS\\R^Q_", // ','
+    "E_IR[R", // '-'
+    "MWRYQZR[SZRY", // '.'
+    "G][BIb", // '/'
+    "H\\YPYMWIUGSFPFNHLKKOKRLVNYP[S[UZWXYTYQ", // '0'
+    "H\\NJPISFS[", // '1'
+    "H\\LJMHOGQFTFVGWIXKXMXOK[Y[", // '2'
+    "H\\LFXFRNUNWPXRYTYWWYUZS[P[NZLX", // '3'
+    "H\\U[UFJUZU", // '4'
+    "H\\XFMFLONOPNSNUOWPYRYUXWWYUZR[P[NZLX", // '5'
+    "H\\VHTFQFOGMJKMKPKTKWMYOZQ[T[VZXXYVYSXQVOTNQNOOMPKRKT", // '6'
+    "H\\KFYFO[", // '7'
+    "H\\RPTPVOWMXKXJWHUGSFQFOGMHLJLKMMNOPPRP RRPOPMQLSKUKWLYNZQ[S[VZXYYWYUXSWQUPRP", // '8'
+    "H\\NYP[S[UZWWYTYQYMYJWHUGSFPFNGLIKKKNLPNRPSSSURWQYOYM", // '9'
+    "MWRNQORPSORN RRYQZR[SZRY", // ':'
+    "MWRNQORPSORN RSZR[QZRYSZS\\R^Q_", // ';'
+    "F^ZIJRZ[", // '<'
+    "E_IO[O RIU[U", // '='
+    "F^JIZRJ[", // '>'
+    "I[LIMHOGQFSFUGWHXJXLWMVOTPRQRT RRYQZR[SZRY", // '?'
+    "E`WRWPVNUMSMRMQNPOOQORPTQURVSVUVVUWSWR RWMWTXVZV\\T\\P[MYIVGRFNGKIILHPITKWNZQ[UZXY", // '@'
+    "I[J[RFZ[ RMTWT", // 'A'
+    "H\\L[LFTFVFWGXHXJXLXNWOVPTPLP RTPVPWQXSYUYVXXWZV[T[L[", // 'B'
+    "H]YJWGTFQFNHLKKOKRLVNYQ[T[WZYW", // 'C'
+    "H\\L[LFRFTGVHXKYOYRXVVYTZR[L[", // 'D'
+    "I[XFLFL[X[ RLPUP", // 'E'
+    "I[XFLFL[ RLPUP", // 'F'
+    "H]YJWGTFQFNHLKKOKRLVNYQ[T[WZYWYSTS", // 'G'
+    "H\\KFK[ RYFY[ RKPYP", // 'H'
+    "NVRFR[", // 'I'
+    "JZVFVVVXUYTZS[Q[PZOYNXNV", // 'J'
+    "H\\KFK[ RYFKT RPOY[", // 'K'
+    "JZMFM[X[", // 'L'
+    "G]J[JFR[ZFZ[", // 'M'
+    "H\\K[KFY[YF", // 'N'
+    "G]ZPYMXIVGSFPFMHKKJOJRKVMYP[S[VZXXYTZQ", // 'O'
+    "H\\L[LFTFVFWGXIYKYLXNWPVQTQLQ", // 'P'
+    "G]ZPYMXIVGSFPFMHKKJOJRKVMYP[S[VZXXYTZQ RTVZ]", // 'Q'
+    "H\\L[LFTFVFWGXIYKYLXNWPVQTQLQ RSQY[", // 'R'
+    "H\\XHVGTFQFOGMHLILKLMNOPPRPUPWRXSYUYWWYUZS[P[NZLX", // 'S'
+    "JZKFYF RRFR[", // 'T'
+    "H\\KFKUKWMYNZQ[S[VZWYYWYUYF", // 'U'
+    "I[JFR[ZF", // 'V'
+    "F^HFM[RJW[\\F", // 'W'
+    "I[KFY[ RYFK[", // 'X'
+    "I[JFRPZF RRPR[", // 'Y'
+    "I[KFYFK[Y[", // 'Z'
+    "KYUBPBP_U_", // '['
+    "G]IB[b", // '\\'
+    "KYOBTBT_O_", // ']'
+    "JZLLRFXL", // '^'
+    "JZJ^Z^", // '_'
+    "MWPFTK", // '`'
+    "I[WMW[ RVQUOSMRMPNNOMQMTMWNYPZR[S[UYVX", // 'a'
+    "I[MFM[ RNQOOQMRMTNVOWQWTWWVYTZR[Q[OYNX", // 'b'
+    "J[WPUNSMQMONNPMSMUNXOZQ[S[UZWX", // 'c'
+    "I[WFW[ RVQUOSMRMPNNOMQMTMWNYPZR[S[UYVX", // 'd'
+    "J[MTXTXQWOUNSMQMONNPMSMUNXOZQ[S[UZWX", // 'e'
+    "LXWFUFSGRJR[ ROMVM", // 'f'
+    "I[WMW]W_V`TbRbQbOaN` RVQUOSMRMPNNOMQMTMWNYPZR[S[UYVX", // 'g'
+    "I[MFM[ RMQNOPNQMSMUNVOWQWSW[", // 'h'
+    "NVRMR[ RRFQGRHSGRF", // 'i'
+    "MWSMS^S_RaQbPbObNaM` RSFRGSHTGSF", // 'j'
+    "JZMFM[ RWMMW RQSX[", // 'k'
+    "NVRFR[", // 'l'
+    "CaGMG[ RGQHOJNLMMMONQORQRSR[ RRQSOUNWMXMZN\\O]Q]S][", // 'm'
+    "I[MMM[ RMQNOPNQMSMUNVOWQWSW[", // 'n'
+    "I[XTWQVOTNSMPMONMPMSMUMXOZP[S[TZVYWWXT", // 'o'
+    "I[MMMb RNQOOQMRMTNVOWQWTWWVYTZR[Q[OYNX", // 'p'
+    "I[WMWb RVQUOSMRMPNNOMQMTMWNYPZR[S[UYVX", // 'q'
+    "LYNMN[ RNSOPQNSMVM", // 'r'
+    "JZVOUNSMRMPMNNMOMQMROSPTRTTTVUWVWWWYVZU[S[P[OZMY", // 's'
+    "LXQFQXQYRZR[S[T[U[VZ RNMVM", // 't'
+    "I[MMMUMWNYOZQ[S[UZVYWWWU RWMW[", // 'u'
+    "JZLMR[XM", // 'v'
+    "G]JMN[ROV[ZM", // 'w'
+    "JZLMX[ RXML[", // 'x'
+    "JZLMR[ RXMR[P`NbLb", // 'y'
+    "JZLMXML[X[", // 'z'
+    "KYUBSCRERMQOOPQQRSR\\S^U_", // '{'
+    "NVRBRb", // '|'
+    "KYOBQCRERMSOUPSQRSR\\Q^O_", // '}'
+    "F^ITKQNPQQTSWTYS[P", // '~'
+];
+
+/// Additional glyphs for the units of component values.
+pub const EXTRA: [(char, &str); 4] = [
+    ('°', "LXUIUHTGSFRFQFPGOGOHOJOKPKQLRLSLTKUJUI"),
+    ('±', "E_RIRW RIP[P RI[[["),
+    ('µ', "I[MMMb RMUMWNYOZQ[S[UZVYWWWUWM RWMW["),
+    ('Ω', "H\\Z[V[VXXUYRYNXKVHSGQGNHLKKNKRLUNXN[J["),
+];
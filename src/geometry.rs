@@ -0,0 +1,288 @@
+//! Geometry of the schematic items.
+//!
+//! The library symbols use a y axis pointing up, the schematic a y axis pointing
+//! down. A placed symbol is rotated first and then mirrored, like in KiCad. The
+//! coordinates are not rounded, angles that are a multiple of 90 degrees are exact.
+
+use crate::sexp::get::get;
+use crate::sexp::test::Test;
+use crate::sexp::{Justify, Sexp};
+use crate::Error;
+use ndarray::{arr1, arr2, Array1, Array2};
+use std::f64::consts::PI;
+
+/// Sine and cosine of the angle in degrees.
+pub fn sin_cos(angle: f64) -> (f64, f64) {
+    let angle = angle.rem_euclid(360.0);
+    if angle == 0.0 {
+        (0.0, 1.0)
+    } else if angle == 90.0 {
+        (1.0, 0.0)
+    } else if angle == 180.0 {
+        (0.0, -1.0)
+    } else if angle == 270.0 {
+        (-1.0, 0.0)
+    } else {
+        angle.to_radians().sin_cos()
+    }
+}
+
+/// The orientation of a placed symbol.
+///
+/// The matrix transforms the schematic directions of the unrotated symbol, the
+/// library coordinates are flipped before.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Orientation {
+    matrix: Array2<f64>,
+    vertical: bool,
+}
+
+impl Orientation {
+    /// Create the orientation from the angle in degrees and the mirror axis `x`, `y` or `xy`.
+    pub fn new(angle: f64, mirror: Option<&str>) -> Self {
+        let (sin, cos) = sin_cos(angle);
+        //counterclockwise on the screen
+        let rotation = arr2(&[[cos, sin], [-sin, cos]]);
+        let mirror = match mirror {
+            Some("x") => arr2(&[[1.0, 0.0], [0.0, -1.0]]),
+            Some("y") => arr2(&[[-1.0, 0.0], [0.0, 1.0]]),
+            Some("xy") => arr2(&[[-1.0, 0.0], [0.0, -1.0]]),
+            _ => arr2(&[[1.0, 0.0], [0.0, 1.0]]),
+        };
+        Orientation {
+            matrix: mirror.dot(&rotation),
+            vertical: sin.abs() > cos.abs(),
+        }
+    }
+
+    /// The orientation of the node from the angle of `at` and the `mirror` field.
+    pub fn from_node(node: &Sexp) -> Result<Self, Error> {
+        let angle: f64 = if node.contains("at") { get!(node, "at", 2).unwrap_or(0.0) } else { 0.0 };
+        let mirror: Option<String> = if node.contains("mirror") { Some(get!(node, "mirror", 0)?) } else { None };
+        Ok(Orientation::new(angle, mirror.as_deref()))
+    }
+
+    /// The symbol is rotated by 90 or 270 degrees.
+    pub fn is_vertical(&self) -> bool {
+        self.vertical
+    }
+
+    /// Transform a direction in schematic coordinates.
+    pub fn direction(&self, vector: &Array1<f64>) -> Array1<f64> {
+        self.matrix.dot(vector)
+    }
+
+    /// Transform library coordinates relative to the symbol position, one point per row.
+    pub fn transform(&self, pts: &Array2<f64>) -> Array2<f64> {
+        let flip = arr2(&[[1.0, 0.0], [0.0, -1.0]]);
+        pts.dot(&flip).dot(&self.matrix.t())
+    }
+
+    /// Transform a library point relative to the symbol position.
+    pub fn transform_point(&self, pt: &Array1<f64>) -> Array1<f64> {
+        self.direction(&arr1(&[pt[0], -pt[1]]))
+    }
+
+    /// The angle and justification of a symbol property text.
+    ///
+    /// The text stays readable, the angle is 0 or 90 degrees. The text angle toggles
+    /// when the symbol is vertical and the justification follows the mirrored and
+    /// rotated text box.
+    pub fn text(&self, angle: f64, justify: &[Justify]) -> (f64, Vec<Justify>) {
+        let draw_angle = (angle + if self.vertical { 90.0 } else { 0.0 }).rem_euclid(180.0);
+        let (sin, cos) = sin_cos(angle);
+        let (draw_sin, draw_cos) = sin_cos(draw_angle);
+        //the text is written along the direction, the second line is below
+        let along = |v: &Array1<f64>| self.direction(v).dot(&arr1(&[draw_cos, -draw_sin])) > 0.0;
+        let below = |v: &Array1<f64>| self.direction(v).dot(&arr1(&[draw_sin, draw_cos])) > 0.0;
+        let justify = justify
+            .iter()
+            .map(|justify| match justify {
+                Justify::Left if along(&arr1(&[cos, -sin])) => Justify::Left,
+                Justify::Left => Justify::Right,
+                Justify::Right if along(&arr1(&[-cos, sin])) => Justify::Left,
+                Justify::Right => Justify::Right,
+                Justify::Top if below(&arr1(&[sin, cos])) => Justify::Top,
+                Justify::Top => Justify::Bottom,
+                Justify::Bottom if below(&arr1(&[-sin, -cos])) => Justify::Top,
+                Justify::Bottom => Justify::Bottom,
+                justify => justify.clone(),
+            })
+            .collect();
+        (draw_angle, justify)
+    }
+}
+
+/// The arc through the start, mid and end point.
+///
+/// The angles are in radians on the screen, the arc runs from the start angle
+/// with increasing angle to the end angle, the end angle is greater than the start angle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcGeometry {
+    pub center: Array1<f64>,
+    pub radius: f64,
+    pub start_angle: f64,
+    pub end_angle: f64,
+}
+
+impl ArcGeometry {
+    /// Calculate the circle through the three points, `None` when the points are on a line.
+    pub fn new(start: &Array1<f64>, mid: &Array1<f64>, end: &Array1<f64>) -> Option<Self> {
+        let (ax, ay) = (start[0], start[1]);
+        let (bx, by) = (mid[0], mid[1]);
+        let (cx, cy) = (end[0], end[1]);
+        let d = 2.0 * (ax * (by - cy) + bx * (cy - ay) + cx * (ay - by));
+        if d.abs() < 1e-9 {
+            return None;
+        }
+        let a = ax * ax + ay * ay;
+        let b = bx * bx + by * by;
+        let c = cx * cx + cy * cy;
+        let center = arr1(&[
+            (a * (by - cy) + b * (cy - ay) + c * (ay - by)) / d,
+            (a * (cx - bx) + b * (ax - cx) + c * (bx - ax)) / d,
+        ]);
+        let radius = ((ax - center[0]).powi(2) + (ay - center[1]).powi(2)).sqrt();
+        let angle = |pt: &Array1<f64>| (pt[1] - center[1]).atan2(pt[0] - center[0]);
+        let (start_angle, mid_angle, end_angle) = (angle(start), angle(mid), angle(end));
+        let sweep = (end_angle - start_angle).rem_euclid(2.0 * PI);
+        let (start_angle, sweep) = if (mid_angle - start_angle).rem_euclid(2.0 * PI) < sweep {
+            (start_angle, sweep)
+        } else {
+            (end_angle, 2.0 * PI - sweep)
+        };
+        Some(ArcGeometry {
+            center,
+            radius,
+            start_angle,
+            end_angle: start_angle + sweep,
+        })
+    }
+
+    /// The point on the arc at the angle.
+    pub fn point(&self, angle: f64) -> Array1<f64> {
+        arr1(&[
+            self.center[0] + self.radius * angle.cos(),
+            self.center[1] + self.radius * angle.sin(),
+        ])
+    }
+
+    /// The bounding box of the arc.
+    pub fn bounds(&self) -> Array2<f64> {
+        let mut pts = vec![self.point(self.start_angle), self.point(self.end_angle)];
+        //the extreme points on the axes within the arc
+        let mut quadrant = (self.start_angle / (PI / 2.0)).ceil() * (PI / 2.0);
+        while quadrant < self.end_angle {
+            pts.push(self.point(quadrant));
+            quadrant += PI / 2.0;
+        }
+        let min = |axis: usize| pts.iter().map(|pt| pt[axis]).fold(f64::INFINITY, f64::min);
+        let max = |axis: usize| pts.iter().map(|pt| pt[axis]).fold(f64::NEG_INFINITY, f64::max);
+        arr2(&[[min(0), min(1)], [max(0), max(1)]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sexp::parser::SexpParser;
+
+    fn assert_close(actual: &Array2<f64>, expected: &Array2<f64>) {
+        assert_eq!(actual.shape(), expected.shape());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-9, "{} != {}\n{}", actual, expected, a);
+        }
+    }
+
+    #[test]
+    fn orientation() {
+        //pin 1 of Device:R is at (0 3.81) in the library
+        let pin = arr2(&[[0.0, 3.81], [1.27, -2.54]]);
+        let fixtures = [
+            (0.0, None, arr2(&[[0.0, -3.81], [1.27, 2.54]])),
+            (90.0, None, arr2(&[[-3.81, 0.0], [2.54, -1.27]])),
+            (180.0, None, arr2(&[[0.0, 3.81], [-1.27, -2.54]])),
+            (270.0, None, arr2(&[[3.81, 0.0], [-2.54, 1.27]])),
+            (0.0, Some("x"), arr2(&[[0.0, 3.81], [1.27, -2.54]])),
+            (0.0, Some("y"), arr2(&[[0.0, -3.81], [-1.27, 2.54]])),
+            (0.0, Some("xy"), arr2(&[[0.0, 3.81], [-1.27, -2.54]])),
+            //rotated first, then mirrored
+            (90.0, Some("x"), arr2(&[[-3.81, 0.0], [2.54, 1.27]])),
+            (90.0, Some("y"), arr2(&[[3.81, 0.0], [-2.54, -1.27]])),
+        ];
+        for (angle, mirror, expected) in fixtures {
+            assert_close(&Orientation::new(angle, mirror).transform(&pin), &expected);
+            let point = Orientation::new(angle, mirror).transform_point(&arr1(&[0.0, 3.81]));
+            assert_eq!(point, arr1(&[expected[[0, 0]], expected[[0, 1]]]));
+        }
+        //no rounding
+        let pt = Orientation::new(0.0, None).transform(&arr2(&[[0.00127, 1.0 / 3.0]]));
+        assert_eq!(pt, arr2(&[[0.00127, -1.0 / 3.0]]));
+        assert!(!Orientation::new(180.0, Some("y")).is_vertical());
+        assert!(Orientation::new(270.0, None).is_vertical());
+    }
+
+    #[test]
+    fn orientation_from_node() {
+        let doc: SexpParser = r#"(kicad_sch (symbol (lib_id "Device:R") (at 10 20 90) (mirror y) (unit 1)))"#
+            .parse()
+            .unwrap();
+        let symbol = doc.values().next().unwrap();
+        assert_eq!(Orientation::from_node(symbol).unwrap(), Orientation::new(90.0, Some("y")));
+    }
+
+    #[test]
+    fn text_orientation() {
+        //R3 in the summe sample: the symbol is rotated by 270 degrees, the reference is horizontal
+        assert_eq!(Orientation::new(270.0, None).text(90.0, &[]), (0.0, vec![]));
+        //R5 in the summe sample: the text is vertical and ends at the position
+        assert_eq!(
+            Orientation::new(90.0, None).text(0.0, &[Justify::Right]),
+            (90.0, vec![Justify::Right])
+        );
+        assert_eq!(
+            Orientation::new(0.0, Some("y")).text(0.0, &[Justify::Left, Justify::Top]),
+            (0.0, vec![Justify::Right, Justify::Top])
+        );
+        assert_eq!(
+            Orientation::new(0.0, Some("x")).text(0.0, &[Justify::Left, Justify::Top]),
+            (0.0, vec![Justify::Left, Justify::Bottom])
+        );
+        assert_eq!(
+            Orientation::new(180.0, None).text(0.0, &[Justify::Left, Justify::Bottom, Justify::Center]),
+            (0.0, vec![Justify::Right, Justify::Top, Justify::Center])
+        );
+        assert_eq!(
+            Orientation::new(270.0, None).text(0.0, &[Justify::Left]),
+            (90.0, vec![Justify::Right])
+        );
+    }
+
+    #[test]
+    fn arc() {
+        //upper half circle on the screen, counterclockwise from the right to the left
+        let arc = ArcGeometry::new(&arr1(&[1.0, 0.0]), &arr1(&[0.0, -1.0]), &arr1(&[-1.0, 0.0])).unwrap();
+        assert_close(&arr2(&[[arc.center[0], arc.center[1]]]), &arr2(&[[0.0, 0.0]]));
+        assert!((arc.radius - 1.0).abs() < 1e-9);
+        assert!((arc.start_angle - PI).abs() < 1e-9 && (arc.end_angle - 2.0 * PI).abs() < 1e-9);
+        assert_close(&arc.bounds(), &arr2(&[[-1.0, -1.0], [1.0, 0.0]]));
+
+        //the lower half circle
+        let arc = ArcGeometry::new(&arr1(&[1.0, 0.0]), &arr1(&[0.0, 1.0]), &arr1(&[-1.0, 0.0])).unwrap();
+        assert!(arc.start_angle.abs() < 1e-9 && (arc.end_angle - PI).abs() < 1e-9);
+        assert_close(&arc.bounds(), &arr2(&[[-1.0, 0.0], [1.0, 1.0]]));
+
+        //a small arc with an offset center
+        let arc = ArcGeometry::new(&arr1(&[12.0, 5.0]), &arr1(&[10.0 + 2f64.sqrt(), 5.0 + 2f64.sqrt()]), &arr1(&[10.0, 7.0])).unwrap();
+        assert_close(&arr2(&[[arc.center[0], arc.center[1]]]), &arr2(&[[10.0, 5.0]]));
+        assert!((arc.end_angle - arc.start_angle - PI / 2.0).abs() < 1e-9);
+        assert_close(&arc.bounds(), &arr2(&[[10.0, 5.0], [12.0, 7.0]]));
+
+        //the arc is larger than a half circle
+        let arc = ArcGeometry::new(&arr1(&[0.0, 1.0]), &arr1(&[0.0, -1.0]), &arr1(&[1.0, 0.0])).unwrap();
+        assert!((arc.end_angle - arc.start_angle - 1.5 * PI).abs() < 1e-9);
+        assert_close(&arc.bounds(), &arr2(&[[-1.0, -1.0], [1.0, 1.0]]));
+
+        assert!(ArcGeometry::new(&arr1(&[0.0, 0.0]), &arr1(&[1.0, 1.0]), &arr1(&[2.0, 2.0])).is_none());
+    }
+}
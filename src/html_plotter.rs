@@ -0,0 +1,142 @@
+//! Interactive HTML export.
+//!
+//! The pages are drawn as inline SVG in a self-contained HTML file. The groups of
+//! the schematic elements carry their attributes as `data-` attributes, like
+//! `data-reference` or `data-net`. The script of the page highlights the whole net
+//! under the mouse and shows the properties of a symbol when it is clicked.
+use std::io::Write;
+
+use crate::plotter::{Backend, DisplayList};
+use crate::svg_plotter::{document, escape};
+use crate::Error;
+
+const STYLE: &str = r#"
+body { margin: 0; background: #808080; font-family: sans-serif; }
+svg { display: block; margin: 1em auto; box-shadow: 0 0 0.5em #404040; }
+.element[data-net], .element[data-type="symbol"] { cursor: pointer; }
+.element[data-net] * { pointer-events: visibleStroke; }
+.element.highlight * { stroke: #ff00ff; }
+.element.selected * { stroke: #0080ff; }
+#properties { position: fixed; top: 1em; right: 1em; padding: 0.5em 1em; background: #ffffff; border: 1px solid #404040; }
+#properties th { text-align: left; padding-right: 1em; }
+"#;
+
+const SCRIPT: &str = r#"
+const panel = document.getElementById('properties');
+function highlight(net, on) {
+  document.querySelectorAll('.element[data-net]').forEach((element) => {
+    if (element.dataset.net === net) {
+      element.classList.toggle('highlight', on);
+    }
+  });
+}
+function unselect() {
+  document.querySelectorAll('.element.selected').forEach((element) => element.classList.remove('selected'));
+  panel.hidden = true;
+}
+function show(element) {
+  unselect();
+  element.classList.add('selected');
+  const table = document.createElement('table');
+  for (const [key, value] of Object.entries(JSON.parse(element.dataset.properties))) {
+    const row = table.insertRow();
+    const name = document.createElement('th');
+    name.textContent = key;
+    row.appendChild(name);
+    row.insertCell().textContent = value;
+  }
+  panel.replaceChildren(table);
+  panel.hidden = false;
+}
+document.querySelectorAll('.element[data-net]').forEach((element) => {
+  element.addEventListener('mouseenter', () => highlight(element.dataset.net, true));
+  element.addEventListener('mouseleave', () => highlight(element.dataset.net, false));
+});
+document.querySelectorAll('.element[data-type="symbol"]').forEach((element) => {
+  element.addEventListener('click', (event) => {
+    event.stopPropagation();
+    show(element);
+  });
+});
+document.addEventListener('click', unselect);
+"#;
+
+/// Backend for a self-contained HTML page with all pages of the schematic.
+pub struct HtmlPlotter {
+    title: String,
+}
+
+impl HtmlPlotter {
+    pub fn new(title: &str) -> HtmlPlotter {
+        HtmlPlotter { title: title.to_string() }
+    }
+
+    /// Create the HTML page.
+    fn html(&self, display_list: &DisplayList, border: bool, scale: f64) -> String {
+        let svgs: Vec<String> = display_list
+            .pages()
+            .iter()
+            .map(|page| document(page, display_list.background_color(), border, scale).to_string())
+            .collect();
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}\n<aside id=\"properties\" hidden></aside>\n<script>{}</script>\n</body>\n</html>\n",
+            escape(&self.title),
+            STYLE,
+            svgs.join("\n"),
+            SCRIPT,
+        )
+    }
+}
+
+impl Backend for HtmlPlotter {
+    fn write(&self, display_list: &DisplayList, mut file: Box<dyn Write>, border: bool, scale: f64) -> Result<(), Error> {
+        file.write_all(self.html(display_list, border, scale).as_bytes())?;
+        Ok(())
+    }
+    fn net_names(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr1;
+
+    use super::*;
+    use crate::plot::plot_items;
+    use crate::plotter::{PlotItem, Plotter, Text};
+    use crate::sexp::parser::SexpParser;
+    use crate::sexp::Color;
+    use crate::themes::Style;
+
+    #[test]
+    fn html_page() {
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let mut display_list = DisplayList::new();
+        plot_items(&mut display_list, &doc, false, &Style::new(), None).unwrap();
+        let plotter = HtmlPlotter::new("summe <test>");
+        let html = plotter.html(&display_list, false, 1.0);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>summe &lt;test&gt;</title>"));
+        assert_eq!(html.matches("<svg").count(), 1);
+        assert_eq!(html.matches("<g").count(), html.matches("</g>").count());
+        assert!(html.contains(r#"data-type="symbol""#));
+        assert!(html.contains(r#"data-reference="R3""#));
+        assert!(html.contains(r#"data-net="IN_1""#));
+        //the properties are escaped JSON
+        assert!(html.contains("&quot;Reference&quot;:&quot;R3&quot;"));
+        assert!(html.contains("<script>"));
+
+        display_list.new_page();
+        display_list.push(PlotItem::TextItem(Text::new(
+            arr1(&[10.0, 10.0]),
+            0.0,
+            String::from("A"),
+            Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
+            1.27,
+            "osifont",
+            vec![],
+        )));
+        assert_eq!(plotter.html(&display_list, true, 1.0).matches("<svg").count(), 2);
+    }
+}
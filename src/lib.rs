@@ -4,7 +4,9 @@ use std::io::Write;
 use pyo3::exceptions::PyOSError;
 use pyo3::prelude::*;
 
+#[cfg(feature = "cairo")]
 pub mod cairo_plotter;
+pub mod config;
 pub mod libraries;
 pub mod plot;
 pub mod reports;
@@ -15,19 +17,34 @@ pub mod netlist;
 pub mod shape;
 pub mod circuit;
 pub mod ngspice;
+pub mod units;
+pub mod schema;
+pub mod diff;
+pub mod drawing_sheet;
+pub mod font;
+pub mod geometry;
+pub mod html_plotter;
+pub mod plotter;
+pub mod svg_plotter;
 
 use crate::sexp::parser::SexpParser;
 use crate::reports::bom;
+#[cfg(feature = "cairo")]
 use crate::cairo_plotter::CairoPlotter;
+use crate::html_plotter::HtmlPlotter;
+use crate::plotter::{Backend, OutputFormat};
+use crate::svg_plotter::SvgPlotter;
 use crate::themes::Style;
-use crate::plot::plot;
 use crate::libraries::Libraries;
-use crate::circuit::Circuit;
+use crate::circuit::{Circuit, OperatingPoint};
+use crate::config::Config;
 
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum Error {
     #[error("Can not parse file.")]
     ParseError,
+    #[error("{0}:{1}:{2}: {3}")]
+    SexpParseError(String, usize, usize, String),
     #[error("can not find symbol {0}.")]
     SymbolNotFound(String),
     #[error("can not find symbol.")]
@@ -52,6 +69,22 @@ pub enum Error {
     MoreThenOnPropertyFound(String),
     #[error("Spice model not found: {0}")]
     SpiceModelNotFound(String),
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+    #[error("Spice netlist error in line {0}: {1}")]
+    SpiceParseError(usize, String),
+    #[error("Spice element not found: {0}")]
+    SpiceElementNotFound(String),
+    #[error("Can not parse value: {0}")]
+    ValueError(String),
+    #[error("Simulation error: {0}")]
+    SimulationError(String),
+    #[error("{1} not found in {0}")]
+    KeyNotFound(String, String),
+    #[error("Can not parse value in {0}: {1}")]
+    ValueParseError(String, String),
+    #[error("Invalid query {0}: {1}")]
+    QueryError(String, String),
 }
 
 impl std::convert::From<std::io::Error> for Error {
@@ -64,6 +97,11 @@ impl std::convert::From<std::fmt::Error> for Error {
         Error::IoError(err.to_string())
     }
 }
+impl std::convert::From<ngspice::NgSpiceError> for Error {
+    fn from(err: ngspice::NgSpiceError) -> Self {
+        Error::SimulationError(format!("{:?}", err))
+    }
+}
 impl std::convert::From<Error> for PyErr {
     fn from(err: Error) -> PyErr {
         PyOSError::new_err(err.to_string())
@@ -85,20 +123,81 @@ fn get_bom(input: &str, output: Option<&str>, group: bool) -> PyResult<()> {
     Ok(())
 }
 
+/// Plot the schematic with all sheets.
+///
+/// The operating point is only drawn on the root sheet, see `plot::plot_pages`.
 #[pyfunction]
+#[allow(clippy::too_many_arguments)]
 fn schema_plot(
     filename: &str,
     output: Option<&str>,
     border: bool,
     scale: f64,
+    op: Option<OperatingPoint>,
+    format: Option<&str>,
+    dpi: Option<f64>,
+    theme: Option<&str>,
 ) -> Result<(), Error> {
-    let mut cairo = CairoPlotter::new();
-    let style = Style::new();
-    let parser = SexpParser::load(filename).unwrap();
-    plot(&mut cairo, output, &parser, border, style).unwrap();
+    let config = Config::load(filename)?;
+    let mut style = Style::select(theme.map(String::from).or_else(|| config.theme()).as_deref())?;
+    if config.force_theme() {
+        style.set_force(true);
+    }
+    let output = output.ok_or_else(|| Error::IoError(String::from("no output file")))?;
+    let pages = plot::pages(filename)?;
+    let format = OutputFormat::select(format, Some(output))?;
+    let plotter = backend(format, dpi, filename)?;
+    for (index, (output, pages)) in plot::page_outputs(format, output, &pages).into_iter().enumerate() {
+        let op = if index == 0 { op.as_ref() } else { None };
+        plot::plot_pages(plotter.as_ref(), &output, pages, border, scale, style.clone(), op)?;
+    }
     Ok(())
 }
 
+/// Select the backend for the output format.
+///
+/// SVG and HTML files are written in Rust, PDF and PNG files need cairo.
+fn backend(format: OutputFormat, dpi: Option<f64>, title: &str) -> Result<Box<dyn Backend>, Error> {
+    match format {
+        OutputFormat::Svg => Ok(Box::new(SvgPlotter::new())),
+        OutputFormat::Html => Ok(Box::new(HtmlPlotter::new(title))),
+        #[cfg(feature = "cairo")]
+        OutputFormat::Pdf | OutputFormat::Png => {
+            let mut cairo = CairoPlotter::new();
+            cairo.set_format(format);
+            if let Some(dpi) = dpi {
+                cairo.set_dpi(dpi);
+            }
+            Ok(Box::new(cairo))
+        }
+        #[cfg(not(feature = "cairo"))]
+        OutputFormat::Pdf | OutputFormat::Png => {
+            let _ = dpi;
+            Err(Error::ValueError(format!("{:?} output needs the cairo feature", format)))
+        }
+    }
+}
+
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+fn symbol_plot(
+    lib_id: &str,
+    output: &str,
+    path: Vec<String>,
+    unit: Option<usize>,
+    convert: Option<usize>,
+    scale: f64,
+    dpi: Option<f64>,
+    theme: Option<&str>,
+) -> Result<(), Error> {
+    let style = Style::select(theme.map(String::from).or_else(|| Config::default().theme()).as_deref())?;
+    let mut libs: Libraries = Libraries::new(path);
+    let node = libs.get(lib_id)?;
+    let symbol = schema::LibrarySymbol::try_from(&node)?;
+    let plotter = backend(OutputFormat::select(None, Some(output))?, dpi, lib_id)?;
+    plot::plot_library_symbol(plotter.as_ref(), output, &symbol, unit, convert, style, scale)
+}
+
 #[pyfunction]
 fn search(term: &str, path: Vec<String>) -> PyResult<Vec<SearchItem>> {
     let mut libs: Libraries = Libraries::new(path);
@@ -107,29 +206,72 @@ fn search(term: &str, path: Vec<String>) -> PyResult<Vec<SearchItem>> {
 }
 
 #[pyfunction]
-fn schema_netlist(input: &str, output: Option<String>) -> PyResult<()> {
+fn schema_netlist(input: &str, output: Option<String>, spice_pathlist: Option<Vec<String>>) -> PyResult<()> {
     let out: Box<dyn Write> = if let Some(filename) = output {
         Box::new(File::create(filename).unwrap())
     } else {
         Box::new(std::io::stdout())
     };
     let parser = SexpParser::load(input).unwrap();
-    let mut netlist = netlist::Netlist::from(&parser);
-    let mut circuit = Circuit::new(vec!["/home/etienne/elektron/samples/files/spice".to_string()]);
+    let mut netlist = netlist::Netlist::from(&parser)?;
+    let config = Config::load(input)?;
+    let mut circuit = Circuit::new(config.spice_pathlist(spice_pathlist));
     netlist.dump(&mut circuit)?;
     println!("{}", circuit);
     Ok(())
 }
 
+#[pyfunction]
+fn schema_diff(
+    old: &str,
+    new: &str,
+    old_revision: Option<&str>,
+    new_revision: Option<&str>,
+    output: Option<&str>,
+    format: Option<&str>,
+) -> Result<bool, Error> {
+    let old_parser = diff::load(old, old_revision)?;
+    let new_parser = diff::load(new, new_revision)?;
+    let result = diff::SchematicDiff::new(&old_parser, &new_parser)?;
+    let format = match (format, output) {
+        (Some(format), _) => format.to_string(),
+        (None, Some(output)) if output.ends_with(".json") => String::from("json"),
+        (None, Some(output)) if output.ends_with(".svg") => String::from("svg"),
+        _ => String::from("text"),
+    };
+    if format == "svg" {
+        let output = output.ok_or_else(|| Error::IoError(String::from("svg output needs a filename")))?;
+        result.plot(&SvgPlotter::new(), &new_parser, output, true, Style::new())?;
+        return Ok(result.is_empty());
+    }
+    let mut out: Box<dyn Write> = if let Some(filename) = output {
+        Box::new(File::create(filename)?)
+    } else {
+        Box::new(std::io::stdout())
+    };
+    match format.as_str() {
+        "json" => result.to_json().write(&mut out)?,
+        "text" => write!(out, "{}", result)?,
+        _ => return Err(Error::ValueError(format!("unknown diff format: {}", format))),
+    }
+    out.flush()?;
+    Ok(result.is_empty())
+}
+
 /// A Python module implemented in Rust.
 #[pymodule]
 fn elektron(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(get_bom, m)?)?;
     m.add_function(wrap_pyfunction!(schema_plot, m)?)?;
+    m.add_function(wrap_pyfunction!(symbol_plot, m)?)?;
     m.add_function(wrap_pyfunction!(search, m)?)?;
     m.add_function(wrap_pyfunction!(schema_netlist, m)?)?;
+    m.add_function(wrap_pyfunction!(schema_diff, m)?)?;
     m.add_class::<draw::Draw>()?;
     m.add_class::<SearchItem>()?;
     m.add_class::<circuit::Circuit>()?;
+    m.add_class::<OperatingPoint>()?;
+    m.add_class::<SexpParser>()?;
+    m.add_class::<schema::Schematic>()?;
     Ok(())
 }
@@ -1,9 +1,49 @@
 use crate::{Error, SearchItem};
 use super::sexp::{Sexp, parser::SexpParser};
 use super::sexp::get::{get, Get};
+use super::sexp::test::Test;
 use std::fs;
 
 
+/// Add the units and pin settings of the parent to a derived symbol.
+///
+/// The derived symbol keeps its own properties.
+fn extend(node: &Sexp, parent: &Sexp) -> Sexp {
+    let mut result = node.clone();
+    if let (Sexp::Node(_, values), Sexp::Node(_, parent_values)) = (&mut result, parent) {
+        values.retain(|value| !matches!(value, Sexp::Node(name, _) if name == "extends"));
+        for value in parent_values {
+            if let Sexp::Node(name, _) = value {
+                let exists = values.iter().any(|v| matches!(v, Sexp::Node(n, _) if n == name));
+                if name == "symbol" || (name != "property" && !exists) {
+                    values.push(value.clone());
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Resolve the `extends` chain of a symbol, the parents can extend other symbols.
+fn resolve(parser: &SexpParser, library: &str, node: &Sexp, visited: &mut Vec<String>) -> Result<Sexp, Error> {
+    if !node.contains("extends") {
+        return Ok(node.clone());
+    }
+    let parent: String = get!(node, "extends", 0)?;
+    if visited.contains(&parent) {
+        return Err(Error::LibraryNotFound(format!("{}:{} extends itself", library, parent)));
+    }
+    visited.push(parent.clone());
+    let parent_node = parser
+        .values()
+        .find(|value| {
+            let name: Result<String, Error> = get!(*value, 0);
+            matches!(name, Ok(name) if name == parent)
+        })
+        .ok_or_else(|| Error::LibraryNotFound(format!("{}:{}", library, parent)))?;
+    Ok(extend(node, &resolve(parser, library, parent_node, visited)?))
+}
+
 pub struct Libraries {
     pathlist: Vec<String>,
 }
@@ -58,7 +98,7 @@ impl Libraries {
                         if node_name == "symbol" {
                             let lib_id: String = get!(node, 0)?;
                             if lib_id == t[1] {
-                                return Ok(node.clone());
+                                return resolve(&parser, t[0], node, &mut Vec::new());
                             }
                         }
                     }
@@ -109,3 +149,37 @@ impl Libraries {
 //        }
 //    }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_extends() {
+        let directory = std::env::temp_dir().join("elektron-extends");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("Test.kicad_sym"),
+            r#"(kicad_symbol_lib (version 20211014) (generator kicad_symbol_editor)
+  (symbol "BASE" (pin_names (offset 0.254)) (in_bom yes) (on_board yes)
+    (property "Reference" "U" (id 0) (at 0 0 0) (effects (font (size 1.27 1.27))))
+    (symbol "BASE_1_1" (pin input line (at 0 0 0) (length 2.54) (name "A" (effects (font (size 1.27 1.27)))) (number "1" (effects (font (size 1.27 1.27)))))))
+  (symbol "MIDDLE" (extends "BASE")
+    (property "Reference" "U" (id 0) (at 0 0 0) (effects (font (size 1.27 1.27)))))
+  (symbol "LEAF" (extends "MIDDLE")
+    (property "Reference" "IC" (id 0) (at 0 0 0) (effects (font (size 1.27 1.27)))))
+  (symbol "LOOP" (extends "LOOP")))"#,
+        )
+        .unwrap();
+        let mut libraries = Libraries::new(vec![directory.to_string_lossy().to_string()]);
+        let leaf = libraries.get("Test:LEAF").unwrap();
+        assert!(!leaf.contains("extends"));
+        assert!(leaf.contains("pin_names"));
+        let units: Vec<&Sexp> = leaf.get("symbol").unwrap();
+        assert_eq!(units.len(), 1);
+        let properties: Vec<&Sexp> = leaf.get("property").unwrap();
+        let reference: String = get!(properties[0], 1).unwrap();
+        assert_eq!(reference, "IC");
+        assert!(matches!(libraries.get("Test:LOOP"), Err(Error::LibraryNotFound(_))));
+    }
+}
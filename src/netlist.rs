@@ -9,7 +9,7 @@ use super::sexp::iterator::{libraries};
 use super::circuit::Circuit;
 use crate::shape::{Shape, Transform};
 
-use ndarray::{Array1, Array2};
+use ndarray::{arr1, Array1, Array2};
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
@@ -19,8 +19,11 @@ pub struct Point {
     y: f64,
 }
 impl Point {
+    /// The coordinates are snapped to the KiCad resolution of 100 nm, the transformed
+    /// pin positions are not rounded. Adding zero removes the sign of `-0.0` for the hash.
     fn new(x: f64, y: f64) -> Point {
-        Point { x, y }
+        let snap = |value: f64| (value * 10000.0).round() / 10000.0 + 0.0;
+        Point { x: snap(x), y: snap(y) }
     }
 }
 impl PartialEq for Point {
@@ -59,6 +62,16 @@ impl NetlistItem {
     }
 }
 
+/// A symbol pin and the net it is connected to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PinNet {
+    pub reference: String,
+    pub pin: String,
+    pub net: Option<String>,
+    pub index: usize,
+    pub at: Array1<f64>,
+}
+
 /* fn libraries(sexp_parser: &SexpParser) -> Result<std::collections::HashMap<String, Sexp>, Error> {
    let mut libraries: std::collections::HashMap<String, Sexp> = std::collections::HashMap::new();
    for element in sexp_parser.values() {
@@ -97,9 +110,9 @@ impl<'a> Netlist<'a> {
             nodes: std::collections::HashMap::new(),
         }
     } */
-    pub fn from(doc: &'a SexpParser) -> Self {
+    pub fn from(doc: &'a SexpParser) -> Result<Self, Error> {
 
-        let libraries = libraries(doc).unwrap();
+        let libraries = libraries(doc)?;
         let mut nodes: HashMap<Point, usize> =  std::collections::HashMap::new();
         let mut netlists: Vec<NetlistItem> = Vec::new();
         let mut symbols: HashMap<String, Vec<Sexp>> = HashMap::new();
@@ -107,71 +120,35 @@ impl<'a> Netlist<'a> {
         for node in doc.values() {
             if let Sexp::Node(name, _) = node {
                 if name == "symbol" {
-                    iterate_unit_pins(node, &libraries).iter().for_each(|el| {
-                    let lib_id: String = get!(node, "lib_id", 0);
-                    // let unit: usize = get_unit(node).unwrap();
-                    let library = libraries.get(&lib_id).unwrap();
+                    let lib_id: String = get!(node, "lib_id", 0)?;
+                    let library = libraries.get(&lib_id).ok_or_else(|| Error::LibraryNotFound(lib_id.clone()))?;
                     let identifier: Option<String> = if library.contains("power") {
-                        Option::from(get_property(node, "Value").unwrap())
+                        Option::from(get_property(node, "Value")?)
                     } else { None };
-                    /* let syms: Vec<&Sexp> = library.get("symbol").unwrap();
-                    for _unit in syms {
-                        let unit_number = get_unit(_unit).unwrap();
-                        if unit_number == 0 || unit_number == unit {
-                            if let Sexp::Node(_, values) = _unit {
-                                for el in values {
-                                    if let Sexp::Node(name, _) = el {
-                                        if name == "pin" { */
-                                            let pin_pos: Array1<f64> = get!(el, "at").unwrap();
-                                            let pts = Shape::transform(node, &pin_pos);
-                                            let p0 = Point::new(pts[0], pts[1]);
-                                            if nodes.contains_key(&p0) {
-                                                let id = if let Some(_) = &identifier {
-                                                    identifier.clone()
-                                                } else {
-                                                    let nl: usize = *nodes.get_mut(&p0).unwrap();
-                                                    netlists[nl].identifier.clone()
-                                                };
-                                                let nl: usize = *nodes.get_mut(&p0).unwrap();
-                                                netlists[nl].netlist_type = get!(el, 0).unwrap();
-                                                netlists[nl].identifier = id;
-                                            } else {
-                                                netlists.push(NetlistItem::new(
-                                                    identifier.clone(),
-                                                    get!(el, 0).unwrap(),
-                                                    p0,
-                                                ));
-                                                nodes.insert(p0, netlists.len() - 1);
-                                            }
-                                        /*}
-                                    } 
-                                }
-                            }
-                        }*/
-                    });
-                    let mut reference: Option<String> = None;
-                    let props: Vec<&Sexp> = node.get("property").unwrap();
-                    for prop in props {
-                        let key: String = get!(prop, 0).unwrap();
-                        if key == "Reference" {
-                            let r: String = get!(prop, 1).unwrap();
-                            reference = Option::from(r);
-                        }
-                    }
-                    match reference {
-                        Some(r) => {
-                            if symbols.contains_key(&r) {
-                                symbols.get_mut(&r).unwrap().push(node.clone());
-                            } else {
-                                symbols.insert(r, Vec::from([node.clone()]));
+                    for el in iterate_unit_pins(node, &libraries)? {
+                        let pin_pos: Array1<f64> = get!(el, "at")?;
+                        let pts = Shape::transform(node, &pin_pos)?;
+                        let p0 = Point::new(pts[0], pts[1]);
+                        let pin_type: String = get!(el, 0)?;
+                        if let Some(nl) = nodes.get(&p0) {
+                            let nl: usize = *nl;
+                            if identifier.is_some() {
+                                netlists[nl].identifier = identifier.clone();
                             }
-                        }
-                        __ => {
-                            println!("no reference in {:?}", node)
+                            netlists[nl].netlist_type = pin_type;
+                        } else {
+                            netlists.push(NetlistItem::new(
+                                identifier.clone(),
+                                pin_type,
+                                p0,
+                            ));
+                            nodes.insert(p0, netlists.len() - 1);
                         }
                     }
+                    let reference: String = get_property(node, "Reference")?;
+                    symbols.entry(reference).or_default().push(node.clone());
                 } else if name == "wire" {
-                    let pts: Array2<f64> = get!(node, "pts").unwrap();
+                    let pts: Array2<f64> = get!(node, "pts")?;
                     let p0 = Point::new(pts.row(0)[0], pts.row(0)[1]);
                     let p1 = Point::new(pts.row(1)[0], pts.row(1)[1]);
                     if nodes.contains_key(&p0) && nodes.contains_key(&p1) {
@@ -200,14 +177,14 @@ impl<'a> Netlist<'a> {
                     }
 
                 } else if name == "label" {
-                    let pts: Array1<f64> = get!(node, "at").unwrap();
+                    let pts: Array1<f64> = get!(node, "at")?;
                     let p0 = Point::new(pts[0], pts[1]);
                     if nodes.contains_key(&p0) {
                         let nl: usize = *nodes.get_mut(&p0).unwrap();
-                        let id: String = get!(&node, 0).unwrap();
+                        let id: String = get!(node, 0)?;
                         netlists[nl].identifier = Option::from(id);
                     } else {
-                         let id: String = get!(&node, 0).unwrap();
+                         let id: String = get!(node, 0)?;
                         netlists.push(NetlistItem::new(
                             Option::from(id),
                             "".to_string(),
@@ -216,14 +193,14 @@ impl<'a> Netlist<'a> {
                         nodes.insert(p0, netlists.len() - 1);
                     }
                 } else if name == "global_label" {
-                    let pts: Array1<f64> = get!(node, "at").unwrap();
+                    let pts: Array1<f64> = get!(node, "at")?;
                     let p0 = Point::new(pts[0], pts[1]);
                     if nodes.contains_key(&p0) {
                         let nl: usize = *nodes.get(&p0).unwrap();
-                        let id: String = get!(&node, 0).unwrap();
+                        let id: String = get!(node, 0)?;
                         netlists[nl].identifier = Option::from(id);
                     } else {
-                        let id: String = get!(&node, 0).unwrap();
+                        let id: String = get!(node, 0)?;
                         netlists.push(NetlistItem::new(
                             Option::from(id),
                             "".to_string(),
@@ -233,7 +210,7 @@ impl<'a> Netlist<'a> {
                     }
 
                 } else if name == "no_connect" {
-                    let pts: Array1<f64> = get!(node, "at").unwrap();
+                    let pts: Array1<f64> = get!(node, "at")?;
                     let p0 = Point::new(pts[0], pts[1]);
                     if nodes.contains_key(&p0) {
                         let nl: usize = *nodes.get_mut(&p0).unwrap();
@@ -251,23 +228,22 @@ impl<'a> Netlist<'a> {
             }
         }
 
-        Netlist {
+        Ok(Netlist {
             index: 0,
             sexp_doc: doc,
-            libraries: libraries,
-            symbols: symbols,
-            netlists: netlists,
-            nodes: nodes,
-        }
+            libraries,
+            symbols,
+            netlists,
+            nodes,
+        })
     }
     pub fn pins(&self, lib_name: &str) -> Result<HashMap<String, (Sexp, usize)>, Error> {
         let mut pins = HashMap::new();
-        if self.libraries.contains_key(lib_name) {
-            let lib: &Sexp = self.libraries.get(lib_name).unwrap();
-            let symbols: Vec<&Sexp> = lib.get("symbol").unwrap();
+        if let Some(lib) = self.libraries.get(lib_name) {
+            let symbols: Vec<&Sexp> = lib.get("symbol")?;
             for symbol in symbols {
                 //get the symbol unit number
-                let name: String = get!(&symbol, 0).unwrap();
+                let name: String = get!(&symbol, 0)?;
                 let unit = if let Some(line) = sexp::RE.captures_iter(&name).next() {
                     line[1].parse::<usize>().unwrap()
                 } else {
@@ -277,9 +253,9 @@ impl<'a> Netlist<'a> {
                 };
                 //search the pins
                 if symbol.contains("pin") {
-                    let _pins: Vec<&Sexp> = symbol.get("pin").unwrap();
+                    let _pins: Vec<&Sexp> = symbol.get("pin")?;
                     for pin in _pins {
-                        let number: String = get!(pin, "number", 0);
+                        let number: String = get!(pin, "number", 0)?;
                         pins.insert(number, (pin.clone(), unit));
                     }
                 }
@@ -298,28 +274,94 @@ impl<'a> Netlist<'a> {
         }
         None
     } */
-    pub fn dump(&mut self, circuit: &mut Circuit) -> Result<(), Error> {
+    /// Create a numeric netname for the unnamed nets in the netlist.
+    pub fn name_nets(&mut self) {
+        let mut id = 1;
+        for net in self.netlists.iter_mut() {
+            if net.identifier.is_none() {
+                net.identifier = Some(id.to_string());
+                id += 1;
+            }
+        }
+    }
 
-        //create a numeric netname for the unnamed nets in the netlist
-        let mut _id = 1;
-        for mut net in self.netlists.iter_mut() {
-            match net.identifier {
-                None => {
-                    net.identifier = Option::from(_id.to_string());
-                    _id += 1;
+    /// Get the name of the net at the position.
+    ///
+    /// The nets must be named with `name_nets` before.
+    pub fn net_name(&self, pos: &Array1<f64>) -> Option<String> {
+        let index = self.nodes.get(&Point::new(pos[0], pos[1]))?;
+        self.netlists[*index].identifier.clone()
+    }
+
+    /// Get the name and a position for each net.
+    ///
+    /// The position is the top left point of the wires, labels and pins in the
+    /// net. The nets must be named with `name_nets` before.
+    pub fn net_positions(&self) -> Vec<(String, Array1<f64>)> {
+        //nets with the same name are connected, like the power symbols.
+        let mut positions: HashMap<&String, Point> = HashMap::new();
+        for (point, index) in &self.nodes {
+            let net = &self.netlists[*index];
+            if net.netlist_type == "no_connect" {
+                continue;
+            }
+            if let Some(id) = &net.identifier {
+                let position = positions.entry(id).or_insert(*point);
+                if (point.y, point.x) < (position.y, position.x) {
+                    *position = *point;
                 }
-                _ => {}
             }
         }
+        let mut result: Vec<(String, Array1<f64>)> = positions
+            .iter()
+            .map(|(id, point)| (id.to_string(), arr1(&[point.x, point.y])))
+            .collect();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        result
+    }
+
+    /// Get the net for each pin of the symbols.
+    ///
+    /// Power symbols are skipped. Unnamed nets have no name, pins with the same
+    /// net name or the same index are connected.
+    pub fn pin_nets(&self) -> Result<Vec<PinNet>, Error> {
+        let mut result = Vec::new();
+        for (reference, symbols) in &self.symbols {
+            if reference.starts_with('#') {
+                continue;
+            }
+            for symbol in symbols {
+                for pin in iterate_unit_pins(symbol, &self.libraries)? {
+                    let pin_pos: Array1<f64> = get!(pin, "at")?;
+                    let pts = Shape::transform(symbol, &pin_pos)?;
+                    let p0 = Point::new(pts[0], pts[1]);
+                    if let Some(index) = self.nodes.get(&p0) {
+                        result.push(PinNet {
+                            reference: reference.clone(),
+                            pin: get!(pin, "number", 0)?,
+                            net: self.netlists[*index].identifier.clone(),
+                            index: *index,
+                            at: pts,
+                        });
+                    }
+                }
+            }
+        }
+        result.sort_by(|a, b| (&a.reference, &a.pin).cmp(&(&b.reference, &b.pin)));
+        Ok(result)
+    }
+
+    pub fn dump(&mut self, circuit: &mut Circuit) -> Result<(), Error> {
+
+        self.name_nets();
 
         //Create a spice entry for each referenca
-        for reference in self.symbols.keys() {
+        for (reference, symbols) in &self.symbols {
             //but not for the power symbols
             if reference.starts_with('#') {
                 continue;
             }
 
-            let symbols = &self.symbols.get(reference).unwrap();
             let first_symbol: &Sexp = &symbols[0];
 
             //skip symbol when Netlist_Enabled is 'N'
@@ -335,8 +377,8 @@ impl<'a> Netlist<'a> {
             }
 
             //create the pin order
-            let lib_id: String = get!(first_symbol, "lib_id", 0);
-            let my_pins = self.pins(&lib_id).unwrap();
+            let lib_id: String = get!(first_symbol, "lib_id", 0)?;
+            let my_pins = self.pins(&lib_id)?;
             let mut pin_sequence: Vec<usize> = (0..my_pins.len()).collect();
 
             //when Node_Sequence is defined, use it
@@ -346,7 +388,9 @@ impl<'a> Netlist<'a> {
                         pin_sequence.clear();
                         let splits: Vec<&str> = sequence.split(' ').collect();
                         for s in splits {
-                            pin_sequence.push(s.parse::<usize>().unwrap());
+                            pin_sequence.push(s.parse::<usize>().map_err(|_| {
+                                Error::ValueParseError(String::from("Spice_Node_Sequence"), s.to_string())
+                            })?);
                         }
                     }
                     _ => {}
@@ -362,13 +406,13 @@ impl<'a> Netlist<'a> {
                         let mut seq_string = String::new();
                         for seq in pin_sequence {
                             let real_pin = (&seq + 1).to_string();
-                            let pin = my_pins.get(&real_pin).unwrap();
-                            let pin_pos: Array1<f64> = get!(pin.0, "at").unwrap();
+                            let pin = my_pins.get(&real_pin).ok_or(Error::PinNotFound(seq + 1))?;
+                            let pin_pos: Array1<f64> = get!(pin.0, "at")?;
                             //get the symbol from the unit number
                             for s in symbols.iter() {
-                                let unit: usize = get!(s, "unit", 0);
+                                let unit: usize = get!(s, "unit", 0)?;
                                 if unit == pin.1 {
-                                    let pts = Shape::transform(s, &pin_pos);
+                                    let pts = Shape::transform(s, &pin_pos)?;
                                     let p0 = Point::new(pts[0], pts[1]);
                                     if let Some(n) = self.nodes.get(&p0) {
                                         let id = if let Some(id) = &self.netlists[*n].identifier {
@@ -386,31 +430,31 @@ impl<'a> Netlist<'a> {
                         }
                         if primitive == "X" {
                             let nodes = seq_string.split(" ").map(|s| s.to_string()).collect();
-                            circuit.circuit(reference.to_string(), nodes, spice_model.unwrap())?;
+                            circuit.circuit(reference.to_string(), nodes, spice_model?)?;
                         } else {
                             println!(
                                 "=> {}{} - {}- {}",
                                 primitive,
                                 reference,
                                 seq_string,
-                                spice_model.unwrap()
+                                spice_model?
                             );
                         }
                     } else {
-                        println!("-> {}{} - - {}", primitive, reference, spice_value.unwrap());
+                        println!("-> {}{} - - {}", primitive, reference, spice_value?);
                     }
                 },
                 Err(Error::PropertyNotFound(_)) => {
                     let mut seq_string = String::new();
                     for seq in pin_sequence {
                         let real_pin = (&seq + 1).to_string();
-                        let pin = my_pins.get(&real_pin).unwrap();
-                        let pin_pos: Array1<f64> = get!(pin.0, "at").unwrap();
+                        let pin = my_pins.get(&real_pin).ok_or(Error::PinNotFound(seq + 1))?;
+                        let pin_pos: Array1<f64> = get!(pin.0, "at")?;
                         //get the symbol from the unit number
                         for s in symbols.iter() {
-                            let unit: usize = get!(s, "unit", 0);
+                            let unit: usize = get!(s, "unit", 0)?;
                             if unit == pin.1 {
-                                let pts = Shape::transform(s, &pin_pos);
+                                let pts = Shape::transform(s, &pin_pos)?;
                                 let p0 = Point::new(pts[0], pts[1]);
                                 if let Some(n) = self.nodes.get(&p0) {
                                     let id = if let Some(id) = &self.netlists[*n].identifier {
@@ -429,17 +473,42 @@ impl<'a> Netlist<'a> {
                     }
                     if reference.starts_with("R") {
                         let nodes: Vec<String> = seq_string.split(" ").map(|s| s.to_string()).collect();
-                        circuit.resistor(reference.clone(), nodes[0].clone(), nodes[1].clone(), spice_value.unwrap());
+                        circuit.resistor(reference.clone(), nodes[0].clone(), nodes[1].clone(), spice_value?);
                     } else if reference.starts_with("C") {
                         let nodes: Vec<String> = seq_string.split(" ").map(|s| s.to_string()).collect();
-                        circuit.capacitor(reference.clone(), nodes[0].clone(), nodes[1].clone(), spice_value.unwrap());
+                        circuit.capacitor(reference.clone(), nodes[0].clone(), nodes[1].clone(), spice_value?);
                     } else {
-                        println!("->> {} {}{}", reference, seq_string, spice_value.unwrap());
+                        println!("->> {} {}{}", reference, seq_string, spice_value?);
                     }
                 }, 
-                _ => { spice_primitive.unwrap(); }
+                Err(err) => return Err(err),
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_positions() {
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let mut netlist = Netlist::from(&doc).unwrap();
+        netlist.name_nets();
+        let positions = netlist.net_positions();
+        assert!(!positions.is_empty());
+        assert!(positions.iter().any(|(name, _)| name == "GND"));
+        //every net has one position
+        let mut names: Vec<&String> = positions.iter().map(|(name, _)| name).collect();
+        names.dedup();
+        assert_eq!(names.len(), positions.len());
+        assert!(positions.iter().any(|(name, pos)| name == "IN_1" && pos[1] <= 45.72));
+        //the net name at the position
+        for (name, pos) in &positions {
+            assert_eq!(netlist.net_name(pos).as_ref(), Some(name));
+        }
+        assert_eq!(netlist.net_name(&arr1(&[-1000.0, -1000.0])), None);
+    }
+}
@@ -1,27 +1,94 @@
 use std::fs::File;
 
 use crate::shape::{Shape, Transform};
+use crate::geometry::{sin_cos, Orientation};
 use crate::sexp::{Sexp, Justify};
 use crate::sexp::parser::SexpParser;
-use crate::sexp::get::{Get, get};
+use crate::sexp::get::{child, Get, get, optional};
 use crate::sexp::test::Test;
-use crate::sexp::{Effects, LineType, FillType, Stroke, Color, get_unit};
+use crate::sexp::{Effects, LineType, FillType, Stroke, Color, get_convert, get_unit, get_property};
+use crate::sexp::iterator::iterate_unit_pins;
+use crate::sexp::writer::format_number;
+use crate::schema::{LibrarySymbol, Schematic};
+use std::path::Path;
+use crate::circuit::OperatingPoint;
+use crate::config::Config;
+use crate::drawing_sheet::{self, DrawingSheet};
+use crate::netlist::Netlist;
+use crate::units::Value;
 use crate::themes::StyleTypes;
 use crate::themes::{Style, StyleContext};
 use crate::Error;
-use crate::cairo_plotter::{Arc, Circle, Line, PlotItem, Plotter, Polyline, Rectangle, Text};
+use crate::plotter::{Arc, Backend, Circle, DisplayList, Image, Line, OutputFormat, PlotItem, Plotter, Polyline, Rectangle, Text};
 use ndarray::{arr1, arr2, Array1, Array2};
 
+/// The paper sizes in mm, the sizes are in landscape orientation.
 pub mod paper {
+    use crate::sexp::get::{get, Get};
+    use crate::sexp::test::Test;
+    use crate::sexp::Sexp;
+    use crate::Error;
+
+    pub const A5: (f64, f64) = (210.0, 148.0);
     pub const A4: (f64, f64) = (297.0, 210.0);
+    pub const A3: (f64, f64) = (420.0, 297.0);
+    pub const A2: (f64, f64) = (594.0, 420.0);
+    pub const A1: (f64, f64) = (841.0, 594.0);
+    pub const A0: (f64, f64) = (1189.0, 841.0);
+    pub const A: (f64, f64) = (279.4, 215.9);
+    pub const B: (f64, f64) = (431.8, 279.4);
+    pub const C: (f64, f64) = (558.8, 431.8);
+    pub const D: (f64, f64) = (863.6, 558.8);
+    pub const E: (f64, f64) = (1117.6, 863.6);
+    pub const US_LETTER: (f64, f64) = (279.4, 215.9);
+    pub const US_LEGAL: (f64, f64) = (355.6, 215.9);
+    pub const US_LEDGER: (f64, f64) = (431.8, 279.4);
+
+    /// Get the paper size by the KiCad name.
+    pub fn size(name: &str) -> Option<(f64, f64)> {
+        match name {
+            "A5" => Some(A5),
+            "A4" => Some(A4),
+            "A3" => Some(A3),
+            "A2" => Some(A2),
+            "A1" => Some(A1),
+            "A0" => Some(A0),
+            "A" => Some(A),
+            "B" => Some(B),
+            "C" => Some(C),
+            "D" => Some(D),
+            "E" => Some(E),
+            "USLetter" => Some(US_LETTER),
+            "USLegal" => Some(US_LEGAL),
+            "USLedger" => Some(US_LEDGER),
+            _ => None,
+        }
+    }
+
+    /// Get the paper name and size from the `paper` node.
+    ///
+    /// `User` papers have the width and height in the node, the other
+    /// papers are turned when the node has the `portrait` flag.
+    pub fn from_node(node: &Sexp) -> Result<(String, (f64, f64)), Error> {
+        let name: String = get!(node, 0)?;
+        if name == "User" {
+            let width: f64 = get!(node, 1)?;
+            let height: f64 = get!(node, 2)?;
+            return Ok((name, (width, height)));
+        }
+        let (width, height) = size(&name).ok_or_else(|| Error::ValueError(format!("unknown paper size: {}", name)))?;
+        if node.has("portrait") {
+            Ok((name, (height, width)))
+        } else {
+            Ok((name, (width, height)))
+        }
+    }
 }
 
 pub fn text(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
-    let pos: Array1<f64> = get!(node, "at").unwrap();
-    let content: String = get!(node, 0).unwrap();
-    let effects: Effects =
-        style.style(&node, "effects", StyleContext::SchemaProperty)
-        .unwrap();
+    let pos: Array1<f64> = get!(node, "at")?;
+    let content: String = get!(node, 0)?;
+    let effects: Effects = style.style(node, "effects", StyleContext::SchemaNote)?;
     plotter.push(PlotItem::TextItem(Text::new(
         pos,
         0.0,
@@ -30,11 +97,17 @@ pub fn text(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(),
         effects.size,
         effects.font.as_str(),
         effects.justify.clone(),
-    )));
+    ).with_effects(&effects)));
     Ok(())
 }
 
-pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+pub fn draw_border(
+    node: Option<Sexp>,
+    paper_name: &str,
+    paper_size: (f64, f64),
+    plotter: &mut dyn Plotter,
+    style: &Style,
+) -> Result<(), Error> {
     let stroke: Stroke = style.schema_border();
     let effects: Effects = style.schema_effects();
     //outline
@@ -88,11 +161,11 @@ pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn
             None,
         )));
     }
-    for i in 0..(paper_size.0 as i32/20+1) {
+    for i in 0..(paper_size.1 as i32/20+1) {
         let text = Text::new(
             arr1(&[2.5, (i as f64) * 20.0 + 10.0]),
             0.0,
-            letters[i as usize].to_string(),
+            letters[i as usize % letters.len()].to_string(),
             effects.color.clone(),
             effects.size,
             effects.font.as_str(),
@@ -122,7 +195,7 @@ pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn
     if let Some(node) = node {
         if node.contains("title") {
             let effects: Effects = style.schema_title_effects();
-            let title: String = get!(&node, "title", 0);
+            let title: String = get!(&node, "title", 0)?;
             let text = Text::new(
                 arr1(&[paper_size.0-115.0, paper_size.1-25.0]),
                 0.0,
@@ -138,7 +211,7 @@ pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn
         let text = Text::new(
             arr1(&[paper_size.0-115.0, paper_size.1-8.0]),
             0.0,
-            format!("Paper: {}", String::from("A4")),
+            format!("Paper: {}", paper_name),
             effects.color.clone(),
             effects.size,
             effects.font.as_str(),
@@ -148,7 +221,7 @@ pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn
 
         if node.contains("date") {
             let effects: Effects = style.schema_effects();
-            let title: String = get!(&node, "date", 0);
+            let title: String = get!(&node, "date", 0)?;
             let text = Text::new(
                 arr1(&[paper_size.0-90.0, paper_size.1-8.0]),
                 0.0,
@@ -162,7 +235,7 @@ pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn
         }
         if node.contains("rev") {
             let effects: Effects = style.schema_effects();
-            let title: String = get!(&node, "rev", 0);
+            let title: String = get!(&node, "rev", 0)?;
             let text = Text::new(
                 arr1(&[paper_size.0-30.0, paper_size.1-8.0]),
                 0.0,
@@ -176,7 +249,7 @@ pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn
         }
         if node.contains("company") {
             let effects: Effects = style.schema_effects();
-            let title: String = get!(&node, "company", 0);
+            let title: String = get!(&node, "company", 0)?;
             let text = Text::new(
                 arr1(&[paper_size.0-115.0, paper_size.1-20.0]),
                 0.0,
@@ -193,10 +266,8 @@ pub fn draw_border(node: Option<Sexp>, paper_size: (f64, f64), plotter: &mut dyn
 }
 
 pub fn no_connect(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
-    let stroke: Stroke = style
-        .style(&node, "stroke", StyleContext::SchemaSymbol)
-        .unwrap();
-    let pos: Array1<f64> = get!(node, "at").unwrap();
+    let stroke: Stroke = style.style(node, "stroke", StyleContext::SchemaNoConnect)?;
+    let pos: Array1<f64> = get!(node, "at")?;
     let lines1 = arr2(&[[-0.8, 0.8], [0.8, -0.8]]) + &pos;
     let lines2 = arr2(&[[0.8, 0.8], [-0.8, -0.8]]) + &pos;
 
@@ -217,16 +288,14 @@ pub fn no_connect(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Resu
 }
 
 pub fn label(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
-    let effects: Effects =
-        style.style(&node, "effects", StyleContext::SchemaProperty)
-        .unwrap();
+    let effects: Effects = style.style(node, "effects", StyleContext::SchemaLabel)?;
     let _fill_color: Option<Color> = style.color(&FillType::Background); //TODO
-    let pos: Array1<f64> = get!(node, "at").unwrap();
-    let mut angle: f64 = get!(node, "at", 2);
+    let pos: Array1<f64> = get!(node, "at")?;
+    let mut angle: f64 = get!(node, "at", 2)?;
     if angle >= 180.0 { //dont know why this is possible
         angle = angle - 180.0;
     }
-    let _text: String = get!(node, 0).unwrap();
+    let _text: String = get!(node, 0)?;
     let text = Text::new(
         pos.clone(),
         angle,
@@ -234,8 +303,8 @@ pub fn label(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<()
         effects.color.clone(),
         effects.size,
         effects.font.as_str(),
-        effects.justify,
-    );
+        effects.justify.clone(),
+    ).with_effects(&effects);
     plotter.push(PlotItem::TextItem(text));
     plotter.push(PlotItem::CircleItem(Circle::new(
         pos,
@@ -248,120 +317,380 @@ pub fn label(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<()
     Ok(())
 }
 
+/// The outline of a global or hierarchical label along the x axis.
+///
+/// The connection point is at the origin, the body between the arrow tips has the length.
+fn label_outline(shape: &str, half: f64, length: f64) -> Array2<f64> {
+    let pts: Vec<[f64; 2]> = match shape {
+        "input" => vec![
+            [0.0, 0.0], [half, half], [length + half, half], [length + half, -half], [half, -half], [0.0, 0.0],
+        ],
+        "output" => vec![
+            [0.0, half], [length, half], [length + half, 0.0], [length, -half], [0.0, -half], [0.0, half],
+        ],
+        "bidirectional" | "tri_state" => vec![
+            [0.0, 0.0], [half, half], [length + half, half], [length + 2.0 * half, 0.0],
+            [length + half, -half], [half, -half], [0.0, 0.0],
+        ],
+        _ => vec![[0.0, half], [length, half], [length, -half], [0.0, -half], [0.0, half]],
+    };
+    arr2(&pts)
+}
+
+/// The text of a label, the text starts at the offset from the position in the label direction.
+fn label_text(pos: &Array1<f64>, angle: f64, offset: f64, content: String, effects: &Effects) -> Text {
+    let pos = arr1(&[
+        pos[0] + angle.to_radians().cos() * offset,
+        pos[1] - angle.to_radians().sin() * offset,
+    ]);
+    let (angle, justify) = if angle >= 180.0 {
+        (angle - 180.0, Justify::Right)
+    } else {
+        (angle, Justify::Left)
+    };
+    Text::new(pos, angle, content, effects.color.clone(), effects.size, effects.font.as_str(), vec![justify])
+        .with_effects(effects)
+}
+
+/// Plot the visible properties of a sheet or global label.
+fn properties(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+    if !node.contains("property") {
+        return Ok(());
+    }
+    let properties: Vec<&Sexp> = node.get("property")?;
+    for property in properties {
+        let key: String = get!(property, 0)?;
+        let context = match key.as_str() {
+            "Sheet name" => StyleContext::SchemaSheetName,
+            "Sheet file" => StyleContext::SchemaSheetFile,
+            _ => StyleContext::SchemaProperty,
+        };
+        let effects: Effects = style.style(property, "effects", context)?;
+        if effects.hide {
+            continue;
+        }
+        let value: String = get!(property, 1)?;
+        let content = if key == "Sheet file" { format!("File: {}", value) } else { value };
+        plotter.push(PlotItem::TextItem(Text::new(
+            get!(property, "at")?,
+            get!(property, "at", 2)?,
+            content,
+            effects.color.clone(),
+            effects.size,
+            effects.font.as_str(),
+            effects.justify.clone(),
+        ).with_effects(&effects)));
+    }
+    Ok(())
+}
+
 pub fn global_label(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
-    let stroke: Stroke = style
-        .style(&node, "stroke", StyleContext::SchemaSymbol)
-        .unwrap();
-    let pos: Array1<f64> = get!(node, "at").unwrap();
-    let lines1 = arr2(&[[-1.0, -1.0], [1.0, 1.0]]) + &pos;
-    let lines2 = arr2(&[[1.0, 1.0], [-1.0, -1.0]]) + &pos;
+    let effects: Effects = style.style(node, "effects", StyleContext::SchemaGlobalLabel)?;
+    let stroke: Stroke = style.stroke(&StyleContext::SchemaGlobalLabel);
+    let pos: Array1<f64> = get!(node, "at")?;
+    let angle: f64 = get!(node, "at", 2)?;
+    let content: String = get!(node, 0)?;
+    let shape: String = if node.contains("shape") { get!(node, "shape", 0)? } else { String::from("passive") };
 
-    plotter.push(PlotItem::LineItem(Line::new(
-        lines1,
-        stroke.width.clone(),
-        stroke.line_type.clone(),
+    let margin = effects.size * 0.3;
+    let half = effects.size / 2.0 + margin;
+    let mut text = label_text(&pos, angle, 0.0, content, &effects);
+    let length = plotter.text_size(&text)[0] + 2.0 * margin;
+    let offset = match shape.as_str() {
+        "input" | "bidirectional" | "tri_state" => half + margin,
+        _ => margin,
+    };
+    text = label_text(&pos, angle, offset, text.text, &effects);
+
+    plotter.push(PlotItem::PolylineItem(Polyline::new(
+        Shape::transform(node, &label_outline(&shape, half, length))?,
+        effects.color.clone(),
+        stroke.width,
+        LineType::Default,
+        None,
+    )));
+    plotter.push(PlotItem::TextItem(text));
+    properties(node, plotter, style)
+}
+
+/// Plot the hierarchical label shape and the text.
+///
+/// The size of the shape is the text size, the shape of sheet pins points into the sheet.
+fn hierarchical_shape(
+    node: &Sexp,
+    shape: &str,
+    angle: f64,
+    reverse: bool,
+    context: StyleContext,
+    plotter: &mut dyn Plotter,
+    style: &Style,
+) -> Result<(), Error> {
+    let effects: Effects = style.style(node, "effects", context)?;
+    let stroke: Stroke = style.stroke(&context);
+    let pos: Array1<f64> = get!(node, "at")?;
+    let content: String = get!(node, 0)?;
+    let size = effects.size;
+    let half = size / 2.0;
+    let length = match shape {
+        "input" | "output" => half,
+        "bidirectional" | "tri_state" => 0.0,
+        _ => size,
+    };
+    let mut outline = label_outline(shape, half, length);
+    if reverse {
+        outline = -outline;
+    }
+    plotter.push(PlotItem::PolylineItem(Polyline::new(
+        Shape::transform(node, &outline)?,
+        effects.color.clone(),
+        stroke.width,
+        LineType::Default,
+        None,
+    )));
+    plotter.push(PlotItem::TextItem(label_text(&pos, angle, size * 1.3, content, &effects)));
+    Ok(())
+}
+
+pub fn hierarchical_label(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+    let angle: f64 = get!(node, "at", 2)?;
+    let shape: String = if node.contains("shape") { get!(node, "shape", 0)? } else { String::from("passive") };
+    hierarchical_shape(node, &shape, angle, false, StyleContext::SchemaHierarchicalLabel, plotter, style)
+}
+
+/// Plot the sheet with the fields and the pins.
+pub fn sheet(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+    let stroke: Stroke = style.style(node, "stroke", StyleContext::SchemaSheet)?;
+    let pos: Array1<f64> = get!(node, "at")?;
+    let size: Array1<f64> = get!(node, "size")?;
+    plotter.push(PlotItem::RectangleItem(Rectangle::new(
+        arr2(&[[pos[0], pos[1]], [pos[0] + size[0], pos[1] + size[1]]]),
         stroke.color.clone(),
+        stroke.width,
+        stroke.line_type.clone(),
+        style.color(&stroke.fill),
     )));
+    properties(node, plotter, style)?;
+    if node.contains("pin") {
+        let pins: Vec<&Sexp> = node.get("pin")?;
+        for pin in pins {
+            //the pin direction is seen from the sheet
+            let direction: String = get!(pin, 1)?;
+            let shape = match direction.as_str() {
+                "input" => String::from("output"),
+                "output" => String::from("input"),
+                shape => shape.to_string(),
+            };
+            let angle: f64 = get!(pin, "at", 2)?;
+            hierarchical_shape(
+                pin,
+                &shape,
+                (angle + 180.0) % 360.0,
+                true,
+                StyleContext::SchemaSheetPin,
+                plotter,
+                style,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn bus_entry(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+    let stroke: Stroke = style.style(node, "stroke", StyleContext::SchemaWire)?;
+    let pos: Array1<f64> = get!(node, "at")?;
+    let size: Array1<f64> = get!(node, "size")?;
     plotter.push(PlotItem::LineItem(Line::new(
-        lines2,
+        arr2(&[[pos[0], pos[1]], [pos[0] + size[0], pos[1] + size[1]]]),
         stroke.width,
         stroke.line_type,
         stroke.color,
     )));
+    Ok(())
+}
 
+/// Plot the graphic items of the schema: polyline, rectangle, circle and arc.
+pub fn graphic(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+    let stroke: Stroke = style.style(node, "stroke", StyleContext::SchemaGraphic)?;
+    let fill_color: Option<Color> = style.color(&stroke.fill);
+    let name = if let Sexp::Node(name, _) = node { name.as_str() } else { "" };
+    match name {
+        "polyline" => plotter.push(PlotItem::PolylineItem(Polyline::new(
+            get!(node, "pts")?,
+            stroke.color,
+            stroke.width,
+            stroke.line_type,
+            fill_color,
+        ))),
+        "rectangle" => {
+            let start: Array1<f64> = get!(node, "start")?;
+            let end: Array1<f64> = get!(node, "end")?;
+            plotter.push(PlotItem::RectangleItem(Rectangle::new(
+                arr2(&[[start[0], start[1]], [end[0], end[1]]]),
+                stroke.color,
+                stroke.width,
+                stroke.line_type,
+                fill_color,
+            )));
+        }
+        "circle" => plotter.push(PlotItem::CircleItem(Circle::new(
+            get!(node, "center")?,
+            get!(node, "radius", 0)?,
+            stroke.width,
+            stroke.line_type,
+            stroke.color,
+            fill_color,
+        ))),
+        "arc" => plotter.push(PlotItem::ArcItem(Arc::new(
+            get!(node, "start")?,
+            get!(node, "mid")?,
+            get!(node, "end")?,
+            stroke.width,
+            stroke.line_type,
+            stroke.color,
+            fill_color,
+        ))),
+        name => return Err(Error::KeyNotFound(name.to_string(), String::from("graphic item"))),
+    }
     Ok(())
 }
 
-pub fn symbol(node: &Sexp, libs: &std::collections::HashMap<String, &Sexp>, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+/// Plot the text box outline and the text at the top left corner.
+pub fn text_box(node: &Sexp, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+    let stroke: Stroke = style.style(node, "stroke", StyleContext::SchemaGraphic)?;
+    let effects: Effects = style.style(node, "effects", StyleContext::SchemaNote)?;
+    let pos: Array1<f64> = get!(node, "at")?;
+    let size: Array1<f64> = get!(node, "size")?;
+    let content: String = get!(node, 0)?;
+    plotter.push(PlotItem::RectangleItem(Rectangle::new(
+        arr2(&[[pos[0], pos[1]], [pos[0] + size[0], pos[1] + size[1]]]),
+        stroke.color,
+        stroke.width,
+        stroke.line_type,
+        style.color(&stroke.fill),
+    )));
+    let margin = effects.size * 0.5;
+    plotter.push(PlotItem::TextItem(Text::new(
+        arr1(&[pos[0] + margin, pos[1] + margin]),
+        get!(node, "at", 2)?,
+        content,
+        effects.color.clone(),
+        effects.size,
+        effects.font.as_str(),
+        vec![Justify::Left, Justify::Top],
+    ).with_effects(&effects)));
+    Ok(())
+}
 
-    let symbol_angle: f64 = get!(node, "at", 2);
-    let properties: Vec<&Sexp> = node.get("property").unwrap();
-    for property in properties {
-        let effects: Effects = 
-            style.style(property, "effects", StyleContext::SchemaProperty)
-            .unwrap();
-        let value: String = get!(property, 1).unwrap();
-        let angle: f64 = get!(property, "at", 2);
-        let mut justify: Vec<Justify> = Vec::new();
-        for j in effects.justify {
-            if angle + symbol_angle >= 180.0 && angle+symbol_angle < 360.0 && j == Justify::Left {
-                justify.push(Justify::Right);
-            } else if (angle + symbol_angle).abs() >= 180.0 && angle+symbol_angle < 360.0 && j == Justify::Right {
-                justify.push(Justify::Left);
-            } else {
-                justify.push(j);
+/// Plot an embedded PNG image.
+///
+/// The image size is calculated from the resolution of the image, KiCad uses 300 ppi by default.
+pub fn image(node: &Sexp, plotter: &mut dyn Plotter) -> Result<(), Error> {
+    let pos: Array1<f64> = get!(node, "at")?;
+    let scale: f64 = if node.contains("scale") { get!(node, "scale", 0)? } else { 1.0 };
+    let mut data = String::new();
+    let nodes: Vec<&Sexp> = node.get("data")?;
+    for data_node in nodes {
+        if let Sexp::Node(_, values) = data_node {
+            for value in values {
+                if let Sexp::Value(value) | Sexp::Text(value) = value {
+                    data.push_str(value);
+                }
             }
         }
-        let prop_angle = if symbol_angle - angle >= 180.0 {
-            symbol_angle - angle - 180.0
-        } else {
-            symbol_angle - angle
+    }
+    let data = base64::decode(&data).map_err(|err| Error::ValueError(format!("invalid image data: {}", err)))?;
+    let reader = png::Decoder::new(data.as_slice())
+        .read_info()
+        .map_err(|err| Error::ValueError(format!("can not read image: {}", err)))?;
+    let info = reader.info();
+    let ppi = match info.pixel_dims {
+        Some(png::PixelDimensions { xppu, unit: png::Unit::Meter, .. }) if xppu > 0 => xppu as f64 * 0.0254,
+        _ => 300.0,
+    };
+    let width = info.width as f64 / ppi * 25.4 * scale;
+    let height = info.height as f64 / ppi * 25.4 * scale;
+    drop(reader);
+    plotter.push(PlotItem::ImageItem(Image::new(
+        arr1(&[pos[0] - width / 2.0, pos[1] - height / 2.0]),
+        width,
+        height,
+        data,
+    )));
+    Ok(())
+}
+
+pub fn symbol(node: &Sexp, libs: &std::collections::HashMap<String, &Sexp>, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+
+    let orientation = Orientation::from_node(node)?;
+    let properties: Vec<&Sexp> = node.get("property")?;
+    for property in properties {
+        let key: String = get!(property, 0)?;
+        let context = match key.as_str() {
+            "Reference" => StyleContext::SchemaReference,
+            "Value" => StyleContext::SchemaValue,
+            _ => StyleContext::SchemaProperty,
         };
+        let effects: Effects = style.style(property, "effects", context)?;
+        let value: String = get!(property, 1)?;
+        let angle: f64 = get!(property, "at", 2)?;
+        let (prop_angle, justify) = orientation.text(angle, &effects.justify);
         if !effects.hide {
             plotter.push(PlotItem::TextItem(Text::new(
-                get!(property, "at").unwrap(), //.get(0).unwrap(),
+                get!(property, "at")?,
                 prop_angle,
                 value,
-                Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
+                effects.color.clone(),
                 effects.size,
                 effects.font.as_str(),
                 justify,
-            )));
+            ).with_effects(&effects)));
         }
     }
-    let lib_id: String = get!(node, "lib_id", 0);
-    let symbol_unit: usize = get_unit(node).unwrap();
-    let lib: &Sexp = &libs.get(&lib_id).unwrap();
-    let units: Vec<&Sexp> = lib.get("symbol").unwrap();
+    let lib_id: String = get!(node, "lib_id", 0)?;
+    let symbol_unit: usize = get_unit(node)?;
+    let symbol_convert: usize = get_convert(node)?;
+    let lib: &Sexp = libs.get(&lib_id).ok_or_else(|| Error::LibraryNotFound(lib_id.clone()))?;
+    let units: Vec<&Sexp> = lib.get("symbol")?;
     for _unit in units {
-        let unit_number = get_unit(&_unit).unwrap();
-        if &unit_number == &0 || &unit_number == &symbol_unit {
+        let unit_number = get_unit(_unit)?;
+        let unit_convert = get_convert(_unit)?;
+        if (unit_convert == 0 || unit_convert == symbol_convert) &&
+           (unit_number == 0 || unit_number == symbol_unit) {
             if let Sexp::Node(_, values) = _unit {
             for graph in values {
                 match graph {
                     Sexp::Node(name, _) => {
                         if name == "polyline" {
-                            let stroke: Stroke = style
-                                .style(&graph, "stroke", StyleContext::SchemaSymbol)
-                                .unwrap();
+                            let stroke: Stroke = style.style(graph, "stroke", StyleContext::SchemaSymbol)?;
                             let fill_color: Option<Color> = style.color(&stroke.fill);
                             plotter.push(PlotItem::PolylineItem(Polyline::new(
-                                Shape::transform(&node, &get!(graph, "pts").unwrap()),
+                                Shape::transform(node, &get!(graph, "pts")?)?,
                                 stroke.color,
                                 stroke.width,
-                                LineType::Default,
+                                stroke.line_type,
                                 fill_color,
                             )));
                         } else if name == "rectangle" {
-                            let stroke: Stroke = style
-                                .style(&graph, "stroke", StyleContext::SchemaSymbol)
-                                .unwrap();
+                            let stroke: Stroke = style.style(graph, "stroke", StyleContext::SchemaSymbol)?;
                             let fill_color: Option<Color> = style.color(&stroke.fill);
-                            let start: Array1<f64> = get!(&graph, "start").unwrap();
-                            let end: Array1<f64> = get!(&graph, "end").unwrap();
+                            let start: Array1<f64> = get!(&graph, "start")?;
+                            let end: Array1<f64> = get!(&graph, "end")?;
                             let pts: Array2<f64> =
                                 arr2(&[[start[0], start[1]], [end[0], end[1]]]);
                             plotter.push(PlotItem::RectangleItem(Rectangle::new(
-                                Shape::transform(&node, &pts),
+                                Shape::transform(node, &pts)?,
                                 stroke.color,
                                 stroke.width,
                                 stroke.line_type,
                                 fill_color,
                             )));
                         } else if name == "circle" {
-                            let stroke: Stroke = style
-                                .style(&graph, "stroke", StyleContext::SchemaSymbol)
-                                .unwrap();
+                            let stroke: Stroke = style.style(graph, "stroke", StyleContext::SchemaSymbol)?;
                             let fill_color: Option<Color> = style.color(&stroke.fill);
-                            let center: Array1<f64> = get!(&graph, "center").unwrap();
-                            let radius: f64 = get!(graph, "radius", 0);
+                            let center: Array1<f64> = get!(&graph, "center")?;
+                            let radius: f64 = get!(graph, "radius", 0)?;
                             plotter.push(PlotItem::CircleItem(Circle::new(
-                                Shape::transform(&node, &center),
+                                Shape::transform(node, &center)?,
                                 radius,
                                 stroke.width,
                                 stroke.line_type,
@@ -369,78 +698,73 @@ pub fn symbol(node: &Sexp, libs: &std::collections::HashMap<String, &Sexp>, plot
                                 fill_color,
                             )));
                         } else if name == "arc" {
-                            let stroke: Stroke = style
-                                .style(&graph, "stroke", StyleContext::SchemaSymbol)
-                                .unwrap();
-                            let _fill_color: Option<Color> = style.color(&stroke.fill); //TODO
-                            let start: Array1<f64> = get!(&graph, "start").unwrap();
-                            let mid: Array1<f64> = get!(&graph, "mid").unwrap();
-                            let end: Array1<f64> = get!(&graph, "end").unwrap();
-
-                            let trans = Shape::transform(&node, &start);
+                            let stroke: Stroke = style.style(graph, "stroke", StyleContext::SchemaSymbol)?;
+                            let fill_color: Option<Color> = style.color(&stroke.fill);
+                            let start: Array1<f64> = get!(&graph, "start")?;
+                            let mid: Array1<f64> = get!(&graph, "mid")?;
+                            let end: Array1<f64> = get!(&graph, "end")?;
+
                             let arc = Arc::new(
-                                trans,
-                                mid,
-                                end,
+                                Shape::transform(node, &start)?,
+                                Shape::transform(node, &mid)?,
+                                Shape::transform(node, &end)?,
                                 stroke.width,
-                                LineType::Default,
+                                stroke.line_type,
                                 stroke.color,
-                                None,
+                                fill_color,
                             );
                             plotter.push(PlotItem::ArcItem(arc));
                         } else if name == "pin" {
-                            let stroke: Stroke = style
-                                .style(&graph, "stroke", StyleContext::SchemaSymbol)
-                                .unwrap();
-                            let pin_pos: Array1<f64> = get!(&graph, "at").unwrap();
-                            let length: f64 = get!(graph, "length", 0);
-                            let pin_angle: f64 = get!(graph, "at", 2);
+                            let stroke: Stroke = style.style(graph, "stroke", StyleContext::SchemaPin)?;
+                            let pin_pos: Array1<f64> = get!(&graph, "at")?;
+                            let length: f64 = get!(graph, "length", 0)?;
+                            let pin_angle: f64 = get!(graph, "at", 2)?;
+                            let (pin_sin, pin_cos) = sin_cos(pin_angle);
                             let pin_line: Array2<f64> = arr2(&[
                                 [pin_pos[0], pin_pos[1]],
                                 [
-                                    pin_pos[0] + pin_angle.to_radians().cos() * length,
-                                    pin_pos[1] + pin_angle.to_radians().sin() * length,
+                                    pin_pos[0] + pin_cos * length,
+                                    pin_pos[1] + pin_sin * length,
                                 ],
                             ]);
                             plotter.push(PlotItem::LineItem(Line::new(
-                                Shape::transform(&node, &pin_line),
+                                Shape::transform(node, &pin_line)?,
                                 stroke.width,
                                 stroke.line_type,
                                 stroke.color,
                             )));
     
 
-                            let pin_number: String = get!(graph, "number", 0);
-                            let pin_name: String = get!(graph, "name", 0);
+                            let pin_number: String = get!(graph, "number", 0)?;
+                            let pin_number_effects: Effects =
+                                style.style(child(graph, "number")?, "effects", StyleContext::SchemaPinNumber)?;
+                            let pin_name_effects: Effects =
+                                style.style(child(graph, "name")?, "effects", StyleContext::SchemaPinName)?;
+                            let pin_name: String = get!(graph, "name", 0)?;
                             let show_pin_numbers = if lib.contains("pin_numbers") {
-                                let numbers_hide: String = get!(lib, "pin_numbers", 0);
+                                let numbers_hide: String = get!(lib, "pin_numbers", 0)?;
                                 numbers_hide != "hide"
                             } else {
                                 true
                             };
-                            if show_pin_numbers {
+                            if show_pin_numbers && !pin_number_effects.hide {
                                 plotter.push(PlotItem::TextItem(Text::new(
-                                    Shape::transform(&node, &pin_pos),
+                                    Shape::transform(node, &pin_pos)?,
                                     0.0,
                                     pin_number,
-                                    Color {
-                                        r: 0.0,
-                                        g: 0.0,
-                                        b: 0.0,
-                                        a: 1.0,
-                                    },
-                                    1.25,
-                                    "osifont",
+                                    pin_number_effects.color.clone(),
+                                    pin_number_effects.size,
+                                    pin_number_effects.font.as_str(),
                                     vec![Justify::Center],
-                                )));
+                                ).with_effects(&pin_number_effects)));
                             }
                             //(pin_names (offset 1.016) hide)
                             let names_offset = if lib.contains("pin_names") {
-                                let pin_names: Vec<&Sexp> = lib.get("pin_names").unwrap();
+                                let pin_names: Vec<&Sexp> = lib.get("pin_names")?;
                                 if pin_names.len() == 1 {
-                                    let the_name = pin_names.get(0).unwrap();
+                                    let the_name = pin_names[0];
                                     let offset: f64 = if the_name.contains("offset") {
-                                        get!(*the_name, "offset", 0)
+                                        get!(the_name, "offset", 0)?
                                     } else {
                                         0.0
                                     };
@@ -452,9 +776,9 @@ pub fn symbol(node: &Sexp, libs: &std::collections::HashMap<String, &Sexp>, plot
                                 0.0
                             };
                             let names_hide = if lib.contains("pin_names") {
-                                let pin_names: Vec<&Sexp> = lib.get("pin_names").unwrap();
+                                let pin_names: Vec<&Sexp> = lib.get("pin_names")?;
                                 if pin_names.len() == 1 {
-                                    let the_name = pin_names.get(0).unwrap();
+                                    let the_name = pin_names[0];
                                     the_name.has("hide")
                                 } else {
                                     false
@@ -463,28 +787,37 @@ pub fn symbol(node: &Sexp, libs: &std::collections::HashMap<String, &Sexp>, plot
                                 false
                             };
                             let name_pos = arr1(&[
-                                    pin_pos[0] + pin_angle.to_radians().cos() * (length + names_offset*4.0),
-                                    pin_pos[1] + pin_angle.to_radians().sin() * (length + names_offset*4.0),
+                                    pin_pos[0] + pin_cos * (length + names_offset*4.0),
+                                    pin_pos[1] + pin_sin * (length + names_offset*4.0),
                             ]);
 
-                            if pin_name != "~" && !names_hide {
+                            if pin_name != "~" && !names_hide && !pin_name_effects.hide {
                                 plotter.push(PlotItem::TextItem(Text::new(
-                                    Shape::transform(&node, &name_pos),
+                                    Shape::transform(node, &name_pos)?,
                                     0.0,
                                     pin_name,
-                                    Color {
-                                        r: 0.0,
-                                        g: 0.0,
-                                        b: 0.0,
-                                        a: 1.0,
-                                    },
-                                    1.25,
-                                    "osifont",
+                                    pin_name_effects.color.clone(),
+                                    pin_name_effects.size,
+                                    pin_name_effects.font.as_str(),
                                     vec![Justify::Center],
-                                )));
+                                ).with_effects(&pin_name_effects)));
+                            }
+                        } else if name == "text" {
+                            let effects: Effects = style.style(graph, "effects", StyleContext::SchemaSymbol)?;
+                            if !effects.hide {
+                                let pos: Array1<f64> = get!(graph, "at")?;
+                                let angle: f64 = get!(graph, "at", 2).unwrap_or(0.0);
+                                let (text_angle, justify) = orientation.text(angle, &effects.justify);
+                                plotter.push(PlotItem::TextItem(Text::new(
+                                    Shape::transform(node, &pos)?,
+                                    text_angle,
+                                    get!(graph, 0)?,
+                                    effects.color.clone(),
+                                    effects.size,
+                                    effects.font.as_str(),
+                                    justify,
+                                ).with_effects(&effects)));
                             }
-                        } else {
-                            println!("Unknwon Graph Item: {:?}", graph);
                         }
                     }
                     _ => {}
@@ -496,13 +829,245 @@ pub fn symbol(node: &Sexp, libs: &std::collections::HashMap<String, &Sexp>, plot
     Ok(())
 }
 
+/// Create a placed symbol for a unit of a library symbol.
+///
+/// The property positions are moved from the library to the schematic coordinates.
+fn placed_symbol(symbol: &LibrarySymbol, unit: usize, convert: usize, at: &Array1<f64>) -> Sexp {
+    let mut values = vec![
+        Sexp::Node(String::from("lib_id"), vec![Sexp::Text(symbol.name.clone())]),
+        Sexp::Node(
+            String::from("at"),
+            vec![
+                Sexp::Value(format_number(at[0])),
+                Sexp::Value(format_number(at[1])),
+                Sexp::Value(String::from("0")),
+            ],
+        ),
+        Sexp::Node(String::from("unit"), vec![Sexp::Value(unit.to_string())]),
+        Sexp::Node(String::from("convert"), vec![Sexp::Value(convert.to_string())]),
+    ];
+    for property in &symbol.properties {
+        let mut property = property.clone();
+        property.at = arr1(&[at[0] + property.at[0], at[1] - property.at[1]]);
+        values.push(Sexp::from(&property));
+    }
+    Sexp::Node(String::from("symbol"), values)
+}
+
+/// The outline of the graphic items and pins of a placed library symbol.
+///
+/// Only the sub-units of the unit and body style of the placed symbol are used,
+/// the outline is the position of the symbol when there are no graphic items.
+pub(crate) fn unit_outline(node: &Sexp, lib: &Sexp) -> Result<Array2<f64>, Error> {
+    let unit = get_unit(node)?;
+    let convert = get_convert(node)?;
+    let mut points: Vec<f64> = Vec::new();
+    let units: Vec<&Sexp> = lib.get("symbol")?;
+    for sub in units {
+        let (sub_unit, sub_convert) = (get_unit(sub)?, get_convert(sub)?);
+        if !((sub_unit == 0 || sub_unit == unit) && (sub_convert == 0 || sub_convert == convert)) {
+            continue;
+        }
+        if let Sexp::Node(_, elements) = sub {
+            for element in elements {
+                if let Sexp::Node(name, _) = element {
+                    match name.as_str() {
+                        "polyline" => {
+                            let pts: Array2<f64> = get!(element, "pts")?;
+                            points.extend(pts.iter());
+                        }
+                        "rectangle" | "arc" => {
+                            let start: Array1<f64> = get!(element, "start")?;
+                            let end: Array1<f64> = get!(element, "end")?;
+                            points.extend(start.iter().chain(end.iter()));
+                        }
+                        "circle" => {
+                            let center: Array1<f64> = get!(element, "center")?;
+                            let radius: f64 = get!(element, "radius", 0)?;
+                            points.extend([center[0] - radius, center[1] - radius, center[0] + radius, center[1] + radius]);
+                        }
+                        "pin" => {
+                            let at: Array1<f64> = get!(element, "at")?;
+                            let angle: f64 = get!(element, "at", 2).unwrap_or(0.0);
+                            let length: f64 = get!(element, "length", 0).unwrap_or(0.0);
+                            points.extend([
+                                at[0],
+                                at[1],
+                                at[0] + angle.to_radians().cos() * length,
+                                at[1] + angle.to_radians().sin() * length,
+                            ]);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    if points.is_empty() {
+        points.extend([0.0, 0.0]);
+    }
+    let pts = Shape::transform(node, &Array2::from_shape_vec((points.len() / 2, 2), points).unwrap())?;
+    let min = |axis: usize| pts.column(axis).iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = |axis: usize| pts.column(axis).iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    Ok(arr2(&[[min(0), min(1)], [max(0), max(1)]]))
+}
+
+/// Draw the units and body styles of a library symbol side by side.
+///
+/// All units and both body styles are drawn when `unit` or `convert` is not set,
+/// every drawing gets a caption when there is more than one.
+pub fn library_symbol(
+    plotter: &mut dyn Plotter,
+    lib_symbol: &LibrarySymbol,
+    unit: Option<usize>,
+    convert: Option<usize>,
+    style: &Style,
+) -> Result<(), Error> {
+    let lib = Sexp::from(lib_symbol);
+    let libs = std::collections::HashMap::from([(lib_symbol.name.clone(), &lib)]);
+    let units: Vec<usize> = match unit {
+        Some(unit) => vec![unit],
+        None => (1..=lib_symbol.unit_count()).collect(),
+    };
+    let converts: Vec<usize> = match convert {
+        Some(convert) => vec![convert],
+        None if lib_symbol.has_de_morgan() => vec![1, 2],
+        None => vec![1],
+    };
+    let captions = units.len() * converts.len() > 1;
+    let mut x = 0.0;
+    for unit in &units {
+        for convert in &converts {
+            let outline = unit_outline(&placed_symbol(lib_symbol, *unit, *convert, &arr1(&[0.0, 0.0])), &lib)?;
+            let at = arr1(&[x - outline[[0, 0]], 0.0]);
+            symbol(&placed_symbol(lib_symbol, *unit, *convert, &at), &libs, plotter, style)?;
+            if captions {
+                let mut caption = format!("Unit {}", unit_name(*unit));
+                if *convert == 2 {
+                    caption += ", De Morgan";
+                }
+                let effects = style.effects(&StyleContext::SchemaProperty);
+                plotter.push(PlotItem::TextItem(Text::new(
+                    arr1(&[x + (outline[[1, 0]] - outline[[0, 0]]) / 2.0, outline[[1, 1]] + 5.08]),
+                    0.0,
+                    caption,
+                    effects.color,
+                    effects.size,
+                    effects.font.as_str(),
+                    vec![Justify::Center],
+                )));
+            }
+            x += outline[[1, 0]] - outline[[0, 0]] + 10.16;
+        }
+    }
+    Ok(())
+}
+
+/// Plot a library symbol, like for a documentation page or a thumbnail.
+pub fn plot_library_symbol(
+    backend: &dyn Backend,
+    filename: &str,
+    lib_symbol: &LibrarySymbol,
+    unit: Option<usize>,
+    convert: Option<usize>,
+    style: Style,
+    scale: f64,
+) -> Result<(), Error> {
+    let mut display_list = DisplayList::new();
+    display_list.background(style.background());
+    library_symbol(&mut display_list, lib_symbol, unit, convert, &style)?;
+    backend.write(&display_list, Box::new(File::create(filename)?), false, scale)?;
+    Ok(())
+}
+
+/// The unit letter, like `A` for the first unit.
+fn unit_name(unit: usize) -> String {
+    if (1..=26).contains(&unit) {
+        char::from(b'A' + (unit - 1) as u8).to_string()
+    } else {
+        unit.to_string()
+    }
+}
+
+/// Draw the node voltages and branch currents of an operating point analysis.
+///
+/// The voltages are drawn at the top left point of each net, the currents
+/// next to the pin where the current enters the element.
+pub fn operating_point(sexp_parser: &SexpParser, op: &OperatingPoint, plotter: &mut dyn Plotter, style: &Style) -> Result<(), Error> {
+    let mut netlist = Netlist::from(sexp_parser)?;
+    netlist.name_nets();
+    let effects = style.op_voltage_effects();
+    for (net, pos) in netlist.net_positions() {
+        if let Some(voltage) = op.voltage(&net) {
+            plotter.push(PlotItem::TextItem(Text::new(
+                pos + arr1(&[0.5, -0.5]),
+                0.0,
+                format!("{}V", Value::new(voltage)),
+                effects.color.clone(),
+                effects.size,
+                effects.font.as_str(),
+                effects.justify.clone(),
+            )));
+        }
+    }
+
+    let libraries = libraries(sexp_parser)?;
+    let effects = style.op_current_effects();
+    let mut references: Vec<String> = Vec::new();
+    for node in sexp_parser.values() {
+        if let Sexp::Node(name, _) = node {
+            if name != "symbol" {
+                continue;
+            }
+            let reference = get_property(node, "Reference")?;
+            //power symbols and the other units of the symbol
+            if reference.starts_with('#') || references.contains(&reference) {
+                continue;
+            }
+            if let Some(current) = op.current(&reference) {
+                let pins = iterate_unit_pins(node, &libraries)?;
+                if let Some(pin) = current_pin(node, &pins) {
+                    let pin_pos: Array1<f64> = get!(pin, "at")?;
+                    plotter.push(PlotItem::TextItem(Text::new(
+                        Shape::transform(node, &pin_pos)? + arr1(&[0.5, 0.5]),
+                        0.0,
+                        format!("{}A", Value::new(current)),
+                        effects.color.clone(),
+                        effects.size,
+                        effects.font.as_str(),
+                        effects.justify.clone(),
+                    )));
+                }
+                references.push(reference);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The pin where the current enters the element.
+///
+/// Spice counts the current from the first node into the element. The first
+/// node is the first pin of the `Spice_Node_Sequence`, otherwise pin `1`.
+fn current_pin<'a>(node: &Sexp, pins: &[&'a Sexp]) -> Option<&'a Sexp> {
+    let sequence = get_property(node, "Spice_Node_Sequence").ok();
+    let first = sequence
+        .as_deref()
+        .and_then(|sequence| sequence.split_whitespace().next())
+        .unwrap_or("1");
+    pins.iter()
+        .find(|pin| matches!(get!(**pin, "number", 0), Ok::<String, Error>(number) if number == first))
+        .or_else(|| pins.first())
+        .copied()
+}
+
 fn libraries(sexp_parser: &SexpParser) -> Result<std::collections::HashMap<String, &Sexp>, Error> {
    let mut libraries: std::collections::HashMap<String, &Sexp> = std::collections::HashMap::new();
    for element in sexp_parser.values() {
        if let Sexp::Node(name, values) = element {
            if name == "lib_symbols" {
                for value in values {
-                   let name: String = value.get(0).unwrap();
+                   let name: String = value.get(0)?;
                    libraries.insert(String::from(name), value);
                }
            }
@@ -511,67 +1076,832 @@ fn libraries(sexp_parser: &SexpParser) -> Result<std::collections::HashMap<Strin
    Ok(libraries)
 }
 
-pub fn plot(plotter: &mut dyn Plotter, filename: Option<&str>, sexp_parser: &SexpParser, border: bool, style: Style) -> Result<(), Error> {
+pub fn plot(
+    backend: &dyn Backend,
+    filename: Option<&str>,
+    sexp_parser: &SexpParser,
+    border: bool,
+    style: Style,
+    op: Option<&OperatingPoint>,
+) -> Result<(), Error> {
+    let filename = filename.ok_or_else(|| Error::IoError(String::from("no output file")))?;
+    let mut display_list = DisplayList::new();
+    display_list.set_net_names(backend.net_names());
+    plot_items(&mut display_list, sexp_parser, border, &style, op)?;
+    let file = Box::new(File::create(filename)?);
+    backend.write(&display_list, file, border, 1.0)?;
+    Ok(())
+}
 
-    let libraries = libraries(sexp_parser).unwrap();
+/// A page of a hierarchical schematic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// The schematic file of the page.
+    pub filename: String,
+    /// The name of the sheet, the root sheet has no name.
+    pub name: String,
+    /// The path of the sheet names, like `/power/filter/`.
+    pub path: String,
+    /// The page number, starting with 1.
+    pub number: usize,
+    /// The number of pages.
+    pub count: usize,
+}
+
+/// Get the pages of a hierarchical schematic, the root schematic first.
+///
+/// The sheet files are relative to the schematic that contains the sheet, a file
+/// that is used by more than one sheet has a page for every sheet. A sheet that
+/// contains its own file is an error.
+pub fn pages(filename: &str) -> Result<Vec<Page>, Error> {
+    fn collect(filename: &str, name: &str, path: &str, parents: &mut Vec<String>, pages: &mut Vec<Page>) -> Result<(), Error> {
+        if parents.iter().any(|parent| parent == filename) {
+            return Err(Error::IoError(format!("recursive sheet {}", filename)));
+        }
+        pages.push(Page {
+            filename: filename.to_string(),
+            name: name.to_string(),
+            path: path.to_string(),
+            number: pages.len() + 1,
+            count: 0,
+        });
+        let schematic = Schematic::load(filename)?;
+        let directory = Path::new(filename).parent().unwrap_or_else(|| Path::new(""));
+        parents.push(filename.to_string());
+        for sheet in schematic.sheets() {
+            if let Some(file) = sheet.filename() {
+                let name = sheet.name().unwrap_or("");
+                collect(&directory.join(file).to_string_lossy(), name, &format!("{}{}/", path, name), parents, pages)?;
+            }
+        }
+        parents.pop();
+        Ok(())
+    }
+    let mut pages = Vec::new();
+    collect(filename, "", "/", &mut Vec::new(), &mut pages)?;
+    let count = pages.len();
+    pages.iter_mut().for_each(|page| page.count = count);
+    Ok(pages)
+}
+
+/// The output filename for a page when every page is written to its own file.
+///
+/// The first page is written to the output, the other pages get the path of
+/// the sheet names appended, like `main-power-filter.svg`.
+pub fn page_filename(output: &str, page: &Page, index: usize) -> String {
+    if index == 0 {
+        return output.to_string();
+    }
+    let path = Path::new(output);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let sheet: String = page
+        .path
+        .trim_matches('/')
+        .chars()
+        .map(|c| match c {
+            '/' => '-',
+            c if c.is_alphanumeric() || c == '_' || c == '-' => c,
+            _ => '_',
+        })
+        .collect();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, sheet, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, sheet),
+    };
+    path.with_file_name(name).to_string_lossy().to_string()
+}
+
+/// The output files and their pages.
+///
+/// PDF and HTML files have all pages, SVG and PNG files have one page and
+/// the other pages are written to the files from `page_filename`.
+pub fn page_outputs<'a>(format: OutputFormat, output: &str, pages: &'a [Page]) -> Vec<(String, &'a [Page])> {
+    match format {
+        OutputFormat::Pdf | OutputFormat::Html => vec![(output.to_string(), pages)],
+        OutputFormat::Svg | OutputFormat::Png => pages
+            .iter()
+            .enumerate()
+            .map(|(index, page)| (page_filename(output, page, index), std::slice::from_ref(page)))
+            .collect(),
+    }
+}
+
+/// Plot the pages of the schematic, the operating point is drawn on the first page.
+///
+/// Only the first page gets the operating point: the nets are named per sheet,
+/// the local nets of the sub-sheets do not have the names of the simulated
+/// circuit and would get the values of other nets.
+///
+/// The border is the drawing sheet of the project configuration, the built-in
+/// border is drawn when the project has no drawing sheet. The items of all pages
+/// are collected in a display list, the backend writes the output file.
+pub fn plot_pages(
+    backend: &dyn Backend,
+    output: &str,
+    pages: &[Page],
+    border: bool,
+    scale: f64,
+    style: Style,
+    op: Option<&OperatingPoint>,
+) -> Result<(), Error> {
+    let config = match pages.first() {
+        Some(page) => Config::load(&page.filename)?,
+        None => Config::default(),
+    };
+    let mut display_list = DisplayList::new();
+    display_list.set_net_names(backend.net_names());
+    display_list.background(style.background());
+    let drawing_sheet = match config.drawing_sheet() {
+        Some(path) if border => Some(DrawingSheet::load(&path)?),
+        _ => None,
+    };
+    for (index, page) in pages.iter().enumerate() {
+        if index > 0 {
+            display_list.new_page();
+        }
+        let parser = SexpParser::load(&page.filename)?;
+        plot_items(&mut display_list, &parser, false, &style, if index == 0 { op } else { None })?;
+        if border {
+            display_list.start_group(&[(String::from("type"), String::from("border"))]);
+            frame(&mut display_list, &parser, page, drawing_sheet.as_ref(), config.variables(), &style)?;
+            display_list.end_group();
+        }
+    }
+    backend.write(&display_list, Box::new(File::create(output)?), border, scale)?;
+    Ok(())
+}
+
+/// Draw the drawing sheet with the title block of the page.
+///
+/// The built-in border is drawn when there is no drawing sheet.
+pub fn frame(
+    plotter: &mut dyn Plotter,
+    sexp_parser: &SexpParser,
+    page: &Page,
+    drawing_sheet: Option<&DrawingSheet>,
+    project: &std::collections::HashMap<String, String>,
+    style: &Style,
+) -> Result<(), Error> {
+    let mut title_block: Option<&Sexp> = None;
+    let mut paper_name = String::from("A4");
+    let mut paper_size = paper::A4;
+    for node in sexp_parser.values() {
+        match node {
+            Sexp::Node(name, _) if name == "paper" => (paper_name, paper_size) = paper::from_node(node)?,
+            Sexp::Node(name, _) if name == "title_block" => title_block = Some(node),
+            _ => {}
+        }
+    }
+    match drawing_sheet {
+        Some(drawing_sheet) => {
+            let variables = drawing_sheet::variables(title_block, &paper_name, page, project);
+            drawing_sheet.plot(plotter, paper_size, page.number, &variables, style)
+        }
+        None => draw_border(title_block.cloned(), &paper_name, paper_size, plotter, style),
+    }
+}
+
+/// The attributes of a schematic element for interactive plots.
+///
+/// Symbols have the reference, the value and all properties as JSON object,
+/// wires, buses, junctions and labels have the name of the net when the netlist
+/// is given. The pins are drawn in the group of the symbol and have no net name,
+/// they are not highlighted with the net. Other elements are not grouped.
+fn element_attributes(node: &Sexp, netlist: Option<&Netlist>) -> Result<Option<Vec<(String, String)>>, Error> {
+    let name = match node {
+        Sexp::Node(name, _) => name.as_str(),
+        _ => return Ok(None),
+    };
+    let pos: Option<Array1<f64>> = match name {
+        "wire" | "bus" => {
+            let pts: Array2<f64> = node.get("pts")?;
+            Some(pts.row(0).to_owned())
+        }
+        "junction" | "label" | "global_label" | "hierarchical_label" => Some(get!(node, "at")?),
+        "symbol" | "no_connect" => None,
+        _ => return Ok(None),
+    };
+    let mut attributes = vec![(String::from("type"), name.to_string())];
+    let uuid: Option<String> = optional(node, "uuid", 0)?;
+    if let Some(uuid) = uuid {
+        attributes.push((String::from("uuid"), uuid));
+    }
+    if name == "symbol" {
+        let mut properties = json::JsonValue::new_object();
+        let nodes: Vec<&Sexp> = node.get("property")?;
+        for property in nodes {
+            let key: String = get!(property, 0)?;
+            let value: String = get!(property, 1)?;
+            match key.as_str() {
+                "Reference" => attributes.push((String::from("reference"), value.clone())),
+                "Value" => attributes.push((String::from("value"), value.clone())),
+                _ => {}
+            }
+            properties[key] = value.into();
+        }
+        attributes.push((String::from("properties"), properties.dump()));
+    } else if let Some(net) = pos.zip(netlist).and_then(|(pos, netlist)| netlist.net_name(&pos)) {
+        attributes.push((String::from("net"), net));
+    }
+    Ok(Some(attributes))
+}
+
+/// Push the plot items of the schema to the plotter without writing the file.
+///
+/// The items of the schematic elements are grouped, the groups carry the
+/// attributes of the elements.
+pub fn plot_items(
+    plotter: &mut dyn Plotter,
+    sexp_parser: &SexpParser,
+    border: bool,
+    style: &Style,
+    op: Option<&OperatingPoint>,
+) -> Result<(), Error> {
+
+    let libraries = libraries(sexp_parser)?;
+    let netlist = if plotter.net_names() {
+        let mut netlist = Netlist::from(sexp_parser)?;
+        netlist.name_nets();
+        Some(netlist)
+    } else {
+        None
+    };
     let mut title_block: Option<Sexp> = None;
+    let mut paper_name = String::from("A4");
     let mut paper_size = paper::A4;
 
-    sexp_parser.values()
-        .for_each(|node| {
-            if let Sexp::Node(name, _) = node {
-                if name == "version" || name == "generator" || 
-                   name == "uuid" || name == "sheet_instances" || 
-                   name == "symbol_instances" || name == "lib_symbols" {
-                    //just skip those elements
-                } else if name == "paper" {
-                    let paper: String = get!(node, 0).unwrap();
-                    plotter.paper(paper);
-                } else if name == "title_block" {
-                    title_block = Option::from(node.clone());
-                } else if name == "no_connect" {
-                    no_connect(node, plotter, &style).unwrap();
-                } else if name == "text" {
-                    text(node, plotter, &style).unwrap();
-                } else if name == "wire" {
-                    let stroke: Stroke = 
-                        style.style(&node, "stroke", StyleContext::SchemaSymbol)
-                        .unwrap();
-                    let pts: Array2<f64> = node.get("pts").unwrap();
-                    plotter.push(
-                        PlotItem::LineItem(Line::new(pts, stroke.width, stroke.line_type, stroke.color)));
-                } else if name == "junction" {
-                    let stroke: Stroke = style
-                        .style(&node, "stroke", StyleContext::SchemaSymbol)
-                        .unwrap();
-                    let fill_color: Option<Color> = style.color(&stroke.fill);
-                    let pos: Array1<f64> = get!(node, "at").unwrap();
-                    plotter.push(PlotItem::CircleItem(Circle::new(
-                        pos,
-                        0.3,
-                        0.1,
-                        stroke.line_type,
-                        stroke.color,
-                        fill_color,
-                    )));
-                } else if name == "label" {
-                    label(node, plotter, &style).unwrap();
-                } else if name == "global_label" {
-                    global_label(node, plotter, &style).unwrap();
-                } else if name == "symbol" {
-                    symbol(node, &libraries, plotter, &style).unwrap();
-                } else {
-                    println!("node not implemented: {}", name);
-                }
+    for node in sexp_parser.values() {
+        let group = element_attributes(node, netlist.as_ref())?;
+        if let Some(attributes) = &group {
+            plotter.start_group(attributes);
+        }
+        if let Sexp::Node(name, _) = node {
+            if name == "version" || name == "generator" || 
+               name == "uuid" || name == "sheet_instances" || 
+               name == "symbol_instances" || name == "lib_symbols" {
+                //just skip those elements
+            } else if name == "paper" {
+                let (name, size) = paper::from_node(node)?;
+                plotter.paper(size);
+                paper_name = name;
+                paper_size = size;
+            } else if name == "title_block" {
+                title_block = Option::from(node.clone());
+            } else if name == "no_connect" {
+                no_connect(node, plotter, style)?;
+            } else if name == "text" {
+                text(node, plotter, style)?;
+            } else if name == "wire" {
+                let stroke: Stroke = 
+                    style.style(node, "stroke", StyleContext::SchemaWire)?;
+                let pts: Array2<f64> = node.get("pts")?;
+                plotter.push(
+                    PlotItem::LineItem(Line::new(pts, stroke.width, stroke.line_type, stroke.color)));
+            } else if name == "junction" {
+                let stroke: Stroke = style.style(node, "stroke", StyleContext::SchemaJunction)?;
+                let fill_color: Option<Color> = Some(stroke.color.clone());
+                let pos: Array1<f64> = get!(node, "at")?;
+                plotter.push(PlotItem::CircleItem(Circle::new(
+                    pos,
+                    0.3,
+                    0.1,
+                    stroke.line_type,
+                    stroke.color,
+                    fill_color,
+                )));
+            } else if name == "label" {
+                label(node, plotter, style)?;
+            } else if name == "global_label" {
+                global_label(node, plotter, style)?;
+            } else if name == "hierarchical_label" {
+                hierarchical_label(node, plotter, style)?;
+            } else if name == "sheet" {
+                sheet(node, plotter, style)?;
+            } else if name == "bus" {
+                let stroke: Stroke =
+                    style.style(node, "stroke", StyleContext::SchemaBus)?;
+                let pts: Array2<f64> = node.get("pts")?;
+                plotter.push(
+                    PlotItem::LineItem(Line::new(pts, stroke.width, stroke.line_type, stroke.color)));
+            } else if name == "bus_entry" {
+                bus_entry(node, plotter, style)?;
+            } else if name == "polyline" || name == "rectangle" || name == "circle" || name == "arc" {
+                graphic(node, plotter, style)?;
+            } else if name == "text_box" {
+                text_box(node, plotter, style)?;
+            } else if name == "image" {
+                image(node, plotter)?;
+            } else if name == "symbol" {
+                symbol(node, &libraries, plotter, style)?;
             } else {
-                panic!("wrong node");
+                //elements without a plot, like from newer KiCad versions, are skipped
             }
-        });
+        } else {
+            return Err(Error::ExpectSexpNode);
+        }
+        if group.is_some() {
+            plotter.end_group();
+        }
+    }
     
+    if let Some(op) = op {
+        plotter.start_group(&[(String::from("type"), String::from("operating_point"))]);
+        operating_point(sexp_parser, op, plotter, style)?;
+        plotter.end_group();
+    }
     if border {
-        draw_border(title_block, paper_size, plotter, &style)?;
+        plotter.start_group(&[(String::from("type"), String::from("border"))]);
+        draw_border(title_block, &paper_name, paper_size, plotter, style)?;
+        plotter.end_group();
     }
-    let file = Box::from(File::create(filename.unwrap()).unwrap());
-    plotter.plot(file, border, 1.0);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schematic;
+
+    /// Collect the plot items.
+    struct Recorder {
+        items: Vec<PlotItem>,
+    }
+    impl Plotter for Recorder {
+        fn push(&mut self, item: PlotItem) {
+            self.items.push(item);
+        }
+        fn text_size(&self, _item: &Text) -> Array1<f64> {
+            arr1(&[0.0, 0.0])
+        }
+        fn bounds(&self) -> Array2<f64> {
+            Array2::default((0, 2))
+        }
+        fn paper(&mut self, _paper_size: (f64, f64)) {}
+        fn new_page(&mut self) {}
+        fn background(&mut self, _color: Color) {}
+        fn start_group(&mut self, _attributes: &[(String, String)]) {}
+        fn end_group(&mut self) {}
+    }
+
+    fn captions(recorder: &Recorder) -> Vec<(String, f64)> {
+        recorder
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlotItem::TextItem(text) if text.text.starts_with("Unit") => Some((text.text.clone(), text.pos[0])),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plot_units() {
+        let schematic = Schematic::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let tl072 = schematic.lib_symbol("Amplifier_Operational:TL072").unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        library_symbol(&mut recorder, tl072, None, None, &Style::new()).unwrap();
+        let units = captions(&recorder);
+        let names: Vec<&str> = units.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Unit A", "Unit B", "Unit C"]);
+        assert!(units[0].1 < units[1].1 && units[1].1 < units[2].1);
+
+        let mut recorder = Recorder { items: Vec::new() };
+        library_symbol(&mut recorder, tl072, Some(3), None, &Style::new()).unwrap();
+        assert!(captions(&recorder).is_empty());
+        assert!(!recorder.items.is_empty());
+    }
+
+    #[test]
+    fn hierarchy() {
+        let names = |pages: &[Page]| -> Vec<String> { pages.iter().map(|page| page.filename.clone()).collect() };
+        assert_eq!(
+            names(&pages("samples/files/summe/summe.kicad_sch").unwrap()),
+            vec![String::from("samples/files/summe/summe.kicad_sch")]
+        );
+
+        let sheet = |name: &str, file: &str| {
+            format!(
+                r#"(sheet (at 10 10) (size 20 10) (uuid "{0}")
+    (property "Sheet name" "{0}" (id 0) (at 10 9 0) (effects (font (size 1.27 1.27))))
+    (property "Sheet file" "{1}" (id 1) (at 10 21 0) (effects (font (size 1.27 1.27)))))"#,
+                name, file
+            )
+        };
+        let schematic = |sheets: &str| {
+            format!(
+                "(kicad_sch (version 20211123) (generator eeschema) (paper \"A4\") (lib_symbols) {})",
+                sheets
+            )
+        };
+        let directory = std::env::temp_dir().join("elektron-hierarchy");
+        std::fs::create_dir_all(directory.join("sub")).unwrap();
+        std::fs::write(
+            directory.join("main.kicad_sch"),
+            schematic(&(sheet("a", "sub/power.kicad_sch") + &sheet("b", "sub/power.kicad_sch") + &sheet("d", "power.kicad_sch"))),
+        ).unwrap();
+        std::fs::write(directory.join("sub/power.kicad_sch"), schematic(&sheet("c", "filter.kicad_sch"))).unwrap();
+        std::fs::write(directory.join("sub/filter.kicad_sch"), schematic("")).unwrap();
+        std::fs::write(directory.join("power.kicad_sch"), schematic("")).unwrap();
+        std::fs::write(directory.join("loop.kicad_sch"), schematic(&sheet("e", "loop.kicad_sch"))).unwrap();
+
+        //every sheet has a page, also when the sheets share the file
+        let pages = pages(&directory.join("main.kicad_sch").to_string_lossy()).unwrap();
+        let paths: Vec<(String, &str, usize, usize)> = pages
+            .iter()
+            .map(|page| {
+                let file = Path::new(&page.filename).strip_prefix(&directory).unwrap().to_string_lossy().to_string();
+                (file, page.path.as_str(), page.number, page.count)
+            })
+            .collect();
+        let page = |file: &str, path: &'static str, number: usize| (file.to_string(), path, number, 6);
+        assert_eq!(
+            paths,
+            vec![
+                page("main.kicad_sch", "/", 1),
+                page("sub/power.kicad_sch", "/a/", 2),
+                page("sub/filter.kicad_sch", "/a/c/", 3),
+                page("sub/power.kicad_sch", "/b/", 4),
+                page("sub/filter.kicad_sch", "/b/c/", 5),
+                page("power.kicad_sch", "/d/", 6),
+            ]
+        );
+        assert!(matches!(
+            super::pages(&directory.join("loop.kicad_sch").to_string_lossy()),
+            Err(Error::IoError(_))
+        ));
+
+        //the files of the pages do not collide
+        let files: Vec<String> = page_outputs(OutputFormat::Svg, "out/main.svg", &pages)
+            .into_iter()
+            .map(|(file, pages)| {
+                assert_eq!(pages.len(), 1);
+                file
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec!["out/main.svg", "out/main-a.svg", "out/main-a-c.svg", "out/main-b.svg", "out/main-b-c.svg", "out/main-d.svg"]
+        );
+        let png = page_outputs(OutputFormat::Png, "main.png", &pages);
+        assert_eq!(png.len(), 6);
+        assert_eq!(png[5].0, "main-d.png");
+        let pdf = page_outputs(OutputFormat::Pdf, "out/main.pdf", &pages);
+        assert_eq!(pdf.len(), 1);
+        assert_eq!(pdf[0].0, "out/main.pdf");
+        assert_eq!(pdf[0].1.len(), 6);
+        assert_eq!(page_outputs(OutputFormat::Html, "main.html", &pages)[0].1.len(), 6);
+    }
+
+    #[test]
+    fn op_current_pin() {
+        let doc: SexpParser = r#"(kicad_sch
+  (lib_symbols
+    (symbol "Device:R"
+      (symbol "R_1_1"
+        (pin passive line (at 0 -3.81 90) (length 1.27) (name "~" (effects (font (size 1.27 1.27)))) (number "2" (effects (font (size 1.27 1.27)))))
+        (pin passive line (at 0 3.81 270) (length 1.27) (name "~" (effects (font (size 1.27 1.27)))) (number "1" (effects (font (size 1.27 1.27))))))))
+  (symbol (lib_id "Device:R") (at 10 10 0) (unit 1)
+    (property "Reference" "R1" (id 0) (at 12 10 0) (effects (font (size 1.27 1.27)))))
+  (symbol (lib_id "Device:R") (at 20 10 0) (unit 1)
+    (property "Reference" "R2" (id 0) (at 22 10 0) (effects (font (size 1.27 1.27))))
+    (property "Spice_Node_Sequence" "2 1" (id 1) (at 22 10 0) (effects (font (size 1.27 1.27))))))"#
+            .parse()
+            .unwrap();
+        let libraries = libraries(&doc).unwrap();
+        let number = |index: usize| -> String {
+            let node = doc.values().filter(|node| matches!(node, Sexp::Node(name, _) if name == "symbol")).nth(index).unwrap();
+            let pins = iterate_unit_pins(node, &libraries).unwrap();
+            get!(current_pin(node, &pins).unwrap(), "number", 0).unwrap()
+        };
+        assert_eq!(number(0), "1");
+        assert_eq!(number(1), "2");
+    }
+
+    #[test]
+    fn plot_de_morgan() {
+        let doc: SexpParser = r#"(kicad_symbol_lib
+  (symbol "Test:INV" (in_bom yes) (on_board yes)
+    (property "Reference" "U" (id 0) (at 0 5.08 0) (effects (font (size 1.27 1.27))))
+    (symbol "INV_1_1"
+      (rectangle (start -2.54 2.54) (end 2.54 -2.54) (stroke (width 0.254) (type default) (color 0 0 0 0)) (fill (type none))))
+    (symbol "INV_1_2"
+      (circle (center 0 0) (radius 2.54) (stroke (width 0.254) (type default) (color 0 0 0 0)) (fill (type none))))))"#
+            .parse()
+            .unwrap();
+        let symbol = LibrarySymbol::try_from(doc.values().next().unwrap()).unwrap();
+        assert!(symbol.has_de_morgan());
+
+        let mut recorder = Recorder { items: Vec::new() };
+        library_symbol(&mut recorder, &symbol, Some(1), Some(2), &Style::new()).unwrap();
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::CircleItem(_))));
+        assert!(!recorder.items.iter().any(|item| matches!(item, PlotItem::RectangleItem(_))));
+
+        let mut recorder = Recorder { items: Vec::new() };
+        library_symbol(&mut recorder, &symbol, None, None, &Style::new()).unwrap();
+        let names: Vec<String> = captions(&recorder).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["Unit A", "Unit A, De Morgan"]);
+        //the reference is moved to the schematic coordinates
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::TextItem(text) if text.text == "U" && text.pos[1] == -5.08)));
+    }
+
+    #[test]
+    fn plot_library_text() {
+        let doc: SexpParser = r#"(kicad_symbol_lib
+  (symbol "Test:AMP" (in_bom yes) (on_board yes)
+    (symbol "AMP_0_1"
+      (text "+" (at -1.27 2.54 0) (effects (font (size 1.27 1.27))))
+      (text "hidden" (at 0 0 0) (effects (font (size 1.27 1.27)) hide)))))"#
+            .parse()
+            .unwrap();
+        let symbol = LibrarySymbol::try_from(doc.values().next().unwrap()).unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        library_symbol(&mut recorder, &symbol, Some(1), Some(1), &Style::new()).unwrap();
+        let texts: Vec<&Text> = recorder
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlotItem::TextItem(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].text, "+");
+        //the library y axis points up
+        assert_eq!(texts[0].pos[1], -2.54);
+    }
+
+    #[test]
+    fn plot_symbol_styles() {
+        let doc: SexpParser = r#"(kicad_symbol_lib
+  (symbol "Test:PIN" (in_bom yes) (on_board yes)
+    (symbol "PIN_1_1"
+      (polyline (pts (xy 0 0) (xy 2.54 0)) (stroke (width 0) (type dash) (color 0 0 0 0)) (fill (type none)))
+      (arc (start 0 1) (mid 1 0) (end 0 -1) (stroke (width 0) (type dot) (color 0 0 0 0)) (fill (type none)))
+      (pin passive line (at 0 0 0) (length 2.54)
+        (name "IN" (effects (font (size 1.27 1.27)) hide))
+        (number "1" (effects (font (size 2 2))))))))"#
+            .parse()
+            .unwrap();
+        let symbol = LibrarySymbol::try_from(doc.values().next().unwrap()).unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        library_symbol(&mut recorder, &symbol, Some(1), Some(1), &Style::new()).unwrap();
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::PolylineItem(polyline) if polyline.linetype == LineType::Dash)));
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::ArcItem(arc) if arc.linetype == LineType::Dot)));
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::TextItem(text) if text.text == "1" && text.fontsize == 2.0)));
+        assert!(!recorder.items.iter().any(|item| matches!(item, PlotItem::TextItem(text) if text.text == "IN")));
+    }
+
+    #[test]
+    fn plot_elements() {
+        let doc: SexpParser = r#"(kicad_sch (version 20211123) (generator eeschema) (paper "A4") (lib_symbols)
+  (global_label "OUT" (shape output) (at 100 50 0) (effects (font (size 1.27 1.27)) (justify left))
+    (property "Intersheet References" "${INTERSHEET_REFS}" (id 0) (at 110 50 0) (effects (font (size 1.27 1.27)) hide)))
+  (global_label "IN" (shape input) (at 100 60 180) (effects (font (size 1.27 1.27)) (justify right)))
+  (hierarchical_label "BIDI" (shape bidirectional) (at 50 50 0) (effects (font (size 1.27 1.27)) (justify left)))
+  (sheet (at 10 10) (size 20 10) (stroke (width 0) (type solid) (color 0 0 0 0)) (fill (color 255 255 194 1))
+    (property "Sheet name" "filter" (id 0) (at 10 9.5 0) (effects (font (size 1.27 1.27)) (justify left bottom)))
+    (property "Sheet file" "filter.kicad_sch" (id 1) (at 10 20.5 0) (effects (font (size 1.27 1.27)) (justify left top)))
+    (pin "IN" input (at 10 15 180) (effects (font (size 1.27 1.27)) (justify left))))
+  (bus (pts (xy 20 20) (xy 40 20)) (stroke (width 0) (type default) (color 0 0 0 0)))
+  (bus_entry (at 25 20) (size 2.54 2.54) (stroke (width 0) (type default) (color 0 0 0 0)))
+  (polyline (pts (xy 0 0) (xy 10 0) (xy 10 10)) (stroke (width 0) (type dash) (color 0 0 0 0)))
+  (rectangle (start 0 0) (end 5 5) (stroke (width 0.2) (type default) (color 0 0 0 0)) (fill (type background)))
+  (circle (center 5 5) (radius 2) (stroke (width 0) (type default) (color 0 0 0 0)) (fill (type none)))
+  (arc (start 0 0) (mid 1 1) (end 2 0) (stroke (width 0) (type default) (color 0 0 0 0)) (fill (type none)))
+  (text_box "note" (at 60 60 0) (size 20 10) (stroke (width 0) (type default) (color 0 0 0 0)) (fill (type none))
+    (effects (font (size 1.27 1.27)) (justify left top)))
+  (image (at 70 70) (scale 2)
+    (data "iVBORw0KGgoAAAANSUhEUgAAAAMAAAACCAIAAAASFvFNAAAAEElEQVR4nGP4z8AAQQxw"
+      "FgBB0gX7h/C5SAAAAABJRU5ErkJggg==")))"#
+            .parse()
+            .unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        plot_items(&mut recorder, &doc, false, &Style::new(), None).unwrap();
+
+        let polylines: Vec<&Polyline> = recorder
+            .items
+            .iter()
+            .filter_map(|item| if let PlotItem::PolylineItem(polyline) = item { Some(polyline) } else { None })
+            .collect();
+        let has_point = |polyline: &Polyline, x: f64, y: f64| {
+            polyline.pts.rows().into_iter().any(|row| (row[0] - x).abs() < 0.01 && (row[1] - y).abs() < 0.01)
+        };
+        //the output label has the tip at the end, the input label at the connection
+        assert!(has_point(polylines[0], 100.0, 48.984) && has_point(polylines[0], 101.778, 50.0));
+        assert!(has_point(polylines[1], 100.0, 60.0) && has_point(polylines[1], 98.222, 61.016));
+        //the bidirectional hierarchical label is a diamond
+        assert_eq!(polylines[2].pts.nrows(), 7);
+        assert!(has_point(polylines[2], 50.0, 50.0) && has_point(polylines[2], 51.27, 50.0));
+        //the sheet pin points into the sheet
+        assert!(has_point(polylines[3], 10.0, 15.635) && has_point(polylines[3], 11.27, 15.0));
+        assert_eq!(polylines[4].pts.nrows(), 3);
+
+        let texts: Vec<(&str, f64, f64, &Vec<Justify>)> = recorder
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlotItem::TextItem(text) => Some((text.text.as_str(), text.pos[0], text.pos[1], &text.align)),
+                _ => None,
+            })
+            .collect();
+        let names: Vec<&str> = texts.iter().map(|text| text.0).collect();
+        assert_eq!(names, vec!["OUT", "IN", "BIDI", "filter", "File: filter.kicad_sch", "IN", "note"]);
+        assert_eq!(texts[0].3, &vec![Justify::Left]);
+        assert!((texts[1].1 - 98.603).abs() < 0.01);
+        assert_eq!(texts[1].3, &vec![Justify::Right]);
+        assert!((texts[5].1 - 11.651).abs() < 0.01);
+
+        let sheet = recorder.items.iter().find_map(|item| match item {
+            PlotItem::RectangleItem(rectangle) if rectangle.pts[[0, 0]] == 10.0 => Some(rectangle),
+            _ => None,
+        });
+        assert_eq!(sheet.unwrap().fill, Some(Color { r: 1.0, g: 1.0, b: 194.0 / 255.0, a: 1.0 }));
+        let lines: Vec<&Line> = recorder
+            .items
+            .iter()
+            .filter_map(|item| if let PlotItem::LineItem(line) = item { Some(line) } else { None })
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].color, Style::new().stroke(&StyleContext::SchemaBus).color);
+        assert_eq!(lines[1].pts, arr2(&[[25.0, 20.0], [27.54, 22.54]]));
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::CircleItem(circle) if circle.radius == 2.0)));
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::ArcItem(_))));
+        assert_eq!(recorder.items.iter().filter(|item| matches!(item, PlotItem::RectangleItem(_))).count(), 3);
+
+        let image = recorder.items.iter().find_map(|item| match item {
+            PlotItem::ImageItem(image) => Some(image),
+            _ => None,
+        });
+        let image = image.unwrap();
+        assert!((image.width - 0.508).abs() < 0.001 && (image.height - 0.3387).abs() < 0.001);
+        assert!((image.pos[0] - (70.0 - 0.254)).abs() < 0.001);
+        assert_eq!(&image.data[1..4], b"PNG");
+    }
+
+    #[test]
+    fn plot_errors() {
+        //broken images are reported and do not panic
+        let doc: SexpParser = "(kicad_sch (version 20211123) (paper \"A4\") (lib_symbols)\n  (image (at 70 50) (data \"iVBO*\")))"
+            .parse()
+            .unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        assert!(matches!(plot_items(&mut recorder, &doc, false, &Style::new(), None), Err(Error::ValueError(_))));
+        //unknown elements are skipped
+        let doc: SexpParser = "(kicad_sch (version 20211123) (paper \"A4\") (lib_symbols)\n  (future_element (at 70 50)))"
+            .parse()
+            .unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        plot_items(&mut recorder, &doc, false, &Style::new(), None).unwrap();
+        assert!(recorder.items.is_empty());
+        //the output file is required
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let svg = crate::svg_plotter::SvgPlotter::new();
+        assert!(matches!(plot(&svg, None, &doc, false, Style::new(), None), Err(Error::IoError(_))));
+    }
+
+    #[test]
+    fn net_names() {
+        use crate::plotter::display_list::Node;
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let nets = |display_list: &DisplayList| -> Vec<String> {
+            display_list.pages()[0].nodes.iter().filter_map(|node| match node {
+                Node::Group(group) => group.attribute("net").map(String::from),
+                _ => None,
+            }).collect()
+        };
+        let mut display_list = DisplayList::new();
+        plot_items(&mut display_list, &doc, false, &Style::new(), None).unwrap();
+        assert!(nets(&display_list).contains(&String::from("IN_1")));
+        //the netlist is not built for backends without net names
+        let mut display_list = DisplayList::new();
+        display_list.set_net_names(false);
+        plot_items(&mut display_list, &doc, false, &Style::new(), None).unwrap();
+        assert!(nets(&display_list).is_empty());
+    }
+
+    #[test]
+    fn plot_schematic() {
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        plot_items(&mut recorder, &doc, true, &Style::new(), None).unwrap();
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::TextItem(text) if text.text == "R3")));
+    }
+
+    #[test]
+    fn plot_symbol_orientation() {
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        plot_items(&mut recorder, &doc, false, &Style::new(), None).unwrap();
+        let close = |pts: &Array2<f64>, expected: Array2<f64>| {
+            pts.iter().zip(expected.iter()).all(|(a, b)| (a - b).abs() < 1e-9)
+        };
+        //R3 is rotated by 270 degrees at (73.66 43.18)
+        assert!(recorder.items.iter().any(|item| matches!(item,
+            PlotItem::RectangleItem(rectangle) if close(&rectangle.pts, arr2(&[[71.12, 42.164], [76.2, 44.196]])))));
+        let reference = recorder.items.iter().find_map(|item| match item {
+            PlotItem::TextItem(text) if text.text == "R3" => Some(text),
+            _ => None,
+        });
+        assert_eq!(reference.unwrap().angle, 0.0);
+        //the pin line starts at the pin position and points to the body
+        assert!(recorder.items.iter().any(|item| matches!(item,
+            PlotItem::LineItem(line) if close(&line.pts, arr2(&[[69.85, 43.18], [71.12, 43.18]])))));
+    }
+
+    #[test]
+    fn paper_sizes() {
+        let paper = |content: &str| {
+            let doc: SexpParser = format!("(kicad_sch {})", content).parse().unwrap();
+            let result = paper::from_node(doc.values().next().unwrap());
+            result
+        };
+        assert_eq!(paper(r#"(paper "A4")"#).unwrap(), (String::from("A4"), (297.0, 210.0)));
+        assert_eq!(paper(r#"(paper "A3" portrait)"#).unwrap(), (String::from("A3"), (297.0, 420.0)));
+        assert_eq!(paper(r#"(paper "USLetter")"#).unwrap(), (String::from("USLetter"), (279.4, 215.9)));
+        assert_eq!(paper(r#"(paper "User" 500 250.5)"#).unwrap(), (String::from("User"), (500.0, 250.5)));
+        assert!(matches!(paper(r#"(paper "A7")"#), Err(Error::ValueError(_))));
+
+        let doc: SexpParser = r#"(kicad_sch (version 20211123) (generator eeschema) (paper "A0" portrait) (lib_symbols)
+  (title_block (title "Portrait")))"#
+            .parse()
+            .unwrap();
+        let mut recorder = Recorder { items: Vec::new() };
+        plot_items(&mut recorder, &doc, true, &Style::new(), None).unwrap();
+        assert!(matches!(&recorder.items[0],
+            PlotItem::RectangleItem(rectangle) if rectangle.pts == arr2(&[[5.0, 5.0], [836.0, 1184.0]])));
+        assert!(recorder.items.iter().any(|item| matches!(item, PlotItem::TextItem(text) if text.text == "Paper: A0")));
+
+        let doc: SexpParser = r#"(kicad_sch (version 20211123) (paper "A7") (lib_symbols))"#.parse().unwrap();
+        assert!(matches!(plot_items(&mut recorder, &doc, true, &Style::new(), None), Err(Error::ValueError(_))));
+    }
+
+    #[test]
+    fn plot_drawing_sheet() {
+        let sheet: DrawingSheet = r#"(kicad_wks (version 20210606) (generator pl_editor)
+  (setup (textsize 1.5 1.5) (linewidth 0.15) (textlinewidth 0.15)
+    (left_margin 10) (right_margin 10) (top_margin 10) (bottom_margin 10))
+  (rect (name "") (start 110 34) (end 2 2))
+  (rect (name "") (start 0 0 ltcorner) (end 0 0) (repeat 2) (incrx 2) (incry 2))
+  (line (name "") (start 50 2 ltcorner) (end 50 0 ltcorner) (repeat 30) (incrx 50))
+  (tbtext "1" (name "") (pos 25 1 ltcorner) (font (size 1.3 1.3)) (repeat 100) (incrx 50))
+  (tbtext "A" (name "") (pos 1 12.5 lbcorner) (justify center) (repeat 100) (incry 25))
+  (tbtext "Title: ${TITLE}" (name "") (pos 109 10.7) (font (size 2 2) bold italic))
+  (tbtext "Id: ${#}/${##}" (name "") (pos 24 4.1) (option page1only))
+  (bitmap (name "") (pos 40 40) (scale 1) (repeat 2) (pngdata (data "89 50 4E 47")))
+  (polygon (name "") (pos 20 20 rtcorner) (rotate 90) (pts (xy 0 0) (xy 2 0) (xy 2 1))))"#
+            .parse()
+            .unwrap();
+        let doc: SexpParser = r#"(kicad_sch (version 20211123) (generator eeschema) (paper "A4") (lib_symbols)
+  (title_block (title "Summe")))"#
+            .parse()
+            .unwrap();
+        let page = |number: usize| Page {
+            filename: String::from("summe.kicad_sch"),
+            name: String::new(),
+            path: String::from("/"),
+            number,
+            count: 2,
+        };
+        let mut recorder = Recorder { items: Vec::new() };
+        frame(&mut recorder, &doc, &page(1), Some(&sheet), &std::collections::HashMap::new(), &Style::new()).unwrap();
+
+        let rectangles: Vec<&Array2<f64>> = recorder
+            .items
+            .iter()
+            .filter_map(|item| if let PlotItem::RectangleItem(rectangle) = item { Some(&rectangle.pts) } else { None })
+            .collect();
+        assert_eq!(rectangles, vec![
+            &arr2(&[[177.0, 166.0], [285.0, 198.0]]),
+            &arr2(&[[10.0, 10.0], [287.0, 200.0]]),
+            &arr2(&[[12.0, 12.0], [285.0, 198.0]]),
+        ]);
+        //the repeated items stop at the page margins
+        let lines = recorder.items.iter().filter(|item| matches!(item, PlotItem::LineItem(_))).count();
+        assert_eq!(lines, 5);
+        let texts: Vec<(&str, f64, f64)> = recorder
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlotItem::TextItem(text) => Some((text.text.as_str(), text.pos[0], text.pos[1])),
+                _ => None,
+            })
+            .collect();
+        let columns: Vec<&str> = texts.iter().filter(|text| text.2 == 11.0).map(|text| text.0).collect();
+        assert_eq!(columns, vec!["1", "2", "3", "4", "5", "6"]);
+        let rows: Vec<&str> = texts.iter().filter(|text| text.1 == 11.0).map(|text| text.0).collect();
+        assert_eq!(rows, vec!["A", "B", "C", "D", "E", "F", "G", "H"]);
+        assert!(texts.contains(&("Title: Summe", 178.0, 189.3)));
+        assert!(texts.contains(&("Id: 1/2", 263.0, 195.9)));
+        let polygon = recorder.items.iter().find_map(|item| match item {
+            PlotItem::PolylineItem(polyline) => Some(polyline),
+            _ => None,
+        });
+        let polygon = polygon.unwrap();
+        assert!(polygon.pts.iter().zip([267.0, 30.0, 267.0, 28.0, 268.0, 28.0].iter()).all(|(a, b)| (a - b).abs() < 1e-9));
+        assert!(polygon.fill.is_some());
+
+        let mut recorder = Recorder { items: Vec::new() };
+        frame(&mut recorder, &doc, &page(2), Some(&sheet), &std::collections::HashMap::new(), &Style::new()).unwrap();
+        assert!(!recorder.items.iter().any(|item| matches!(item, PlotItem::TextItem(text) if text.text.starts_with("Id:"))));
+    }
+}
@@ -0,0 +1,389 @@
+//! The plot model of the schematic.
+//!
+//! The plot functions push the items to a [`Plotter`], normally a
+//! [`DisplayList`]. The display list is written to the file by a [`Backend`],
+//! the backends only convert the items and do not collect them.
+use std::io::Write;
+use ndarray::{arr1, arr2, Array1, Array2};
+use crate::sexp::{Color, Effects, Justify, LineType};
+use crate::font::{self, Font, Strokes};
+use crate::geometry::{sin_cos, ArcGeometry};
+use crate::Error;
+
+pub mod display_list;
+
+pub use display_list::DisplayList;
+
+#[derive(Debug)]
+pub struct Line {
+    pub pts: Array2<f64>,
+    pub linewidth: f64,
+    pub linetype: LineType,
+    pub color: Color
+}
+impl Line {
+    pub fn new(pts: Array2<f64>, linewidth: f64, linetype: LineType, color: Color) -> Line {
+        Line {
+            pts,
+            linewidth,
+            linetype,
+            color
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Rectangle {
+    pub pts: Array2<f64>,
+    pub color: Color,
+    pub linewidth: f64,
+    pub linetype: LineType,
+    pub fill: Option<Color>,
+}
+impl Rectangle {
+    pub fn new(pts: Array2<f64>, color: Color, linewidth: f64, linetype: LineType, fill: Option<Color>) -> Rectangle {
+        Rectangle {
+            pts,
+            color,
+            linewidth,
+            linetype,
+            fill,
+        }
+    }
+}
+#[derive(Debug)]
+pub struct Arc {
+    pub start: Array1<f64>,
+    pub mid: Array1<f64>,
+    pub end: Array1<f64>,
+    pub linewidth: f64,
+    pub linetype: LineType,
+    pub color: Color,
+    pub fill: Option<Color>
+}
+impl Arc {
+    pub fn new(start: Array1<f64>, mid: Array1<f64>, end: Array1<f64>, linewidth: f64, linetype: LineType, color: Color, fill: Option<Color>) -> Arc {
+        Arc {
+            start,
+            mid,
+            end,
+            linewidth,
+            linetype,
+            color,
+            fill
+        }
+    }
+}
+#[derive(Debug)]
+pub struct Circle {
+    pub pos: Array1<f64>,
+    pub radius: f64,
+    pub linewidth: f64,
+    pub linetype: LineType,
+    pub color: Color,
+    pub fill: Option<Color>
+}
+impl Circle {
+    pub fn new(pos: Array1<f64>, radius: f64, linewidth: f64, linetype: LineType, color: Color, fill: Option<Color>) -> Circle {
+        Circle {
+            pos,
+            radius,
+            linewidth,
+            linetype,
+            color,
+            fill
+        }
+    }
+}
+#[derive(Debug)]
+pub struct Polyline {
+    pub pts: Array2<f64>,
+    pub color: Color,
+    pub linewidth: f64,
+    pub linetype: LineType,
+    pub fill: Option<Color>
+}
+impl Polyline {
+    pub fn new(pts: Array2<f64>, color: Color, linewidth: f64, linetype: LineType, fill: Option<Color>) -> Polyline {
+        Polyline {
+            pts,
+            color,
+            linewidth,
+            linetype,
+            fill
+        }
+    }
+}
+/// A text, the font size is the height and the width of the glyphs.
+///
+/// The text is drawn with the built-in stroke font, a thickness of zero
+/// selects the default pen width for the size.
+#[derive(Debug)]
+pub struct Text {
+    pub pos: Array1<f64>,
+    pub text: String,
+    pub color: Color,
+    pub fontsize: f64,
+    pub fontwidth: f64,
+    pub thickness: f64,
+    pub bold: bool,
+    pub italic: bool,
+    pub line_spacing: f64,
+    pub font: String,
+    pub align: Vec<Justify>,
+    pub angle: f64,
+}
+impl Text {
+    pub fn new(pos: Array1<f64>, angle: f64, text: String, color: Color, fontsize: f64, font: &str, align: Vec<Justify>) -> Text {
+        Text {
+            pos,
+            text,
+            color,
+            fontsize,
+            fontwidth: fontsize,
+            thickness: 0.0,
+            bold: false,
+            italic: false,
+            line_spacing: 1.0,
+            font: font.to_string(),
+            align,
+            angle,
+        }
+    }
+
+    /// Take the width, thickness, weight, slant and line spacing from the effects.
+    pub fn with_effects(mut self, effects: &Effects) -> Text {
+        if effects.width > 0.0 {
+            self.fontwidth = effects.width;
+        }
+        self.thickness = effects.thickness;
+        self.bold = effects.bold;
+        self.italic = effects.italic;
+        if effects.line_spacing > 0.0 {
+            self.line_spacing = effects.line_spacing;
+        }
+        self
+    }
+
+    /// Render the text with the stroke font.
+    pub fn strokes(&self) -> Strokes {
+        let font = Font {
+            width: self.fontwidth,
+            height: self.fontsize,
+            thickness: self.thickness,
+            bold: self.bold,
+            italic: self.italic,
+            line_spacing: self.line_spacing,
+        };
+        font::render(&self.text, &font, &self.align)
+    }
+}
+/// A PNG image, the position is the top left corner.
+#[derive(Debug)]
+pub struct Image {
+    pub pos: Array1<f64>,
+    pub width: f64,
+    pub height: f64,
+    pub data: Vec<u8>,
+}
+impl Image {
+    pub fn new(pos: Array1<f64>, width: f64, height: f64, data: Vec<u8>) -> Image {
+        Image {
+            pos,
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PlotItem {
+    ArcItem(Arc),
+    CircleItem(Circle),
+    LineItem(Line),
+    RectangleItem(Rectangle),
+    PolylineItem(Polyline),
+    TextItem(Text),
+    ImageItem(Image),
+}
+
+/// The offset of the top left corner of the text box from the text position.
+///
+/// The offset is in the direction of the text, it is rotated with the text.
+pub fn text_offset(text: &Text, outline: &Array1<f64>) -> Array1<f64> {
+    let x = if text.align.contains(&Justify::Right) {
+        -outline[0]
+    } else if text.align.contains(&Justify::Left) {
+        0.0
+    } else {
+        -outline[0] / 2.0
+    };
+    let y = if text.align.contains(&Justify::Top) {
+        0.0
+    } else if text.align.contains(&Justify::Bottom) {
+        -outline[1]
+    } else {
+        -outline[1] / 2.0
+    };
+    arr1(&[x, y])
+}
+
+
+/// Calculate the drawing area of the items, the area is empty without items.
+pub fn items_bounds<'a>(items: impl IntoIterator<Item = &'a PlotItem>) -> Array2<f64> {
+    let mut __bounds: Array2<f64> = Array2::default((0, 2));
+    for item in items {
+        let arr: Option<Array2<f64>> = match item {
+            PlotItem::ArcItem(arc) => {
+                Option::from(match ArcGeometry::new(&arc.start, &arc.mid, &arc.end) {
+                    Some(geometry) => geometry.bounds(),
+                    None => arr2(&[[arc.start[0], arc.start[1]], [arc.end[0], arc.end[1]]]),
+                })
+            },
+            PlotItem::LineItem(line) => {
+                Option::from(arr2(&[[line.pts[[0, 0]], line.pts[[0, 1]]], 
+                     [line.pts[[1, 0]], line.pts[[1, 1]]]]))
+            },
+            PlotItem::TextItem(text) => {
+                let strokes = text.strokes();
+                let outline = arr1(&[strokes.width, strokes.height]);
+                let offset = text_offset(text, &outline);
+                let corners = arr2(&[
+                    [offset[0], offset[1]],
+                    [offset[0] + outline[0], offset[1] + outline[1]],
+                ]);
+                let (sin, cos) = sin_cos(text.angle);
+                let rotation = arr2(&[[cos, -sin], [sin, cos]]);
+                arr_outline(&(corners.dot(&rotation) + &text.pos))
+            },
+            PlotItem::CircleItem(circle) => {
+                Option::from(arr2(&[[circle.pos[0] - circle.radius, circle.pos[1] - circle.radius], 
+                       [circle.pos[0] + circle.radius, circle.pos[1] + circle.radius]]))
+            },
+            PlotItem::PolylineItem(polyline) => {
+                arr_outline(&polyline.pts)
+            },
+            PlotItem::RectangleItem(rect) => {
+                Option::from(arr2(&[[rect.pts[[0, 0]], rect.pts[[0, 1]]], 
+                     [rect.pts[[1, 0]], rect.pts[[1, 1]]]]))
+            },
+            PlotItem::ImageItem(image) => {
+                Option::from(arr2(&[[image.pos[0], image.pos[1]],
+                     [image.pos[0] + image.width, image.pos[1] + image.height]]))
+            },
+        };
+        if let Some(array) = arr {
+            for row in array.rows() {
+                __bounds.push_row(row).unwrap();
+            }
+        }
+    }
+    arr_outline(&__bounds).unwrap_or_else(|| Array2::zeros((2, 2)))
+}
+
+/// The bounding box of the points, `NaN` coordinates are ignored.
+fn arr_outline(boxes: &Array2<f64>) -> Option<Array2<f64>> {
+    if boxes.is_empty() {
+        return None;
+    }
+    let min = |axis: usize| boxes.column(axis).iter().fold(f64::INFINITY, |a, b| f64::min(a, *b));
+    let max = |axis: usize| boxes.column(axis).iter().fold(f64::NEG_INFINITY, |a, b| f64::max(a, *b));
+    Some(arr2(&[[min(0), min(1)], [max(0), max(1)]]))
+}
+
+/// The output file format of the plot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Svg,
+    Pdf,
+    Png,
+    Html,
+}
+
+impl OutputFormat {
+    /// Get the format from the extension of the filename.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let extension = std::path::Path::new(filename).extension()?.to_str()?;
+        extension.to_lowercase().parse().ok()
+    }
+
+    /// Select the format by the name, by the extension of the output or SVG.
+    pub fn select(format: Option<&str>, filename: Option<&str>) -> Result<Self, Error> {
+        match (format, filename) {
+            (Some(format), _) => format.parse(),
+            (None, Some(filename)) => Ok(Self::from_filename(filename).unwrap_or(OutputFormat::Svg)),
+            (None, None) => Ok(OutputFormat::Svg),
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = Error;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "svg" => Ok(OutputFormat::Svg),
+            "pdf" => Ok(OutputFormat::Pdf),
+            "png" => Ok(OutputFormat::Png),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(Error::ValueError(format!("unknown output format: {}", format))),
+        }
+    }
+}
+
+/// Collects the items of the plot.
+pub trait Plotter {
+    fn push(&mut self, item: PlotItem);
+    /// Get the size of the text box in mm.
+    fn text_size(&self, item: &Text) -> Array1<f64>;
+    /// Calculate the drawing area of the current page.
+    fn bounds(&self) -> Array2<f64>;
+    /// Set the paper size in mm for the current page.
+    fn paper(&mut self, paper_size: (f64, f64));
+    /// Finish the current page, the following items are drawn on a new page.
+    fn new_page(&mut self);
+    /// Set the color of the page background.
+    fn background(&mut self, color: Color);
+    /// Start a group with the items of a schematic element, the attributes describe the element.
+    fn start_group(&mut self, attributes: &[(String, String)]);
+    /// Close the current group.
+    fn end_group(&mut self);
+    /// Are the net names of the elements added to the group attributes.
+    fn net_names(&self) -> bool {
+        false
+    }
+}
+
+/// Writes the display list to the output file.
+pub trait Backend {
+    fn write(&self, display_list: &DisplayList, file: Box<dyn Write>, border: bool, scale: f64) -> Result<(), Error>;
+    /// Does the output use the net names of the groups, the netlist is only built for these backends.
+    fn net_names(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_format() {
+        assert_eq!(OutputFormat::select(None, None).unwrap(), OutputFormat::Svg);
+        assert_eq!(OutputFormat::select(None, Some("out/main.PDF")).unwrap(), OutputFormat::Pdf);
+        assert_eq!(OutputFormat::select(None, Some("main.png")).unwrap(), OutputFormat::Png);
+        assert_eq!(OutputFormat::select(None, Some("main.html")).unwrap(), OutputFormat::Html);
+        assert_eq!(OutputFormat::select(None, Some("main")).unwrap(), OutputFormat::Svg);
+        assert_eq!(OutputFormat::select(None, Some("main.txt")).unwrap(), OutputFormat::Svg);
+        //the format name wins over the extension
+        assert_eq!(OutputFormat::select(Some("pdf"), Some("main.svg")).unwrap(), OutputFormat::Pdf);
+        assert!(matches!(OutputFormat::select(Some("jpg"), None), Err(Error::ValueError(_))));
+    }
+
+    #[test]
+    fn outline() {
+        assert_eq!(arr_outline(&arr2(&[[1.0, 4.0], [3.0, f64::NAN], [-2.0, 2.0]])), Some(arr2(&[[-2.0, 2.0], [3.0, 4.0]])));
+        assert_eq!(arr_outline(&Array2::zeros((0, 2))), None);
+        assert_eq!(items_bounds(&[]), Array2::<f64>::zeros((2, 2)));
+    }
+}
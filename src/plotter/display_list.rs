@@ -0,0 +1,301 @@
+//! The backend-neutral display list.
+//!
+//! The items of a page are collected in the groups of the schematic elements.
+//! Each group is drawn on a layer, the layers define the z-order of the groups.
+//! Items and groups on the same layer are drawn in the order they are pushed.
+use ndarray::{arr1, arr2, Array1, Array2};
+
+use super::{items_bounds, PlotItem, Plotter, Text};
+use crate::plot::paper;
+use crate::sexp::Color;
+
+/// The layers of the plot, from bottom to top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Border,
+    Graphics,
+    Symbols,
+    Wires,
+    Labels,
+    Overlay,
+}
+
+impl Layer {
+    /// Get the layer for the type of a schematic element.
+    pub fn of(element: &str) -> Layer {
+        match element {
+            "border" => Layer::Border,
+            "symbol" => Layer::Symbols,
+            "wire" | "bus" | "bus_entry" | "junction" | "no_connect" => Layer::Wires,
+            "label" | "global_label" | "hierarchical_label" => Layer::Labels,
+            "operating_point" | "highlight" => Layer::Overlay,
+            _ => Layer::Graphics,
+        }
+    }
+}
+
+/// The items of a schematic element.
+#[derive(Debug)]
+pub struct Group {
+    pub attributes: Vec<(String, String)>,
+    pub layer: Layer,
+    pub nodes: Vec<Node>,
+}
+
+impl Group {
+    /// Get the value of an attribute.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub enum Node {
+    Item(Box<PlotItem>),
+    Group(Group),
+}
+
+impl Node {
+    /// The layer of the node, items outside of a group are graphics.
+    pub fn layer(&self) -> Layer {
+        match self {
+            Node::Item(_) => Layer::Graphics,
+            Node::Group(group) => group.layer,
+        }
+    }
+
+    fn collect<'a>(&'a self, items: &mut Vec<&'a PlotItem>) {
+        match self {
+            Node::Item(item) => items.push(item),
+            Node::Group(group) => group.nodes.iter().for_each(|node| node.collect(items)),
+        }
+    }
+}
+
+/// A page of the plot.
+#[derive(Debug)]
+pub struct Page {
+    pub paper_size: (f64, f64),
+    pub nodes: Vec<Node>,
+}
+
+impl Page {
+    fn new(paper_size: (f64, f64)) -> Self {
+        Page { paper_size, nodes: Vec::new() }
+    }
+
+    /// The nodes in drawing order.
+    pub fn layers(&self) -> Vec<&Node> {
+        let mut nodes: Vec<&Node> = self.nodes.iter().collect();
+        nodes.sort_by_key(|node| node.layer());
+        nodes
+    }
+
+    /// The items in drawing order.
+    pub fn items(&self) -> Vec<&PlotItem> {
+        let mut items = Vec::new();
+        for node in self.layers() {
+            node.collect(&mut items);
+        }
+        items
+    }
+
+    /// The top left point and the size of the drawing in mm.
+    ///
+    /// The area is the paper with a border, otherwise the bounds of the items
+    /// with a margin.
+    pub fn area(&self, border: bool) -> (Array1<f64>, f64, f64) {
+        let items = self.items();
+        if border || items.is_empty() {
+            (arr1(&[0.0, 0.0]), self.paper_size.0, self.paper_size.1)
+        } else {
+            let bounds = items_bounds(items) + arr2(&[[-2.54, -2.54], [2.54, 2.54]]);
+            (bounds.row(0).to_owned(), bounds[[1, 0]] - bounds[[0, 0]], bounds[[1, 1]] - bounds[[0, 1]])
+        }
+    }
+}
+
+/// Collects the plot items of the pages.
+#[derive(Debug)]
+pub struct DisplayList {
+    pages: Vec<Page>,
+    groups: Vec<Group>,
+    background: Color,
+    net_names: bool,
+}
+
+impl DisplayList {
+    pub fn new() -> Self {
+        DisplayList {
+            pages: vec![Page::new(paper::A4)],
+            groups: Vec::new(),
+            background: Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 },
+            net_names: true,
+        }
+    }
+
+    /// Collect the net names of the elements, the backend decides if they are used.
+    pub fn set_net_names(&mut self, net_names: bool) {
+        self.net_names = net_names;
+    }
+
+    /// The pages of the plot, the groups that are not closed are not included.
+    pub fn pages(&self) -> &[Page] {
+        //the current page is empty after a final new_page call
+        match self.pages.split_last() {
+            Some((last, pages)) if last.nodes.is_empty() && !pages.is_empty() => pages,
+            _ => &self.pages,
+        }
+    }
+
+    /// The color of the page background.
+    pub fn background_color(&self) -> &Color {
+        &self.background
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().unwrap()
+    }
+
+    fn add(&mut self, node: Node) {
+        match self.groups.last_mut() {
+            Some(group) => group.nodes.push(node),
+            None => self.page().nodes.push(node),
+        }
+    }
+}
+
+impl Default for DisplayList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Plotter for DisplayList {
+    fn push(&mut self, item: PlotItem) {
+        self.add(Node::Item(Box::new(item)));
+    }
+    fn text_size(&self, item: &Text) -> Array1<f64> {
+        let strokes = item.strokes();
+        arr1(&[strokes.width, strokes.height])
+    }
+    fn bounds(&self) -> Array2<f64> {
+        let page = self.pages.last().unwrap();
+        let mut items = Vec::new();
+        page.nodes.iter().for_each(|node| node.collect(&mut items));
+        self.groups.iter().flat_map(|group| &group.nodes).for_each(|node| node.collect(&mut items));
+        items_bounds(items)
+    }
+    fn paper(&mut self, paper_size: (f64, f64)) {
+        self.page().paper_size = paper_size;
+    }
+    fn new_page(&mut self) {
+        while !self.groups.is_empty() {
+            self.end_group();
+        }
+        let paper_size = self.page().paper_size;
+        self.pages.push(Page::new(paper_size));
+    }
+    fn background(&mut self, color: Color) {
+        self.background = color;
+    }
+    fn start_group(&mut self, attributes: &[(String, String)]) {
+        let parent = self.groups.last().map(|group| group.layer);
+        let layer = attributes
+            .iter()
+            .find(|(key, _)| key == "type")
+            .map(|(_, value)| Layer::of(value))
+            .or(parent)
+            .unwrap_or(Layer::Graphics);
+        self.groups.push(Group { attributes: attributes.to_vec(), layer, nodes: Vec::new() });
+    }
+    fn end_group(&mut self) {
+        if let Some(group) = self.groups.pop() {
+            self.add(Node::Group(group));
+        }
+    }
+    fn net_names(&self) -> bool {
+        self.net_names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plotter::Line;
+    use crate::sexp::LineType;
+
+    fn line(x: f64) -> PlotItem {
+        PlotItem::LineItem(Line::new(
+            arr2(&[[x, 0.0], [x, 10.0]]),
+            0.25,
+            LineType::Solid,
+            Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
+        ))
+    }
+
+    fn attributes(element: &str) -> Vec<(String, String)> {
+        vec![(String::from("type"), element.to_string())]
+    }
+
+    fn x(item: &PlotItem) -> f64 {
+        match item {
+            PlotItem::LineItem(line) => line.pts[[0, 0]],
+            _ => panic!("not a line"),
+        }
+    }
+
+    #[test]
+    fn z_order() {
+        let mut display_list = DisplayList::new();
+        display_list.start_group(&attributes("wire"));
+        display_list.push(line(1.0));
+        display_list.end_group();
+        display_list.start_group(&attributes("symbol"));
+        display_list.push(line(2.0));
+        //the nested group is on the layer of the symbol
+        display_list.start_group(&[(String::from("pin"), String::from("1"))]);
+        display_list.push(line(3.0));
+        display_list.end_group();
+        display_list.end_group();
+        display_list.push(line(4.0));
+        display_list.start_group(&attributes("border"));
+        display_list.push(line(5.0));
+        display_list.end_group();
+
+        let pages = display_list.pages();
+        assert_eq!(pages.len(), 1);
+        let layers: Vec<Layer> = pages[0].layers().iter().map(|node| node.layer()).collect();
+        assert_eq!(layers, vec![Layer::Border, Layer::Graphics, Layer::Symbols, Layer::Wires]);
+        let order: Vec<f64> = pages[0].items().into_iter().map(x).collect();
+        assert_eq!(order, vec![5.0, 4.0, 2.0, 3.0, 1.0]);
+        match pages[0].layers()[2] {
+            Node::Group(group) => assert_eq!(group.attribute("type"), Some("symbol")),
+            Node::Item(_) => panic!("symbol is not grouped"),
+        }
+        assert_eq!(display_list.bounds(), arr2(&[[1.0, 0.0], [5.0, 10.0]]));
+    }
+
+    #[test]
+    fn pages() {
+        let mut display_list = DisplayList::new();
+        display_list.paper(paper::A3);
+        display_list.start_group(&attributes("symbol"));
+        display_list.push(line(1.0));
+        //the open group is closed with the page
+        display_list.new_page();
+        display_list.paper(paper::A4);
+        display_list.push(line(2.0));
+        display_list.new_page();
+
+        let pages = display_list.pages();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].paper_size, paper::A3);
+        assert_eq!(pages[0].items().len(), 1);
+        assert_eq!(pages[1].paper_size, paper::A4);
+        assert_eq!(pages[0].area(true), (arr1(&[0.0, 0.0]), paper::A3.0, paper::A3.1));
+        let (origin, width, height) = pages[1].area(false);
+        assert_eq!(origin, arr1(&[-0.54, -2.54]));
+        assert!((width - 5.08).abs() < 1e-9 && (height - 15.08).abs() < 1e-9);
+    }
+}
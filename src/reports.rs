@@ -1,10 +1,13 @@
 use json;
 
 use crate::Error;
-use crate::sexp::{Sexp, parser::SexpParser, get_unit};
-use crate::sexp::get::Get;
+use crate::schema::SymbolInstance;
+use crate::sexp::Sexp;
+use crate::sexp::parser::SexpParser;
+use crate::units::Value;
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::Write;
 
@@ -31,58 +34,47 @@ fn reference(value: &str) -> String {
     format!("{}{:0>4}", reference_characters, reference_numbers)
 }
 
-fn read_entry(node: &Sexp) -> Result<Option<BomItem>, Error> {
-    let mut references = Vec::new();
-    let mut value = String::new();
-    let mut footprint = String::new();
-    let mut datasheet = String::new();
-    let mut description = String::new();
-
-    if let Sexp::Node(name, _values) = node {
-        if name == "symbol" {
-            let unit = get_unit(node)?;
-            let lib_id: Vec<&Sexp> = node.get("lib_id")?;
-            let lib_name: String = lib_id.get(0).unwrap().get(0)?; //TODO remove unerap
-            if unit == 1 && !lib_name.starts_with("power:") && !lib_name.starts_with("Mechanical:") {
-                let properties: Vec<&Sexp> = node.get("property")?;
-                for prop in properties {
-                    let prop_name: String = prop.get(0)?;
-                    let prop_value = prop.get(1)?;
-                    if prop_name == "Reference" {
-                        references.push(prop_value);
-                    } else if prop_name == "Value" {
-                        value = prop_value;
-                    } else if prop_name == "Datascheet" {
-                        datasheet = prop_value;
-                    } else if prop_name == "Footprint" {
-                        footprint = prop_value;
-                    } else if prop_name == "Description" {
-                        description = prop_value;
-                    }
-                }
-            } else { return Ok(None); }
-        } else { return Ok(None); }
-    } else { return Err(Error::ExpectSexpNode); }
-    Ok(Some(BomItem {
+fn read_entry(symbol: &SymbolInstance) -> Option<BomItem> {
+    if symbol.unit != 1 || symbol.lib_id.starts_with("power:") || symbol.lib_id.starts_with("Mechanical:") {
+        return None;
+    }
+    let property = |key: &str| symbol.property(key).unwrap_or_default().to_string();
+    Some(BomItem {
         amount: 1,
-        references,
-        value,
-        footprint,
-        datasheet,
-        description,
-    }))
+        references: symbol.reference().map(|r| vec![r.to_string()]).unwrap_or_default(),
+        value: property("Value"),
+        footprint: property("Footprint"),
+        datasheet: property("Datasheet"),
+        description: property("Description"),
+    })
+}
+
+/// The value used to group the BOM items.
+///
+/// Equal values in different notations, like 4k7 and 4.7k, are grouped.
+/// The unit is kept, 10uF and 10uH are different parts.
+fn group_value(value: &str) -> String {
+    match Value::with_unit(value) {
+        Ok((number, unit)) => format!("{}{}", number, unit),
+        Err(_) => value.to_string(),
+    }
 }
 
 pub fn bom(filename: Option<&str>, sexp_parser: &SexpParser, group: bool) -> Result<(), Error> {
 
-    let mut items: Vec<BomItem> = sexp_parser
-        .values()
-        .filter_map(|n| read_entry(n).transpose()).collect::<Result<Vec<BomItem>, Error>>()?;
+    let mut items: Vec<BomItem> = Vec::new();
+    for node in sexp_parser.values() {
+        if matches!(node, Sexp::Node(name, _) if name == "symbol") {
+            if let Some(item) = read_entry(&SymbolInstance::try_from(node)?) {
+                items.push(item);
+            }
+        }
+    }
 
     if group {
         let mut map: HashMap<String, Vec<&BomItem>> = HashMap::new();
         for item in &items {
-            let key = format!("{}:{}", item.value, item.footprint);
+            let key = format!("{}:{}", group_value(&item.value), item.footprint);
             if map.contains_key(&key) {
                 map.get_mut(&key).unwrap().push(item);
             } else {
@@ -136,3 +128,16 @@ pub fn bom(filename: Option<&str>, sexp_parser: &SexpParser, group: bool) -> Res
     out.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::group_value;
+
+    #[test]
+    fn group_values() {
+        assert_eq!(group_value("4k7"), group_value("4.7k"));
+        assert_eq!(group_value("100nF"), group_value("0.1uF"));
+        assert_ne!(group_value("10uF"), group_value("10uH"));
+        assert_eq!(group_value("1N4148"), "1N4148");
+    }
+}
@@ -0,0 +1,827 @@
+use crate::Error;
+use crate::sexp::Sexp;
+use crate::sexp::get::{child, get, optional, text, Get};
+use crate::sexp::parser::SexpParser;
+use crate::sexp::writer::{self, format_number};
+
+use ndarray::{arr1, Array1};
+use pyo3::prelude::*;
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::Write;
+
+pub mod items;
+pub mod symbol;
+
+pub use items::{Bus, GlobalLabel, HierarchicalLabel, Junction, Label, NoConnect, Sheet, SheetPin, Text, TitleBlock, Wire};
+pub use symbol::{LibrarySymbol, Property, SymbolInstance};
+
+/// The name of the node.
+fn name(node: &Sexp) -> &str {
+    if let Sexp::Node(name, _) = node {
+        name
+    } else {
+        ""
+    }
+}
+
+fn is_node(node: &Sexp, key: &str) -> bool {
+    matches!(node, Sexp::Node(name, _) if name == key)
+}
+
+/// The position and angle from the `at` node.
+fn position(node: &Sexp) -> Result<(Array1<f64>, f64), Error> {
+    let angle: Option<f64> = get!(node, "at", 2)?;
+    Ok((get!(node, "at")?, angle.unwrap_or(0.0)))
+}
+
+fn values_mut(node: &mut Sexp) -> &mut Vec<Sexp> {
+    if let Sexp::Node(_, values) = node {
+        values
+    } else {
+        panic!("schema elements are always nodes");
+    }
+}
+
+/// Find the index where a new child is inserted, before the first child that
+/// follows in the KiCad order.
+fn insert_index(values: &[Sexp], key: &str, order: &[&str]) -> usize {
+    if let Some(pos) = order.iter().position(|name| *name == key) {
+        let later = &order[pos + 1..];
+        values
+            .iter()
+            .position(|value| matches!(value, Sexp::Node(name, _) if later.contains(&name.as_str())))
+            .unwrap_or(values.len())
+    } else {
+        values.len()
+    }
+}
+
+/// Set the values of a child node, the node is created when it does not exist.
+fn set_field(node: &mut Sexp, key: &str, new_values: Vec<Sexp>, order: &[&str]) {
+    let values = values_mut(node);
+    if let Some(Sexp::Node(_, child_values)) = values.iter_mut().find(|value| is_node(value, key)) {
+        *child_values = new_values;
+    } else {
+        let index = insert_index(values, key, order);
+        values.insert(index, Sexp::Node(key.to_string(), new_values));
+    }
+}
+
+/// Set a single value of a child node, the other values are kept.
+fn set_field_value(node: &mut Sexp, key: &str, index: usize, value: Sexp, order: &[&str]) {
+    let mut new_values = if let Ok(Sexp::Node(_, values)) = child(node, key) {
+        values.clone()
+    } else {
+        Vec::new()
+    };
+    if index < new_values.len() {
+        new_values[index] = value;
+    } else {
+        new_values.push(value);
+    }
+    set_field(node, key, new_values, order);
+}
+
+fn remove_field(node: &mut Sexp, key: &str) {
+    values_mut(node).retain(|value| !is_node(value, key));
+}
+
+/// Replace all the child nodes with the name.
+fn set_children(node: &mut Sexp, key: &str, nodes: Vec<Sexp>, order: &[&str]) {
+    let values = values_mut(node);
+    let index = values
+        .iter()
+        .position(|value| is_node(value, key))
+        .unwrap_or_else(|| insert_index(values, key, order));
+    values.retain(|value| !is_node(value, key));
+    values.splice(index..index, nodes);
+}
+
+/// Set a value by index.
+fn set_value(node: &mut Sexp, index: usize, value: Sexp) {
+    let values = values_mut(node);
+    if index < values.len() {
+        values[index] = value;
+    } else {
+        values.push(value);
+    }
+}
+
+/// Create a text value of the same kind as the existing value, KiCad 6 writes
+/// uuids without quotes and later versions with quotes.
+fn same_kind(existing: Option<&Sexp>, text: &str) -> Sexp {
+    match existing {
+        Some(Sexp::Text(_)) => Sexp::Text(text.to_string()),
+        _ => Sexp::Value(text.to_string()),
+    }
+}
+
+/// Create the number values, unchanged numbers keep their original text.
+fn numbers(existing: Option<&Sexp>, numbers: &[f64]) -> Vec<Sexp> {
+    numbers
+        .iter()
+        .enumerate()
+        .map(|(index, number)| {
+            if let Some(node) = existing {
+                if let Ok(text) = text(node, index) {
+                    if text.parse::<f64>().ok() == Some(*number) {
+                        return Sexp::Value(text.to_string());
+                    }
+                }
+            }
+            Sexp::Value(format_number(*number))
+        })
+        .collect()
+}
+
+fn set_numbers(node: &mut Sexp, key: &str, values: &[f64], order: &[&str]) {
+    let values = numbers(child(node, key).ok(), values);
+    set_field(node, key, values, order);
+}
+
+/// Write the position, a zero angle is only written when the existing node has an angle.
+fn set_position(node: &mut Sexp, at: &Array1<f64>, angle: Option<f64>, order: &[&str]) {
+    let without_angle = matches!(child(node, "at"), Ok(at) if text(at, 2).is_err());
+    match angle {
+        Some(angle) if angle != 0.0 || !without_angle => set_numbers(node, "at", &[at[0], at[1], angle], order),
+        _ => set_numbers(node, "at", &[at[0], at[1]], order),
+    }
+}
+
+fn set_flag(node: &mut Sexp, key: &str, flag: bool, order: &[&str]) {
+    let value = if flag { "yes" } else { "no" };
+    set_field(node, key, vec![Sexp::Value(value.to_string())], order);
+}
+
+fn set_uuid(node: &mut Sexp, uuid: &Option<String>, order: &[&str]) {
+    match uuid {
+        Some(uuid) => {
+            let value = same_kind(child(node, "uuid").ok().and_then(|uuid| match uuid {
+                Sexp::Node(_, values) => values.first(),
+                _ => None,
+            }), uuid);
+            set_field(node, "uuid", vec![value], order);
+        }
+        None => remove_field(node, "uuid"),
+    }
+}
+
+/// An element of the schematic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    Junction(Junction),
+    NoConnect(NoConnect),
+    Wire(Wire),
+    Bus(Bus),
+    Label(Label),
+    GlobalLabel(GlobalLabel),
+    HierarchicalLabel(HierarchicalLabel),
+    Text(Text),
+    Symbol(SymbolInstance),
+    Sheet(Sheet),
+    /// Elements without a typed model, like images, are kept as they are.
+    Other(Sexp),
+}
+
+impl TryFrom<&Sexp> for Element {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        Ok(match name(node) {
+            "junction" => Element::Junction(Junction::try_from(node)?),
+            "no_connect" => Element::NoConnect(NoConnect::try_from(node)?),
+            "wire" => Element::Wire(Wire::try_from(node)?),
+            "bus" => Element::Bus(Bus::try_from(node)?),
+            "label" => Element::Label(Label::try_from(node)?),
+            "global_label" => Element::GlobalLabel(GlobalLabel::try_from(node)?),
+            "hierarchical_label" => Element::HierarchicalLabel(HierarchicalLabel::try_from(node)?),
+            "text" => Element::Text(Text::try_from(node)?),
+            "symbol" => Element::Symbol(SymbolInstance::try_from(node)?),
+            "sheet" => Element::Sheet(Sheet::try_from(node)?),
+            _ => Element::Other(node.clone()),
+        })
+    }
+}
+
+impl From<&Element> for Sexp {
+    fn from(element: &Element) -> Sexp {
+        match element {
+            Element::Junction(junction) => junction.into(),
+            Element::NoConnect(no_connect) => no_connect.into(),
+            Element::Wire(wire) => wire.into(),
+            Element::Bus(bus) => bus.into(),
+            Element::Label(label) => label.into(),
+            Element::GlobalLabel(label) => label.into(),
+            Element::HierarchicalLabel(label) => label.into(),
+            Element::Text(text) => text.into(),
+            Element::Symbol(symbol) => symbol.into(),
+            Element::Sheet(sheet) => sheet.into(),
+            Element::Other(node) => node.clone(),
+        }
+    }
+}
+
+impl Element {
+    /// The node name of the element, like `symbol` or `wire`.
+    pub fn kind(&self) -> &str {
+        match self {
+            Element::Junction(_) => "junction",
+            Element::NoConnect(_) => "no_connect",
+            Element::Wire(_) => "wire",
+            Element::Bus(_) => "bus",
+            Element::Label(_) => "label",
+            Element::GlobalLabel(_) => "global_label",
+            Element::HierarchicalLabel(_) => "hierarchical_label",
+            Element::Text(_) => "text",
+            Element::Symbol(_) => "symbol",
+            Element::Sheet(_) => "sheet",
+            Element::Other(node) => name(node),
+        }
+    }
+
+    pub fn uuid(&self) -> Option<&str> {
+        match self {
+            Element::Junction(junction) => junction.uuid.as_deref(),
+            Element::NoConnect(no_connect) => no_connect.uuid.as_deref(),
+            Element::Wire(wire) => wire.uuid.as_deref(),
+            Element::Bus(bus) => bus.uuid.as_deref(),
+            Element::Label(label) => label.uuid.as_deref(),
+            Element::GlobalLabel(label) => label.uuid.as_deref(),
+            Element::HierarchicalLabel(label) => label.uuid.as_deref(),
+            Element::Text(text) => text.uuid.as_deref(),
+            Element::Symbol(symbol) => symbol.uuid.as_deref(),
+            Element::Sheet(sheet) => sheet.uuid.as_deref(),
+            Element::Other(node) => child(node, "uuid").and_then(|uuid| text(uuid, 0)).ok(),
+        }
+    }
+
+    /// Move the element by the offset, untyped elements are not moved.
+    pub fn translate(&mut self, offset: &Array1<f64>) {
+        match self {
+            Element::Junction(junction) => junction.at = &junction.at + offset,
+            Element::NoConnect(no_connect) => no_connect.at = &no_connect.at + offset,
+            Element::Wire(Wire { pts, .. }) | Element::Bus(Bus { pts, .. }) => {
+                for mut row in pts.rows_mut() {
+                    row += offset;
+                }
+            }
+            Element::Label(label) => label.at = &label.at + offset,
+            Element::GlobalLabel(label) => {
+                label.at = &label.at + offset;
+                for property in &mut label.properties {
+                    property.translate(offset);
+                }
+            }
+            Element::HierarchicalLabel(label) => label.at = &label.at + offset,
+            Element::Text(text) => text.at = &text.at + offset,
+            Element::Symbol(symbol) => symbol.translate(offset),
+            Element::Sheet(sheet) => {
+                sheet.at = &sheet.at + offset;
+                for property in &mut sheet.properties {
+                    property.translate(offset);
+                }
+                for pin in &mut sheet.pins {
+                    pin.at = &pin.at + offset;
+                }
+            }
+            Element::Other(_) => {}
+        }
+    }
+}
+
+/// The nodes before the elements of the schematic.
+const HEADER: [&str; 7] = ["version", "generator", "generator_version", "uuid", "paper", "title_block", "lib_symbols"];
+
+/// A KiCad schematic.
+///
+/// The header fields and the elements are typed, everything else is kept
+/// as it is and written back unchanged.
+#[pyclass]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    pub version: String,
+    pub generator: String,
+    pub uuid: Option<String>,
+    pub paper: String,
+    pub title_block: Option<TitleBlock>,
+    pub lib_symbols: Vec<LibrarySymbol>,
+    pub elements: Vec<Element>,
+    node: Sexp,
+}
+
+impl Schematic {
+    /// Load a schematic file.
+    pub fn load(filename: &str) -> Result<Self, Error> {
+        let parser = SexpParser::load(filename)?;
+        Schematic::try_from(parser.root())
+    }
+
+    /// Write the schematic in the KiCad file format.
+    pub fn save(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        writer::write(&Sexp::from(self), writer)
+    }
+
+    /// Write the schematic to a file.
+    pub fn save_file(&self, filename: &str) -> Result<(), Error> {
+        let mut file = File::create(filename)?;
+        self.save(&mut file)
+    }
+
+    /// Get an element by the uuid.
+    pub fn element(&self, uuid: &str) -> Option<&Element> {
+        self.elements.iter().find(|element| element.uuid() == Some(uuid))
+    }
+
+    /// Get a mutable element by the uuid.
+    pub fn element_mut(&mut self, uuid: &str) -> Option<&mut Element> {
+        self.elements.iter_mut().find(|element| element.uuid() == Some(uuid))
+    }
+
+    /// Get the elements by the node name, like `wire` or `global_label`.
+    pub fn elements_of(&self, kind: &str) -> Vec<&Element> {
+        self.elements.iter().filter(|element| element.kind() == kind).collect()
+    }
+
+    /// Remove an element by the uuid.
+    pub fn remove(&mut self, uuid: &str) -> Option<Element> {
+        let index = self.elements.iter().position(|element| element.uuid() == Some(uuid))?;
+        Some(self.elements.remove(index))
+    }
+
+    /// Remove all units of a symbol by the reference, returns the number of removed units.
+    ///
+    /// The entries of the symbol in the `symbol_instances` are removed as well.
+    pub fn remove_symbol(&mut self, reference: &str) -> usize {
+        let count = self.elements.len();
+        self.elements.retain(|element| {
+            !matches!(element, Element::Symbol(symbol) if symbol.reference() == Some(reference))
+        });
+        for element in &mut self.elements {
+            if let Element::Other(Sexp::Node(name, paths)) = element {
+                if name == "symbol_instances" {
+                    paths.retain(|path| {
+                        optional::<String>(path, "reference", 0).ok().flatten().as_deref() != Some(reference)
+                    });
+                }
+            }
+        }
+        count - self.elements.len()
+    }
+
+    /// All the mutable symbol instances.
+    pub fn symbols_mut(&mut self) -> impl Iterator<Item = &mut SymbolInstance> {
+        self.elements.iter_mut().filter_map(|element| match element {
+            Element::Symbol(symbol) => Some(symbol),
+            _ => None,
+        })
+    }
+
+    /// Get the mutable units of a symbol by the reference.
+    pub fn symbol_mut(&mut self, reference: &str) -> Vec<&mut SymbolInstance> {
+        self.symbols_mut()
+            .filter(|symbol| symbol.reference() == Some(reference))
+            .collect()
+    }
+
+    /// Rename the local, global and hierarchical labels of a net, returns the number of renamed labels.
+    pub fn rename_net(&mut self, name: &str, new_name: &str) -> usize {
+        let mut count = 0;
+        for element in &mut self.elements {
+            let text = match element {
+                Element::Label(Label { text, .. })
+                | Element::GlobalLabel(GlobalLabel { text, .. })
+                | Element::HierarchicalLabel(HierarchicalLabel { text, .. }) => text,
+                _ => continue,
+            };
+            if text == name {
+                *text = new_name.to_string();
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// All the symbol instances.
+    pub fn symbols(&self) -> impl Iterator<Item = &SymbolInstance> {
+        self.elements.iter().filter_map(|element| match element {
+            Element::Symbol(symbol) => Some(symbol),
+            _ => None,
+        })
+    }
+
+    /// Get the units of a symbol by the reference.
+    pub fn symbol(&self, reference: &str) -> Vec<&SymbolInstance> {
+        self.symbols()
+            .filter(|symbol| symbol.reference() == Some(reference))
+            .collect()
+    }
+
+    /// Get a library symbol by the lib_id.
+    pub fn lib_symbol(&self, lib_id: &str) -> Option<&LibrarySymbol> {
+        self.lib_symbols.iter().find(|symbol| symbol.name == lib_id)
+    }
+
+    /// All the wires.
+    pub fn wires(&self) -> impl Iterator<Item = &Wire> {
+        self.elements.iter().filter_map(|element| match element {
+            Element::Wire(wire) => Some(wire),
+            _ => None,
+        })
+    }
+
+    /// All the local labels.
+    pub fn labels(&self) -> impl Iterator<Item = &Label> {
+        self.elements.iter().filter_map(|element| match element {
+            Element::Label(label) => Some(label),
+            _ => None,
+        })
+    }
+
+    /// All the global labels.
+    pub fn global_labels(&self) -> impl Iterator<Item = &GlobalLabel> {
+        self.elements.iter().filter_map(|element| match element {
+            Element::GlobalLabel(label) => Some(label),
+            _ => None,
+        })
+    }
+
+    /// All the junctions.
+    pub fn junctions(&self) -> impl Iterator<Item = &Junction> {
+        self.elements.iter().filter_map(|element| match element {
+            Element::Junction(junction) => Some(junction),
+            _ => None,
+        })
+    }
+
+    /// All the sheets.
+    pub fn sheets(&self) -> impl Iterator<Item = &Sheet> {
+        self.elements.iter().filter_map(|element| match element {
+            Element::Sheet(sheet) => Some(sheet),
+            _ => None,
+        })
+    }
+}
+
+#[pymethods]
+impl Schematic {
+    /// Load a schematic file.
+    #[staticmethod]
+    #[pyo3(name = "load")]
+    fn py_load(filename: &str) -> Result<Self, Error> {
+        Self::load(filename)
+    }
+
+    /// Write the schematic to a file.
+    #[pyo3(name = "save")]
+    fn py_save(&self, filename: &str) -> Result<(), Error> {
+        self.save_file(filename)
+    }
+
+    /// The references of the symbols, filtered by the lib_id and a regular
+    /// expression for the value of a property.
+    #[pyo3(name = "references")]
+    fn py_references(&self, lib_id: Option<&str>, key: Option<&str>, pattern: Option<&str>) -> Result<Vec<String>, Error> {
+        let pattern = match pattern {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|err| Error::ValueError(err.to_string()))?),
+            None => None,
+        };
+        let mut references: Vec<String> = self
+            .symbols()
+            .filter(|symbol| lib_id.is_none() || lib_id == Some(symbol.lib_id.as_str()))
+            .filter(|symbol| match (key, &pattern) {
+                (Some(key), Some(pattern)) => matches!(symbol.property(key), Some(value) if pattern.is_match(value)),
+                (Some(key), None) => symbol.property(key).is_some(),
+                _ => true,
+            })
+            .filter_map(|symbol| symbol.reference().map(|reference| reference.to_string()))
+            .collect();
+        references.sort();
+        references.dedup();
+        Ok(references)
+    }
+
+    /// Get a property of a symbol.
+    #[pyo3(name = "property")]
+    fn py_property(&self, reference: &str, key: &str) -> Result<String, Error> {
+        self.symbol(reference)
+            .first()
+            .ok_or_else(|| Error::SymbolNotFound(reference.to_string()))?
+            .property(key)
+            .map(|value| value.to_string())
+            .ok_or_else(|| Error::PropertyNotFound(key.to_string()))
+    }
+
+    /// Set a property on all units of a symbol.
+    #[pyo3(name = "set_property")]
+    fn py_set_property(&mut self, reference: &str, key: &str, value: &str) -> Result<(), Error> {
+        let symbols = self.symbol_mut(reference);
+        if symbols.is_empty() {
+            return Err(Error::SymbolNotFound(reference.to_string()));
+        }
+        for symbol in symbols {
+            symbol.set_property(key, value);
+        }
+        Ok(())
+    }
+
+    /// Move a symbol unit to the position.
+    #[pyo3(name = "move_symbol")]
+    fn py_move_symbol(&mut self, reference: &str, x: f64, y: f64, unit: Option<usize>) -> Result<(), Error> {
+        let unit = unit.unwrap_or(1);
+        let symbol = self
+            .symbol_mut(reference)
+            .into_iter()
+            .find(|symbol| symbol.unit == unit)
+            .ok_or_else(|| Error::SymbolNotFound(reference.to_string()))?;
+        symbol.move_to(arr1(&[x, y]));
+        Ok(())
+    }
+
+    /// Move an element by the uuid.
+    #[pyo3(name = "translate")]
+    fn py_translate(&mut self, uuid: &str, x: f64, y: f64) -> Result<(), Error> {
+        self.element_mut(uuid)
+            .ok_or_else(|| Error::KeyNotFound(String::from("kicad_sch"), uuid.to_string()))?
+            .translate(&arr1(&[x, y]));
+        Ok(())
+    }
+
+    /// The uuids of the elements by the node name, like `wire`.
+    fn uuids(&self, kind: &str) -> Vec<String> {
+        self.elements_of(kind)
+            .iter()
+            .filter_map(|element| element.uuid().map(|uuid| uuid.to_string()))
+            .collect()
+    }
+
+    /// Delete an element by the uuid.
+    fn delete(&mut self, uuid: &str) -> bool {
+        self.remove(uuid).is_some()
+    }
+
+    /// Delete all units of a symbol.
+    #[pyo3(name = "delete_symbol")]
+    fn py_delete_symbol(&mut self, reference: &str) -> usize {
+        self.remove_symbol(reference)
+    }
+
+    /// Rename the labels of a net.
+    #[pyo3(name = "rename_net")]
+    fn py_rename_net(&mut self, name: &str, new_name: &str) -> usize {
+        self.rename_net(name, new_name)
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        let mut content: Vec<u8> = Vec::new();
+        self.save(&mut content)?;
+        Ok(String::from_utf8_lossy(&content).to_string())
+    }
+}
+
+impl TryFrom<&Sexp> for Schematic {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        if name(node) != "kicad_sch" {
+            return Err(Error::KeyNotFound(name(node).to_string(), String::from("kicad_sch")));
+        }
+        let values = if let Sexp::Node(_, values) = node { values } else { unreachable!() };
+        //the header ends with the first element
+        let split = values
+            .iter()
+            .position(|value| !HEADER.contains(&name(value)))
+            .unwrap_or(values.len());
+        let header = Sexp::Node(name(node).to_string(), values[..split].to_vec());
+
+        let title_block = match child(&header, "title_block") {
+            Ok(title_block) => Some(TitleBlock::try_from(title_block)?),
+            Err(_) => None,
+        };
+        let lib_symbols = match child(&header, "lib_symbols") {
+            Ok(lib_symbols) => {
+                let symbols: Vec<&Sexp> = lib_symbols.get("symbol")?;
+                symbols
+                    .into_iter()
+                    .map(LibrarySymbol::try_from)
+                    .collect::<Result<Vec<LibrarySymbol>, Error>>()?
+            }
+            Err(_) => Vec::new(),
+        };
+        let elements = values[split..]
+            .iter()
+            .map(Element::try_from)
+            .collect::<Result<Vec<Element>, Error>>()?;
+        Ok(Schematic {
+            version: get!(&header, "version", 0)?,
+            generator: optional(&header, "generator", 0)?.unwrap_or_default(),
+            uuid: optional(&header, "uuid", 0)?,
+            paper: optional(&header, "paper", 0)?.unwrap_or_else(|| String::from("A4")),
+            title_block,
+            lib_symbols,
+            elements,
+            node: header,
+        })
+    }
+}
+
+impl From<&Schematic> for Sexp {
+    fn from(schematic: &Schematic) -> Sexp {
+        let order = &HEADER;
+        let mut node = schematic.node.clone();
+        set_field(&mut node, "version", vec![Sexp::Value(schematic.version.clone())], order);
+        let generator = same_kind(child(&node, "generator").ok().and_then(|g| match g {
+            Sexp::Node(_, values) => values.first(),
+            _ => None,
+        }), &schematic.generator);
+        set_field(&mut node, "generator", vec![generator], order);
+        set_uuid(&mut node, &schematic.uuid, order);
+        set_field_value(&mut node, "paper", 0, Sexp::Text(schematic.paper.clone()), order);
+        match &schematic.title_block {
+            Some(title_block) => set_field(&mut node, "title_block", match Sexp::from(title_block) {
+                Sexp::Node(_, values) => values,
+                _ => Vec::new(),
+            }, order),
+            None => remove_field(&mut node, "title_block"),
+        }
+        set_field(
+            &mut node,
+            "lib_symbols",
+            schematic.lib_symbols.iter().map(Sexp::from).collect(),
+            order,
+        );
+        values_mut(&mut node).extend(schematic.elements.iter().map(Sexp::from));
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(node: &Sexp) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        writer::write(node, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn load_schematic() {
+        let schematic = Schematic::load("samples/files/summe/summe.kicad_sch").unwrap();
+        assert_eq!(schematic.version, "20211123");
+        assert_eq!(schematic.paper, "A4");
+        assert_eq!(schematic.title_block.as_ref().unwrap().title, Some(String::from("summe")));
+        assert_eq!(schematic.lib_symbols.len(), 7);
+        assert_eq!(schematic.wires().count(), 14);
+        assert_eq!(schematic.junctions().count(), 5);
+        assert_eq!(schematic.labels().next().unwrap().text, "IN_1");
+        assert_eq!(schematic.global_labels().count(), 2);
+
+        let r5 = schematic.symbol("R5");
+        assert_eq!(r5.len(), 1);
+        assert_eq!(r5[0].lib_id, "Device:R");
+        assert_eq!(r5[0].value(), Some("1k"));
+        assert_eq!(r5[0].angle, 90.0);
+        assert_eq!(schematic.symbol("U1").len(), 3);
+        assert!(schematic.lib_symbol("power:GND").unwrap().power);
+        assert_eq!(schematic.lib_symbol("Amplifier_Operational:TL072").unwrap().unit_count(), 3);
+        assert_eq!(schematic.lib_symbol("Device:R").unwrap().unit_count(), 1);
+        assert!(!schematic.lib_symbol("Device:R").unwrap().has_de_morgan());
+    }
+    #[test]
+    fn round_trip() {
+        let content = std::fs::read_to_string("samples/files/summe/summe.kicad_sch").unwrap();
+        let schematic = Schematic::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        schematic.save(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), content);
+    }
+    #[test]
+    fn update_fields() {
+        let mut schematic = Schematic::load("samples/files/summe/summe.kicad_sch").unwrap();
+        for element in schematic.elements.iter_mut() {
+            if let Element::Symbol(symbol) = element {
+                if symbol.reference() == Some("R5") {
+                    symbol.set_property("Value", "4k7");
+                    symbol.at = arr1(&[100.0, 50.8]);
+                }
+            }
+        }
+        let node = Sexp::from(&schematic);
+        let schematic = Schematic::try_from(&node).unwrap();
+        let r5 = schematic.symbol("R5");
+        assert_eq!(r5[0].value(), Some("4k7"));
+        assert_eq!(r5[0].at, arr1(&[100.0, 50.8]));
+        assert!(write(&node).contains("(symbol (lib_id \"Device:R\") (at 100 50.8 90) (unit 1)"));
+    }
+    #[test]
+    fn edit_schematic() {
+        let mut schematic = Schematic::load("samples/files/summe/summe.kicad_sch").unwrap();
+        //set the footprint of all 0805 resistors
+        for symbol in schematic.symbols_mut() {
+            if symbol.lib_id == "Device:R" && symbol.property("Footprint").unwrap_or_default().contains("0805") {
+                symbol.set_property("Footprint", "Resistor_SMD:R_0603_1608Metric");
+                symbol.set_property("Tolerance", "1%");
+            }
+        }
+        assert_eq!(schematic.py_references(Some("Device:R"), Some("Footprint"), Some("0603")).unwrap().len(), 3);
+        let r5 = schematic.symbol("R5")[0];
+        assert_eq!(r5.property("Tolerance"), Some("1%"));
+        assert_eq!(r5.properties.last().unwrap().id, Some(5));
+        //the new property is not a copy of the last property
+        let tolerance = r5.properties.last().unwrap();
+        assert!(!tolerance.visible());
+        assert_eq!(tolerance.at, r5.at);
+        let content = write(&Sexp::from(tolerance));
+        assert!(content.contains(&format!(
+            "(property \"Tolerance\" \"1%\" (id 5) (at {} {} 0)",
+            format_number(r5.at[0]),
+            format_number(r5.at[1])
+        )));
+        assert!(content.contains("(effects (font (size 1.27 1.27)) hide)"));
+
+        //move a symbol with the properties
+        let reference_at = r5.properties[0].at.clone();
+        schematic.symbol_mut("R5")[0].move_to(arr1(&[115.41, 50.72]));
+        assert_eq!(schematic.symbol("R5")[0].properties[0].at, &reference_at + &arr1(&[10.0, 5.0]));
+
+        //delete elements
+        let uuid = schematic.wires().next().unwrap().uuid.clone().unwrap();
+        assert!(schematic.remove(&uuid).is_some());
+        assert!(schematic.element(&uuid).is_none());
+        assert_eq!(schematic.wires().count(), 13);
+        assert_eq!(schematic.remove_symbol("U1"), 3);
+        assert_eq!(schematic.rename_net("IN_1", "SUM"), 1);
+        assert_eq!(schematic.elements_of("label").len(), 1);
+
+        //save and load
+        let mut out: Vec<u8> = Vec::new();
+        schematic.save(&mut out).unwrap();
+        let doc: SexpParser = String::from_utf8(out).unwrap().parse().unwrap();
+        let loaded = Schematic::try_from(doc.root()).unwrap();
+        assert_eq!(loaded.symbol("R5")[0].property("Tolerance"), Some("1%"));
+        assert!(loaded.symbol("U1").is_empty());
+        let instances = loaded.elements_of("symbol_instances");
+        let paths: Vec<&Sexp> = match instances[0] {
+            Element::Other(node) => node.get("path").unwrap(),
+            _ => unreachable!(),
+        };
+        assert!(!paths.is_empty());
+        assert!(paths.iter().all(|path| get!(*path, "reference", 0).ok() != Some(String::from("U1"))));
+        assert_eq!(loaded.labels().next().unwrap().text, "SUM");
+        assert_eq!(loaded.wires().count(), 13);
+    }
+    #[test]
+    fn keep_unknown_fields() {
+        let doc: SexpParser = "(kicad_sch (version 20211123) (generator eeschema)
+  (wire (pts (xy 0 0) (xy 10 0)) (future_field 1)
+    (stroke (width 0) (type default) (color 0 0 0 0))
+    (uuid 2d2bcbb6-b5bd-4cd2-a0d1-9e4b2fa2d28e)
+  )
+  (image (at 10 10) (uuid 89d1ac49-7e6f-4db4-8b4f-7e4f3a5ba1e1))
+)"
+        .parse()
+        .unwrap();
+        let mut schematic = Schematic::try_from(doc.root()).unwrap();
+        assert!(matches!(schematic.elements[1], Element::Other(_)));
+        if let Element::Wire(wire) = &mut schematic.elements[0] {
+            wire.pts[[1, 0]] = 20.0;
+        }
+        let content = write(&Sexp::from(&schematic));
+        assert!(content.contains("(wire (pts (xy 0 0) (xy 20 0)) (future_field 1)"));
+        assert!(content.contains("(image (at 10 10)"));
+    }
+    #[test]
+    fn keep_optional_fields() {
+        let content = "(kicad_sch (version 20211123) (generator eeschema) (paper \"A4\") (lib_symbols)
+  (junction (at 10 10) (uuid 2d2bcbb6-b5bd-4cd2-a0d1-9e4b2fa2d28e))
+  (label \"IN\" (at 20 10) (uuid 89d1ac49-7e6f-4db4-8b4f-7e4f3a5ba1e1))
+)";
+        let doc: SexpParser = content.parse().unwrap();
+        let mut schematic = Schematic::try_from(doc.root()).unwrap();
+        assert_eq!(write(&Sexp::from(&schematic)), write(doc.root()));
+
+        for element in schematic.elements.iter_mut() {
+            match element {
+                Element::Junction(junction) => junction.diameter = 1.0,
+                Element::Label(label) => label.angle = 90.0,
+                _ => {}
+            }
+        }
+        let content = write(&Sexp::from(&schematic));
+        assert!(content.contains("(junction (at 10 10) (diameter 1)"));
+        assert!(content.contains("(label \"IN\" (at 20 10 90)"));
+    }
+    #[test]
+    fn errors() {
+        let doc: SexpParser = "(kicad_sch (version 20211123) (junction (diameter 0)))".parse().unwrap();
+        assert!(matches!(
+            Schematic::try_from(doc.root()),
+            Err(Error::KeyNotFound(node, key)) if node == "junction" && key == "at"
+        ));
+        let doc: SexpParser = "(kicad_sch (version 20211123) (junction (at 1 x)))".parse().unwrap();
+        assert!(matches!(
+            Schematic::try_from(doc.root()),
+            Err(Error::ValueParseError(node, value)) if node == "junction/at" && value == "x"
+        ));
+    }
+}
@@ -0,0 +1,372 @@
+use super::{
+    numbers, position, remove_field, set_children, set_field, set_numbers, set_position, set_uuid, set_value,
+    Property,
+};
+use crate::Error;
+use crate::sexp::Sexp;
+use crate::sexp::get::{child, get, optional, Get};
+use crate::sexp::test::Test;
+
+use ndarray::{Array1, Array2};
+use std::convert::TryFrom;
+
+const JUNCTION: [&str; 4] = ["at", "diameter", "color", "uuid"];
+const NO_CONNECT: [&str; 2] = ["at", "uuid"];
+const LINE: [&str; 3] = ["pts", "stroke", "uuid"];
+const LABEL: [&str; 6] = ["shape", "at", "fields_autoplaced", "effects", "uuid", "property"];
+const SHEET: [&str; 7] = ["at", "size", "fields_autoplaced", "stroke", "fill", "uuid", "property"];
+const SHEET_PIN: [&str; 3] = ["at", "effects", "uuid"];
+const TITLE_BLOCK: [&str; 5] = ["title", "date", "rev", "company", "comment"];
+
+/// The text of the first value.
+fn label_text(node: &Sexp) -> Result<String, Error> {
+    node.get(0)
+}
+
+/// Write the points to the `pts` node.
+fn set_points(node: &mut Sexp, pts: &Array2<f64>, order: &[&str]) {
+    let existing: Vec<&Sexp> = child(node, "pts").and_then(|pts| pts.get("xy")).unwrap_or_default();
+    let xy = pts
+        .rows()
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| Sexp::Node(String::from("xy"), numbers(existing.get(i).copied(), &[row[0], row[1]])))
+        .collect();
+    set_field(node, "pts", xy, order);
+}
+
+fn properties(node: &Sexp) -> Result<Vec<Property>, Error> {
+    let nodes: Vec<&Sexp> = node.get("property")?;
+    nodes.into_iter().map(Property::try_from).collect()
+}
+
+/// A junction of wires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Junction {
+    pub at: Array1<f64>,
+    pub diameter: f64,
+    pub uuid: Option<String>,
+    node: Sexp,
+}
+
+impl TryFrom<&Sexp> for Junction {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        Ok(Junction {
+            at: position(node)?.0,
+            diameter: optional(node, "diameter", 0)?.unwrap_or(0.0),
+            uuid: optional(node, "uuid", 0)?,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&Junction> for Sexp {
+    fn from(junction: &Junction) -> Sexp {
+        let mut node = junction.node.clone();
+        set_position(&mut node, &junction.at, None, &JUNCTION);
+        //the default diameter is not written
+        if junction.diameter != 0.0 || node.contains("diameter") {
+            set_numbers(&mut node, "diameter", &[junction.diameter], &JUNCTION);
+        }
+        set_uuid(&mut node, &junction.uuid, &JUNCTION);
+        node
+    }
+}
+
+/// A pin that is marked as not connected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoConnect {
+    pub at: Array1<f64>,
+    pub uuid: Option<String>,
+    node: Sexp,
+}
+
+impl TryFrom<&Sexp> for NoConnect {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        Ok(NoConnect {
+            at: position(node)?.0,
+            uuid: optional(node, "uuid", 0)?,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&NoConnect> for Sexp {
+    fn from(no_connect: &NoConnect) -> Sexp {
+        let mut node = no_connect.node.clone();
+        set_position(&mut node, &no_connect.at, None, &NO_CONNECT);
+        set_uuid(&mut node, &no_connect.uuid, &NO_CONNECT);
+        node
+    }
+}
+
+/// Wires and buses have the same fields.
+macro_rules! line {
+    ($name:ident, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name {
+            pub pts: Array2<f64>,
+            pub uuid: Option<String>,
+            node: Sexp,
+        }
+
+        impl TryFrom<&Sexp> for $name {
+            type Error = Error;
+            fn try_from(node: &Sexp) -> Result<Self, Error> {
+                Ok($name {
+                    pts: node.get("pts")?,
+                    uuid: optional(node, "uuid", 0)?,
+                    node: node.clone(),
+                })
+            }
+        }
+
+        impl From<&$name> for Sexp {
+            fn from(line: &$name) -> Sexp {
+                let mut node = line.node.clone();
+                set_points(&mut node, &line.pts, &LINE);
+                set_uuid(&mut node, &line.uuid, &LINE);
+                node
+            }
+        }
+    };
+}
+line!(Wire, "A wire, the points are the rows of `pts`.");
+line!(Bus, "A bus, the points are the rows of `pts`.");
+
+/// Labels and text have the same fields, the label macro adds the extra fields.
+macro_rules! label {
+    ($name:ident, $doc:literal $(, $field:ident: $type:ty = $read:expr => $write:expr)*) => {
+        #[doc = $doc]
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name {
+            pub text: String,
+            pub at: Array1<f64>,
+            pub angle: f64,
+            pub uuid: Option<String>,
+            $(pub $field: $type,)*
+            node: Sexp,
+        }
+
+        impl TryFrom<&Sexp> for $name {
+            type Error = Error;
+            fn try_from(node: &Sexp) -> Result<Self, Error> {
+                let (at, angle) = position(node)?;
+                Ok($name {
+                    text: label_text(node)?,
+                    at,
+                    angle,
+                    uuid: optional(node, "uuid", 0)?,
+                    $($field: $read(node)?,)*
+                    node: node.clone(),
+                })
+            }
+        }
+
+        impl From<&$name> for Sexp {
+            fn from(label: &$name) -> Sexp {
+                let mut node = label.node.clone();
+                set_value(&mut node, 0, Sexp::Text(label.text.clone()));
+                set_position(&mut node, &label.at, Some(label.angle), &LABEL);
+                set_uuid(&mut node, &label.uuid, &LABEL);
+                $($write(&mut node, &label.$field);)*
+                node
+            }
+        }
+    };
+}
+
+fn read_shape(node: &Sexp) -> Result<String, Error> {
+    get!(node, "shape", 0)
+}
+
+fn write_shape(node: &mut Sexp, shape: &str) {
+    set_field(node, "shape", vec![Sexp::Value(shape.to_string())], &LABEL);
+}
+
+fn write_properties(node: &mut Sexp, properties: &[Property]) {
+    set_children(node, "property", properties.iter().map(Sexp::from).collect(), &LABEL);
+}
+
+label!(Label, "A local label.");
+label!(
+    GlobalLabel, "A global label, the shape is `input`, `output`, `bidirectional`, `tri_state` or `passive`.",
+    shape: String = read_shape => write_shape,
+    properties: Vec<Property> = properties => write_properties
+);
+label!(
+    HierarchicalLabel, "A hierarchical label, the shape is the same as for the global label.",
+    shape: String = read_shape => write_shape
+);
+label!(Text, "A text note.");
+
+/// A pin of a hierarchical sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetPin {
+    pub name: String,
+    pub shape: String,
+    pub at: Array1<f64>,
+    pub angle: f64,
+    pub uuid: Option<String>,
+    node: Sexp,
+}
+
+impl TryFrom<&Sexp> for SheetPin {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        let (at, angle) = position(node)?;
+        Ok(SheetPin {
+            name: label_text(node)?,
+            shape: node.get(1)?,
+            at,
+            angle,
+            uuid: optional(node, "uuid", 0)?,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&SheetPin> for Sexp {
+    fn from(pin: &SheetPin) -> Sexp {
+        let mut node = pin.node.clone();
+        set_value(&mut node, 0, Sexp::Text(pin.name.clone()));
+        set_value(&mut node, 1, Sexp::Value(pin.shape.clone()));
+        set_position(&mut node, &pin.at, Some(pin.angle), &SHEET_PIN);
+        set_uuid(&mut node, &pin.uuid, &SHEET_PIN);
+        node
+    }
+}
+
+/// A hierarchical sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub at: Array1<f64>,
+    pub size: Array1<f64>,
+    pub uuid: Option<String>,
+    pub properties: Vec<Property>,
+    pub pins: Vec<SheetPin>,
+    node: Sexp,
+}
+
+impl Sheet {
+    /// The name of the sheet.
+    pub fn name(&self) -> Option<&str> {
+        self.property("Sheet name")
+    }
+
+    /// The filename of the sheet schematic.
+    pub fn filename(&self) -> Option<&str> {
+        self.property("Sheet file")
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|property| property.key == key)
+            .map(|property| property.value.as_str())
+    }
+}
+
+impl TryFrom<&Sexp> for Sheet {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        let width: f64 = get!(node, "size", 0)?;
+        let height: f64 = get!(node, "size", 1)?;
+        let pins: Vec<&Sexp> = node.get("pin")?;
+        Ok(Sheet {
+            at: position(node)?.0,
+            size: ndarray::arr1(&[width, height]),
+            uuid: optional(node, "uuid", 0)?,
+            properties: properties(node)?,
+            pins: pins
+                .into_iter()
+                .map(SheetPin::try_from)
+                .collect::<Result<Vec<SheetPin>, Error>>()?,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&Sheet> for Sexp {
+    fn from(sheet: &Sheet) -> Sexp {
+        let mut node = sheet.node.clone();
+        set_position(&mut node, &sheet.at, None, &SHEET);
+        set_numbers(&mut node, "size", &[sheet.size[0], sheet.size[1]], &SHEET);
+        set_uuid(&mut node, &sheet.uuid, &SHEET);
+        set_children(&mut node, "property", sheet.properties.iter().map(Sexp::from).collect(), &SHEET);
+        set_children(&mut node, "pin", sheet.pins.iter().map(Sexp::from).collect(), &SHEET);
+        node
+    }
+}
+
+/// The title block of the schematic, the comments are numbered from 1 to 9.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TitleBlock {
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub rev: Option<String>,
+    pub company: Option<String>,
+    pub comments: Vec<(usize, String)>,
+    node: Sexp,
+}
+
+impl TitleBlock {
+    /// Get a comment by number.
+    pub fn comment(&self, number: usize) -> Option<&str> {
+        self.comments
+            .iter()
+            .find(|(n, _)| *n == number)
+            .map(|(_, comment)| comment.as_str())
+    }
+}
+
+impl TryFrom<&Sexp> for TitleBlock {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        let comments: Vec<&Sexp> = node.get("comment")?;
+        let comments = comments
+            .into_iter()
+            .map(|comment| Ok((comment.get(0)?, comment.get(1)?)))
+            .collect::<Result<Vec<(usize, String)>, Error>>()?;
+        Ok(TitleBlock {
+            title: optional(node, "title", 0)?,
+            date: optional(node, "date", 0)?,
+            rev: optional(node, "rev", 0)?,
+            company: optional(node, "company", 0)?,
+            comments,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&TitleBlock> for Sexp {
+    fn from(title_block: &TitleBlock) -> Sexp {
+        let mut node = title_block.node.clone();
+        for (key, value) in [
+            ("title", &title_block.title),
+            ("date", &title_block.date),
+            ("rev", &title_block.rev),
+            ("company", &title_block.company),
+        ] {
+            match value {
+                Some(value) => set_field(&mut node, key, vec![Sexp::Text(value.clone())], &TITLE_BLOCK),
+                None => remove_field(&mut node, key),
+            }
+        }
+        let comments = title_block
+            .comments
+            .iter()
+            .map(|(number, comment)| {
+                Sexp::Node(
+                    String::from("comment"),
+                    vec![Sexp::Value(number.to_string()), Sexp::Text(comment.clone())],
+                )
+            })
+            .collect();
+        set_children(&mut node, "comment", comments, &TITLE_BLOCK);
+        node
+    }
+}
@@ -0,0 +1,300 @@
+use super::{
+    position, remove_field, same_kind, set_children, set_field, set_flag, set_position, set_uuid, set_value,
+};
+use crate::Error;
+use crate::sexp::get::{child, flag, get, optional, Get};
+use crate::sexp::test::Test;
+use crate::sexp::{get_convert, get_pins, get_unit, Sexp};
+
+use ndarray::{arr1, Array1};
+use std::convert::TryFrom;
+
+const PROPERTY: [&str; 3] = ["id", "at", "effects"];
+const SYMBOL: [&str; 12] = [
+    "lib_id", "at", "mirror", "unit", "convert", "in_bom", "on_board", "fields_autoplaced", "uuid", "property",
+    "pin", "instances",
+];
+const LIB_SYMBOL: [&str; 7] = ["extends", "power", "pin_numbers", "pin_names", "in_bom", "on_board", "property"];
+
+/// A property of a symbol, sheet or global label.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub key: String,
+    pub value: String,
+    pub id: Option<usize>,
+    pub at: Array1<f64>,
+    pub angle: f64,
+    node: Sexp,
+}
+
+impl Property {
+    /// Create a new hidden property with the default font.
+    pub fn new(key: &str, value: &str, at: Array1<f64>) -> Self {
+        let node = |name: &str, values: Vec<Sexp>| Sexp::Node(name.to_string(), values);
+        let size = || Sexp::Value(String::from("1.27"));
+        let effects = node(
+            "effects",
+            vec![node("font", vec![node("size", vec![size(), size()])]), Sexp::Value(String::from("hide"))],
+        );
+        Property {
+            key: key.to_string(),
+            value: value.to_string(),
+            id: None,
+            at,
+            angle: 0.0,
+            node: node("property", vec![Sexp::Text(key.to_string()), Sexp::Text(value.to_string()), effects]),
+        }
+    }
+
+    /// Move the property by the offset.
+    pub fn translate(&mut self, offset: &Array1<f64>) {
+        self.at = &self.at + offset;
+    }
+
+    /// The property is not hidden.
+    pub fn visible(&self) -> bool {
+        match child(&self.node, "effects") {
+            Ok(Sexp::Node(_, values)) => !values.contains(&Sexp::Value(String::from("hide"))),
+            _ => true,
+        }
+    }
+}
+
+impl TryFrom<&Sexp> for Property {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        let key: String = node.get(0)?;
+        let value: String = node.get(1)?;
+        let (at, angle) = if node.contains("at") { position(node)? } else { (arr1(&[0.0, 0.0]), 0.0) };
+        Ok(Property {
+            key,
+            value,
+            id: optional(node, "id", 0)?,
+            at,
+            angle,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&Property> for Sexp {
+    fn from(property: &Property) -> Sexp {
+        let mut node = property.node.clone();
+        set_value(&mut node, 0, Sexp::Text(property.key.clone()));
+        set_value(&mut node, 1, Sexp::Text(property.value.clone()));
+        match property.id {
+            Some(id) => set_field(&mut node, "id", vec![Sexp::Value(id.to_string())], &PROPERTY),
+            None => remove_field(&mut node, "id"),
+        }
+        set_position(&mut node, &property.at, Some(property.angle), &PROPERTY);
+        node
+    }
+}
+
+/// Get a property value by key.
+fn property<'a>(properties: &'a [Property], key: &str) -> Option<&'a str> {
+    properties
+        .iter()
+        .find(|property| property.key == key)
+        .map(|property| property.value.as_str())
+}
+
+/// Set a property value, a new property is placed at the position and gets the next id.
+fn set_property(properties: &mut Vec<Property>, key: &str, value: &str, at: &Array1<f64>) {
+    if let Some(property) = properties.iter_mut().find(|property| property.key == key) {
+        property.value = value.to_string();
+    } else {
+        let mut property = Property::new(key, value, at.clone());
+        property.id = properties.iter().filter_map(|property| property.id).max().map(|id| id + 1);
+        properties.push(property);
+    }
+}
+
+/// The properties of the node.
+fn properties(node: &Sexp) -> Result<Vec<Property>, Error> {
+    let nodes: Vec<&Sexp> = node.get("property")?;
+    nodes.into_iter().map(Property::try_from).collect()
+}
+
+/// A symbol placed in the schematic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolInstance {
+    pub lib_id: String,
+    pub at: Array1<f64>,
+    pub angle: f64,
+    pub mirror: Option<String>,
+    pub unit: usize,
+    pub in_bom: bool,
+    pub on_board: bool,
+    pub uuid: Option<String>,
+    pub properties: Vec<Property>,
+    node: Sexp,
+}
+
+impl SymbolInstance {
+    /// Get a property value by key.
+    pub fn property(&self, key: &str) -> Option<&str> {
+        property(&self.properties, key)
+    }
+
+    /// Set a property value, a new property is hidden and placed at the symbol.
+    pub fn set_property(&mut self, key: &str, value: &str) {
+        set_property(&mut self.properties, key, value, &self.at);
+    }
+
+    pub fn reference(&self) -> Option<&str> {
+        self.property("Reference")
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.property("Value")
+    }
+
+    /// Move the symbol and the properties by the offset.
+    pub fn translate(&mut self, offset: &Array1<f64>) {
+        self.at = &self.at + offset;
+        for property in &mut self.properties {
+            property.translate(offset);
+        }
+    }
+
+    /// Move the symbol to the position, the properties keep their distance to the symbol.
+    pub fn move_to(&mut self, at: Array1<f64>) {
+        let offset = &at - &self.at;
+        self.translate(&offset);
+    }
+
+    /// The pin nodes with the pin number and uuid.
+    pub fn pins(&self) -> Vec<&Sexp> {
+        self.node.get("pin").unwrap_or_default()
+    }
+}
+
+impl TryFrom<&Sexp> for SymbolInstance {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        let (at, angle) = position(node)?;
+        Ok(SymbolInstance {
+            lib_id: get!(node, "lib_id", 0)?,
+            at,
+            angle,
+            mirror: optional(node, "mirror", 0)?,
+            unit: optional(node, "unit", 0)?.unwrap_or(1),
+            in_bom: flag(node, "in_bom")?,
+            on_board: flag(node, "on_board")?,
+            uuid: optional(node, "uuid", 0)?,
+            properties: properties(node)?,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&SymbolInstance> for Sexp {
+    fn from(symbol: &SymbolInstance) -> Sexp {
+        let mut node = symbol.node.clone();
+        set_field(&mut node, "lib_id", vec![Sexp::Text(symbol.lib_id.clone())], &SYMBOL);
+        set_position(&mut node, &symbol.at, Some(symbol.angle), &SYMBOL);
+        match &symbol.mirror {
+            Some(mirror) => set_field(&mut node, "mirror", vec![Sexp::Value(mirror.clone())], &SYMBOL),
+            None => remove_field(&mut node, "mirror"),
+        }
+        set_field(&mut node, "unit", vec![Sexp::Value(symbol.unit.to_string())], &SYMBOL);
+        set_flag(&mut node, "in_bom", symbol.in_bom, &SYMBOL);
+        set_flag(&mut node, "on_board", symbol.on_board, &SYMBOL);
+        set_uuid(&mut node, &symbol.uuid, &SYMBOL);
+        set_children(&mut node, "property", symbol.properties.iter().map(Sexp::from).collect(), &SYMBOL);
+        node
+    }
+}
+
+/// A symbol in the `lib_symbols` of the schematic.
+///
+/// The graphic items and pins of the units are kept as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibrarySymbol {
+    pub name: String,
+    pub extends: Option<String>,
+    pub power: bool,
+    pub in_bom: bool,
+    pub on_board: bool,
+    pub properties: Vec<Property>,
+    node: Sexp,
+}
+
+impl LibrarySymbol {
+    /// Get a property value by key.
+    pub fn property(&self, key: &str) -> Option<&str> {
+        property(&self.properties, key)
+    }
+
+    /// Set a property value, a new property is hidden and placed at the origin.
+    pub fn set_property(&mut self, key: &str, value: &str) {
+        set_property(&mut self.properties, key, value, &arr1(&[0.0, 0.0]));
+    }
+
+    /// The unit symbols, like `R_0_1` and `R_1_1`.
+    pub fn units(&self) -> Vec<&Sexp> {
+        self.node.get("symbol").unwrap_or_default()
+    }
+
+    /// The number of units, the unit numbers are taken from the unit symbol names.
+    pub fn unit_count(&self) -> usize {
+        self.units()
+            .into_iter()
+            .filter_map(|unit| get_unit(unit).ok())
+            .max()
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    /// The symbol has a De Morgan body style.
+    pub fn has_de_morgan(&self) -> bool {
+        self.units().into_iter().any(|unit| matches!(get_convert(unit), Ok(2)))
+    }
+
+    /// The pins of a unit, unit 0 are the pins common to all units.
+    pub fn pins(&self, unit: Option<usize>) -> Result<Vec<&Sexp>, Error> {
+        get_pins(&self.node, unit)
+    }
+}
+
+impl TryFrom<&Sexp> for LibrarySymbol {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Self, Error> {
+        Ok(LibrarySymbol {
+            name: node.get(0)?,
+            extends: optional(node, "extends", 0)?,
+            power: node.contains("power"),
+            in_bom: flag(node, "in_bom")?,
+            on_board: flag(node, "on_board")?,
+            properties: properties(node)?,
+            node: node.clone(),
+        })
+    }
+}
+
+impl From<&LibrarySymbol> for Sexp {
+    fn from(symbol: &LibrarySymbol) -> Sexp {
+        let mut node = symbol.node.clone();
+        let name = same_kind(
+            if let Sexp::Node(_, values) = &node { values.first() } else { None },
+            &symbol.name,
+        );
+        set_value(&mut node, 0, name);
+        match &symbol.extends {
+            Some(extends) => set_field(&mut node, "extends", vec![Sexp::Text(extends.clone())], &LIB_SYMBOL),
+            None => remove_field(&mut node, "extends"),
+        }
+        if symbol.power {
+            if !node.contains("power") {
+                set_field(&mut node, "power", Vec::new(), &LIB_SYMBOL);
+            }
+        } else {
+            remove_field(&mut node, "power");
+        }
+        set_flag(&mut node, "in_bom", symbol.in_bom, &LIB_SYMBOL);
+        set_flag(&mut node, "on_board", symbol.on_board, &LIB_SYMBOL);
+        set_children(&mut node, "property", symbol.properties.iter().map(Sexp::from).collect(), &LIB_SYMBOL);
+        node
+    }
+}
@@ -5,8 +5,10 @@ use lazy_static::lazy_static;
 pub mod elements;
 pub mod get;
 pub mod parser;
+pub mod query;
 pub mod test;
 pub mod iterator;
+pub mod writer;
 
 use crate::sexp::get::{get, Get};
 use crate::sexp::test::Test;
@@ -34,17 +36,39 @@ pub enum Justify {
     Bottom,
     Mirror,
 }
+/// The attributes of text effects and strokes that are not set in the file.
+///
+/// KiCad leaves attributes unset to use the defaults of the editor, like a
+/// stroke width of zero, the color `(color 0 0 0 0)` or a font without a face.
+/// The plot takes unset attributes from the theme.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unset {
+    pub font: bool,
+    pub color: bool,
+    pub size: bool,
+    pub thickness: bool,
+    pub line_spacing: bool,
+    pub width: bool,
+}
+
+/// The text effects.
+///
+/// `size` is the font height and `width` the font width, `unset` tells which
+/// attributes are not set in the file. `href` is the hyperlink of the text.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Effects {
     pub font: String,
     pub color: Color,
     pub size: f64,
+    pub width: f64,
     pub thickness: f64,
     pub bold: bool,
     pub italic: bool,
     pub line_spacing: f64,
     pub justify: Vec<Justify>,
     pub hide: bool,
+    pub href: Option<String>,
+    pub unset: Unset,
 }
 impl Effects {
     pub fn new(
@@ -62,15 +86,22 @@ impl Effects {
             font,
             color,
             size,
+            width: size,
             thickness,
             bold,
             italic,
             line_spacing,
             justify,
             hide,
+            href: None,
+            unset: Unset::default(),
         }
     }
 }
+/// A color with the channels from 0.0 to 1.0.
+///
+/// KiCad writes the red, green and blue channels from 0 to 255, they are
+/// scaled when the color is read from the file.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Color {
     pub r: f64,
@@ -78,14 +109,19 @@ pub struct Color {
     pub b: f64,
     pub a: f64,
 }
-impl From<&Sexp> for Color {
-    fn from(node: &Sexp) -> Color {
-        Color {
-            r: node.get(0).unwrap(),
-            g: node.get(0).unwrap(),
-            b: node.get(0).unwrap(),
-            a: node.get(0).unwrap(),
-        }
+impl TryFrom<&Sexp> for Color {
+    type Error = Error;
+    fn try_from(node: &Sexp) -> Result<Color, Error> {
+        let r: f64 = node.get(0)?;
+        let g: f64 = node.get(1)?;
+        let b: f64 = node.get(2)?;
+        let a: Option<f64> = node.get(3)?;
+        Ok(Color {
+            r: r / 255.0,
+            g: g / 255.0,
+            b: b / 255.0,
+            a: a.unwrap_or(1.0),
+        })
     }
 }
 #[derive(Debug, Clone, PartialEq)]
@@ -93,13 +129,16 @@ pub enum FillType {
     None,
     Outline,
     Background,
+    Color(Color),
 }
+/// The stroke of a graphic item, `unset` tells if the width and the color are not set in the file.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stroke {
     pub width: f64,
     pub line_type: LineType,
     pub color: Color,
     pub fill: FillType,
+    pub unset: Unset,
 }
 
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone)]
@@ -118,12 +157,42 @@ pub fn get_unit(node: &Sexp) -> Result<usize, Error> {
         }
 
         if node.contains("unit") {
-            let unit: usize = get!(node, "unit", 0);
+            let unit: usize = get!(node, "unit", 0)?;
             return Ok(unit);
         } else {
-            let name: String = get!(node, 0).unwrap();
+            let name: String = get!(node, 0)?;
             if let Some(line) = RE.captures_iter(&name).next() {
-                return Ok(line[1].parse().unwrap());
+                return line[1]
+                    .parse()
+                    .map_err(|_| Error::ValueParseError(String::from("symbol"), name.to_string()));
+            }
+        }
+    }
+    Ok(1)
+}
+
+/// Get the body style of a symbol, `1` is the normal and `2` the De Morgan style.
+///
+/// The style of a library unit is the last number in the name, like `74LS00_1_2`,
+/// units with the style `0` are drawn for all styles.
+pub fn get_convert(node: &Sexp) -> Result<usize, Error> {
+    if let Sexp::Node(name, _) = node {
+        if name != "symbol" {
+            return Err(Error::ExpectSexpNode);
+        }
+
+        if node.contains("convert") {
+            let convert: usize = get!(node, "convert", 0)?;
+            return Ok(convert);
+        } else if node.contains("lib_id") {
+            //placed symbols only have the field for the De Morgan style
+            return Ok(1);
+        } else {
+            let name: String = get!(node, 0)?;
+            if let Some(line) = RE.captures_iter(&name).next() {
+                return line[2]
+                    .parse()
+                    .map_err(|_| Error::ValueParseError(String::from("symbol"), name.to_string()));
             }
         }
     }
@@ -133,7 +202,7 @@ pub fn get_unit(node: &Sexp) -> Result<usize, Error> {
 pub fn get_pin<'a>(node: &'a Sexp, index: usize) -> Result<&'a Sexp, Error> {
     let pins = get_pins(node, None)?;
     for p in pins {
-        let i: usize = get!(p, "number", 0);
+        let i: usize = get!(p, "number", 0)?;
         if index == i {
             return Ok(p);
         }
@@ -144,23 +213,13 @@ pub fn get_pin<'a>(node: &'a Sexp, index: usize) -> Result<&'a Sexp, Error> {
 /// Get all the pins of a library symbol.
 pub fn get_pins<'a>(node: &'a Sexp, number: Option<usize>) -> Result<Vec<&'a Sexp>, Error> {
     let symbols: Vec<&Sexp> = node.get("symbol")?;
-    let symbols: Vec<&Sexp> = symbols 
-        .iter()
-        .filter_map(|symbol| {
-            let symbol_unit = get_unit(symbol).unwrap();
-            if let Some(number) = number {
-                if number == symbol_unit {
-                    Option::from(*symbol)
-                } else {
-                    None
-                }
-            } else {
-                Option::from(*symbol)
-            }
-        }).collect();
-
     let mut result: Vec<&Sexp> = Vec::new();
     for symbol in symbols {
+        if let Some(number) = number {
+            if number != get_unit(symbol)? {
+                continue;
+            }
+        }
         let pins: Vec<&Sexp> = symbol.get("pin")?;
         for pin in pins {
             result.push(pin);
@@ -171,26 +230,13 @@ pub fn get_pins<'a>(node: &'a Sexp, number: Option<usize>) -> Result<Vec<&'a Sex
 
 pub fn get_property(node: &Sexp, key: &str) -> Result<String, Error> {
     let props: Vec<&Sexp> = node.get("property")?;
-    let result: Vec<String> = props
-        .iter()
-        .filter_map(|node| {
-            if let Sexp::Node(name, _) = node {
-                if name == "property" {
-                    let k: String = get!(node, 0).unwrap();
-                    if k == key {
-                        let res: String = get!(node, 1).unwrap();
-                        Option::from(res)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
-        .collect();
+    let mut result: Vec<String> = Vec::new();
+    for prop in props {
+        let k: String = get!(prop, 0)?;
+        if k == key {
+            result.push(get!(prop, 1)?);
+        }
+    }
 
     if result.is_empty() {
         Err(Error::PropertyNotFound(key.to_string()))
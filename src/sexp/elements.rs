@@ -24,8 +24,8 @@ macro_rules! pos {
         Sexp::Node(
             String::from("at"),
             vec![
-                Sexp::Value($pos[0].to_string()),
-                Sexp::Value($pos[1].to_string()),
+                Sexp::Value($crate::sexp::writer::format_number($pos[0])),
+                Sexp::Value($crate::sexp::writer::format_number($pos[1])),
             ],
         )
     };
@@ -33,8 +33,8 @@ macro_rules! pos {
         Sexp::Node(
             String::from("at"),
             vec![
-                Sexp::Value($pos[0].to_string()),
-                Sexp::Value($pos[1].to_string()),
+                Sexp::Value($crate::sexp::writer::format_number($pos[0])),
+                Sexp::Value($crate::sexp::writer::format_number($pos[1])),
                 Sexp::Value($angle.to_string()),
             ],
         )
@@ -88,8 +88,8 @@ macro_rules! pts {
     ($($pt:expr),+) => {
         Sexp::Node(String::from("pts"), vec![
             $(Sexp::Node(String::from("xy"), vec![
-                    Sexp::Value(String::from($pt[0].to_string())),
-                    Sexp::Value(String::from($pt[1].to_string())),
+                    Sexp::Value($crate::sexp::writer::format_number($pt[0])),
+                    Sexp::Value($crate::sexp::writer::format_number($pt[1])),
             ]),)*
         ])
     }
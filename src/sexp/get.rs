@@ -1,290 +1,461 @@
 use ndarray::{arr1, Array1, Array2, ArrayView};
 
 use crate::Error;
-use crate::sexp::{Color, Effects, Justify, Sexp, Stroke, LineType, FillType};
+use crate::sexp::{Color, Effects, Justify, Sexp, Stroke, LineType, FillType, Unset};
 use crate::sexp::test::Test;
 
+use std::convert::TryFrom;
+
 macro_rules! get {
     ($node:expr, $key:expr) => {
         $node.get($key)
     };
     ($node:expr, $key:expr, $index:expr) => {
-        Get::<_, Vec<&Sexp>>::get($node, $key)
-            .unwrap().get(0).unwrap()
-            .get($index).unwrap()
+        $crate::sexp::get::value($node, $key, $index)
     };
 }
 pub(crate) use get;
 
+/// The name of the node, used for the path in the errors.
+fn name(node: &Sexp) -> &str {
+    if let Sexp::Node(name, _) = node {
+        name
+    } else {
+        ""
+    }
+}
+
+/// Prepend the parent name to the node path of the error.
+fn in_path(err: Error, parent: &str) -> Error {
+    match err {
+        Error::KeyNotFound(path, key) => Error::KeyNotFound(format!("{}/{}", parent, path), key),
+        Error::ValueParseError(path, value) => Error::ValueParseError(format!("{}/{}", parent, path), value),
+        err => err,
+    }
+}
+
+/// Get the first child node by key.
+pub(crate) fn child<'a>(node: &'a Sexp, key: &str) -> Result<&'a Sexp, Error> {
+    let nodes: Vec<&Sexp> = node.get(key)?;
+    nodes
+        .first()
+        .copied()
+        .ok_or_else(|| Error::KeyNotFound(name(node).to_string(), key.to_string()))
+}
+
+/// Get the value of the first child node by key and index.
+pub fn value<'a, T>(node: &'a Sexp, key: &str, index: usize) -> Result<T, Error>
+where
+    Sexp: Get<'a, usize, T>,
+{
+    child(node, key)?.get(index).map_err(|err| in_path(err, name(node)))
+}
+
+/// Get the value of an optional child node, `None` when the node does not exist.
+pub fn optional<'a, T>(node: &'a Sexp, key: &str, index: usize) -> Result<Option<T>, Error>
+where
+    Sexp: Get<'a, usize, Option<T>>,
+{
+    if node.contains(key) {
+        child(node, key)?.get(index).map_err(|err| in_path(err, name(node)))
+    } else {
+        Ok(None)
+    }
+}
 
 /// Access the nodes and values.
+///
+/// Missing nodes and values return `Error::KeyNotFound` and values that can
+/// not be parsed `Error::ValueParseError`, both with the path of the node.
+/// The `Option` variants return `None` for a missing node or value.
 pub trait Get<'a, S, T> {
     fn get(&'a self, index: S) -> Result<T, Error>;
 }
+
+/// Get the text of the value by index.
+pub(crate) fn text(node: &Sexp, index: usize) -> Result<&str, Error> {
+    if let Sexp::Node(_, values) = node {
+        match values.get(index) {
+            Some(Sexp::Value(value)) | Some(Sexp::Text(value)) => Ok(value),
+            _ => Err(Error::KeyNotFound(name(node).to_string(), index.to_string())),
+        }
+    } else {
+        Err(Error::ExpectSexpNode)
+    }
+}
+
 /// Get the value as String by index.
 impl Get<'_, usize, String> for Sexp {
-    /// Get the value as String by index.
     fn get(&self, index: usize) -> Result<String, Error> {
-        if let Sexp::Node(_, values) = &self {
-            if let Some(Sexp::Value(value)) = values.get(index) {
-                Ok(value.to_string())
-            } else if let Some(Sexp::Text(value)) = values.get(index) {
-                Ok(value.to_string())
-            } else { Err(Error::ParseError) }
-        } else { Err(Error::ExpectValueNode) }
+        text(self, index).map(|value| value.to_string())
     }
 }
 /// Get the value as float by index.
 impl Get<'_, usize, f64> for Sexp {
-    /// Get the value as String by index.
     fn get(&self, index: usize) -> Result<f64, Error> {
-        if let Sexp::Node(_, values) = &self {
-            if let Some(Sexp::Value(value)) = values.get(index) {
-                Ok(value.parse().unwrap())
-            } else if let Some(Sexp::Text(value)) = values.get(index) {
-                Ok(value.parse().unwrap())
-            } else { Err(Error::ParseError) }
-        } else { Err(Error::ExpectValueNode) }
+        let value = text(self, index)?;
+        value
+            .parse()
+            .map_err(|_| Error::ValueParseError(name(self).to_string(), value.to_string()))
     }
 }
 /// Get the value as usize by index.
 impl Get<'_, usize, usize> for Sexp {
-    /// Get the value as String by index.
     fn get(&self, index: usize) -> Result<usize, Error> {
-        if let Sexp::Node(_, values) = &self {
-            if let Some(Sexp::Value(value)) = values.get(index) {
-                Ok(value.parse().unwrap())
-            } else if let Some(Sexp::Text(value)) = values.get(index) {
-                Ok(value.parse().unwrap())
-            } else { Err(Error::ParseError) }
-        } else { Err(Error::ExpectValueNode) }
+        let value = text(self, index)?;
+        value
+            .parse()
+            .map_err(|_| Error::ValueParseError(name(self).to_string(), value.to_string()))
+    }
+}
+/// Get an optional value by index, `None` when the index does not exist.
+impl<'a, T> Get<'a, usize, Option<T>> for Sexp
+where
+    Sexp: Get<'a, usize, T>,
+{
+    fn get(&'a self, index: usize) -> Result<Option<T>, Error> {
+        match self {
+            Sexp::Node(_, values) if index >= values.len() => Ok(None),
+            _ => Get::<usize, T>::get(self, index).map(Some),
+        }
     }
 }
-/// Get the value as Array1 by index.
+/// Get the value as Array1 by key.
 impl Get<'_, &str, Array1<f64>> for Sexp {
     fn get(&self, key: &str) -> Result<Array1<f64>, Error> {
-        let nodes: Vec<&Sexp> = self.get(key).unwrap();
-        let pos: &Sexp = nodes.get(0).unwrap();
-        let x: f64 = pos.get(0).unwrap();
-        let y: f64 = pos.get(1).unwrap();
+        let x: f64 = value(self, key, 0)?;
+        let y: f64 = value(self, key, 1)?;
         Ok(arr1(&[x, y]))
     }
 }
-/// Get the value as Array2 by index.
+/// Get the value as Array2 by key.
 impl Get<'_, &str, Array2<f64>> for Sexp {
-    /// Get the value as String by index.
     fn get(&self, key: &str) -> Result<Array2<f64>, Error> {
         let mut array: Array2<f64> = Array2::zeros((0, 2));
-        let nodes: Vec<&Sexp> = self.get(key).unwrap();
-        let xy: Vec<&Sexp> = nodes.get(0).unwrap().get("xy").unwrap();
-
+        let pts = child(self, key)?;
+        let xy: Vec<&Sexp> = pts.get("xy")?;
         for _xy in xy {
-            let x: f64 = _xy.get(0).unwrap();
-            let y: f64 = _xy.get(1).unwrap();
-            array.push_row(ArrayView::from(&[x, y])).unwrap();
+            let x: f64 = _xy.get(0).map_err(|err| in_path(in_path(err, key), name(self)))?;
+            let y: f64 = _xy.get(1).map_err(|err| in_path(in_path(err, key), name(self)))?;
+            array
+                .push_row(ArrayView::from(&[x, y]))
+                .map_err(|err| Error::ValueParseError(format!("{}/{}", name(self), key), err.to_string()))?;
         }
         Ok(array)
     }
 }
-/// Get the value as String by index.
+/// Get the child nodes by key.
 impl<'a> Get<'a, &str, Vec<&'a Sexp>> for Sexp {
-    /// Get the value as String by index.
     fn get(&'a self, key: &str) -> Result<Vec<&'a Sexp>, Error> {
         if let Sexp::Node(_, values) = &self {
-            Ok(values.into_iter().filter(|n| {
+            Ok(values.iter().filter(|n| {
                 if let Sexp::Node(name, _) = n {
                     name == key
                 } else { false }
             }).collect())
-        } else { Err(Error::ExpectValueNode) }
+        } else { Err(Error::ExpectSexpNode) }
+    }
+}
+/// Get an optional element by key, `None` when the node does not exist.
+impl<'a> Get<'a, &str, Option<Array1<f64>>> for Sexp {
+    fn get(&'a self, key: &str) -> Result<Option<Array1<f64>>, Error> {
+        if self.contains(key) { self.get(key).map(Some) } else { Ok(None) }
+    }
+}
+impl<'a> Get<'a, &str, Option<Effects>> for Sexp {
+    fn get(&'a self, key: &str) -> Result<Option<Effects>, Error> {
+        if self.contains(key) { self.get(key).map(Some) } else { Ok(None) }
+    }
+}
+impl<'a> Get<'a, &str, Option<Stroke>> for Sexp {
+    fn get(&'a self, key: &str) -> Result<Option<Stroke>, Error> {
+        if self.contains(key) { self.get(key).map(Some) } else { Ok(None) }
+    }
+}
+
+/// KiCad writes the color `(color 0 0 0 0)` when the color is not set.
+fn unset_color(color: &Color) -> bool {
+    color.r == 0.0 && color.g == 0.0 && color.b == 0.0 && color.a == 0.0
+}
+
+/// Flags are written as value like `bold` or as node like `(bold yes)`.
+pub(crate) fn flag(node: &Sexp, key: &str) -> Result<bool, Error> {
+    if node.has(key) {
+        return Ok(true);
     }
+    let value: Option<String> = optional(node, key, 0)?;
+    Ok(match value.as_deref() {
+        Some("no") => false,
+        Some(_) => true,
+        None => node.contains(key),
+    })
 }
 
-/// Get the value as Effects by index.
+/// Get the value as Effects by key.
 impl<'a> Get<'a, &str, Effects> for Sexp {
     fn get(&'a self, key: &str) -> Result<Effects, Error> {
-        let nodes: Vec<&Sexp> = self.get(key).unwrap();
-        if nodes.len() == 1 {
-            let node = nodes.get(0).unwrap();
-                let fonts: Vec<&Sexp> = node.get("font").unwrap();
-                if fonts.len() == 1 {
-                    let font = fonts.get(0).unwrap();
-                    // get face 0
-                    /* let face_list: Vec<&Sexp> = font.get("face").unwrap();
-                    let face_item: &Sexp = face_list.get(0).unwrap();
-                    let face: String = face_item.get(0).unwrap(); */
+        let node = child(self, key)?;
+        let path = |err| in_path(in_path(err, key), name(self));
+        let font = child(node, "font").map_err(path)?;
+        let path = |err| in_path(in_path(in_path(err, "font"), key), name(self));
 
-                    let face: String = if font.contains("face") {
-                        get!(*font, "face", 0)
-                    } else {
-                        "default".to_string()
-                    };
-                    let size: f64 = if font.contains("size") {
-                        get!(*font, "size", 0)
-                    } else {
-                        0.0
-                    };
-                    let thickness: f64 = if font.contains("thickess") {
-                        get!(*font, "thickness", 0)
-                    } else {
-                        0.0
-                    };
-                    let line_spacing: f64 = if font.contains("line_spacing") {
-                        get!(*font, "line_spacing", 0)
-                    } else {
-                        0.0
-                    };
-                    let justify: Vec<Justify> = if node.contains("justify") {
-                        get!(*node, "justify").unwrap()
-                    } else {
-                        vec![Justify::Center]
-                    };
-
-                    let effects = Effects::new(
-                        face,
-                        Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 0.0,
-                        },
-                        size,
-                        thickness,
-                        font.has("bold"),
-                        font.has("italic"),
-                        line_spacing,
-                        justify,
-                        node.has("hide"),
-                    );
-                    return Ok(effects);
-                } else {
-                    Err(Error::ParseError)
-                }
+        let face: String = optional(font, "face", 0).map_err(path)?.unwrap_or_else(|| String::from("default"));
+        let size: f64 = optional(font, "size", 0).map_err(path)?.unwrap_or(0.0);
+        let width: f64 = optional(font, "size", 1).map_err(path)?.flatten().unwrap_or(size);
+        let thickness: f64 = optional(font, "thickness", 0).map_err(path)?.unwrap_or(0.0);
+        let line_spacing: f64 = optional(font, "line_spacing", 0).map_err(path)?.unwrap_or(0.0);
+        let color = if font.contains("color") {
+            Color::try_from(child(font, "color")?).map_err(path)?
         } else {
-            Err(Error::ParseError)
-        }
+            Color {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.0,
+            }
+        };
+        let justify: Vec<Justify> = if node.contains("justify") {
+            get!(node, "justify")?
+        } else {
+            vec![Justify::Center]
+        };
+
+        let unset = Unset {
+            font: !font.contains("face"),
+            color: unset_color(&color),
+            size: size == 0.0,
+            thickness: thickness == 0.0,
+            line_spacing: line_spacing == 0.0,
+            width: width == 0.0,
+        };
+        Ok(Effects {
+            font: face,
+            color,
+            size,
+            width,
+            thickness,
+            bold: flag(font, "bold").map_err(path)?,
+            italic: flag(font, "italic").map_err(path)?,
+            line_spacing,
+            justify,
+            hide: flag(node, "hide").map_err(|err| in_path(in_path(err, key), name(self)))?,
+            href: optional(node, "href", 0).map_err(|err| in_path(in_path(err, key), name(self)))?,
+            unset,
+        })
     }
 }
 
-/// Get the value as Stroke by index.
+/// Get the value as Stroke by key.
 impl<'a> Get<'a, &str, Stroke> for Sexp {
     fn get(&'a self, key: &str) -> Result<Stroke, Error> {
-        let nodes: Vec<&Sexp> = self.get(key).unwrap();
-        if nodes.len() == 1 {
-            let stroke = nodes.get(0).unwrap();
-
-            let width: f64 = if stroke.contains("width") {
-                get!(*stroke, "width", 0)
-            } else {
-                0.0
-            };
-            let line_type: LineType = if stroke.contains("type") {
-                stroke.get("type").unwrap()
-            } else {
-                LineType::Default
-            };
-            let color: Color = if stroke.contains("color") {
-                let nodes: Vec<&Sexp> = stroke.get("color").unwrap();
-                let color: &Sexp = nodes.get(0).unwrap();
-                Color::from(color)
-            } else {
-                Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 0.0,
-                }
-            };
-            let fill: FillType = if self.contains("fill") {
-                get!(self, "fill").unwrap()
-            } else {
-                FillType::None
-            };
-
-            Ok(Stroke {
-                width,
-                line_type,
-                color,
-                fill,
-            })
+        let stroke = child(self, key)?;
+        let width: f64 = if stroke.contains("width") {
+            get!(stroke, "width", 0).map_err(|err| in_path(err, name(self)))?
         } else {
-            Err(Error::ParseError)
-        }
+            0.0
+        };
+        let line_type: LineType = if stroke.contains("type") {
+            stroke.get("type")?
+        } else {
+            LineType::Default
+        };
+        let color: Color = if stroke.contains("color") {
+            Color::try_from(child(stroke, "color")?).map_err(|err| in_path(in_path(err, key), name(self)))?
+        } else {
+            Color {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.0,
+            }
+        };
+        let fill: FillType = if self.contains("fill") {
+            get!(self, "fill")?
+        } else {
+            FillType::None
+        };
+
+        let unset = Unset {
+            color: unset_color(&color),
+            width: width == 0.0,
+            ..Unset::default()
+        };
+        Ok(Stroke {
+            width,
+            line_type,
+            color,
+            fill,
+            unset,
+        })
     }
 }
 
 impl<'a> Get<'a, &str, Vec<Justify>> for Sexp {
-    /// Get the LineType
+    /// Get the justify values.
     fn get(&self, key: &str) -> Result<Vec<Justify>, Error> {
         let mut justify = Vec::new();
-        let node: Vec<&Sexp> = self.get(key).unwrap();
-        if node.len() == 1 {
-            if let Sexp::Node(_, j) = node.get(0).unwrap() {
-                for _j in j {
-                    if let Sexp::Value(_j) = _j {
-                        if _j == "right" {
-                            justify.push(Justify::Right);
-                        } else if _j == "left" {
-                            justify.push(Justify::Left);
-                        } else if _j == "top" {
-                            justify.push(Justify::Top);
-                        } else if _j == "bottom" {
-                            justify.push(Justify::Bottom);
-                        } else if _j == "mirror" {
-                            justify.push(Justify::Mirror);
-                        } else {
-                            return Err(Error::JustifyValueError);
-                        }
-                    } else {
-                        return Err(Error::ExpectValueNode);
-                    }
+        if let Sexp::Node(_, values) = child(self, key)? {
+            for value in values {
+                if let Sexp::Value(value) = value {
+                    justify.push(match value.as_str() {
+                        "right" => Justify::Right,
+                        "left" => Justify::Left,
+                        "top" => Justify::Top,
+                        "bottom" => Justify::Bottom,
+                        "mirror" => Justify::Mirror,
+                        _ => return Err(Error::JustifyValueError),
+                    });
+                } else {
+                    return Err(Error::ExpectValueNode);
                 }
-            } else {
-                return Err(Error::ExpectSexpNode);
             }
-        } else {
-            return Err(Error::JustifyValueError);
         }
         Ok(justify)
     }
 }
 
 impl<'a> Get<'a, &str, FillType> for Sexp {
-    /// Get the LineType
+    /// Get the FillType
     fn get(&self, key: &str) -> Result<FillType, Error> {
-        let nodes: Vec<&Sexp> = get!(self, key).unwrap();
-        let myfill: &Sexp = nodes.get(0).unwrap();
-        let mytype: String = get!(myfill, "type", 0);
-        if mytype == "none" {
-            Ok(FillType::None)
-        } else if mytype == "outline" {
-            Ok(FillType::Outline)
-        } else if mytype == "background" {
-            Ok(FillType::Background)
-        } else {
-            Ok(FillType::None)
-        }
+        let fill = child(self, key)?;
+        let filltype: Option<String> = optional(fill, "type", 0).map_err(|err| in_path(err, name(self)))?;
+        Ok(match filltype.as_deref() {
+            Some("outline") => FillType::Outline,
+            Some("background") => FillType::Background,
+            //the fill of sheets has a color and no type
+            Some("color") | None if fill.contains("color") => FillType::Color(
+                Color::try_from(child(fill, "color")?).map_err(|err| in_path(in_path(err, key), name(self)))?,
+            ),
+            _ => FillType::None,
+        })
     }
 }
 
 impl<'a> Get<'a, &str, LineType> for Sexp {
     /// Get the LineType
     fn get(&self, key: &str) -> Result<LineType, Error> {
-        let mytype: String = get!(self, key, 0);
-        if mytype == "dash" {
-            Ok(LineType::Dash)
-        } else if mytype == "dash_dot" {
-            Ok(LineType::DashDot)
-        } else if mytype == "dash_dot_dot" {
-            Ok(LineType::DashDotDot)
-        } else if mytype == "dot" {
-            Ok(LineType::Dot)
-        } else if mytype == "default" {
-            Ok(LineType::Default)
-        } else if mytype == "solid" {
-            Ok(LineType::Solid)
-        } else {
-            Err(Error::LineTypeValueError)
+        let mytype: String = get!(self, key, 0)?;
+        match mytype.as_str() {
+            "dash" => Ok(LineType::Dash),
+            "dash_dot" => Ok(LineType::DashDot),
+            "dash_dot_dot" => Ok(LineType::DashDotDot),
+            "dot" => Ok(LineType::Dot),
+            "default" => Ok(LineType::Default),
+            "solid" => Ok(LineType::Solid),
+            _ => Err(Error::LineTypeValueError),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sexp::parser::SexpParser;
+
+    fn node(content: &str) -> Sexp {
+        let doc: SexpParser = content.parse().unwrap();
+        let node = doc.values().next().unwrap().clone();
+        node
+    }
+
+    #[test]
+    fn get_values() {
+        let node = node("(kicad_sch (symbol (lib_id \"Device:R\") (at 10 20.5 90) (unit 2)))");
+        let lib_id: String = get!(&node, "lib_id", 0).unwrap();
+        assert_eq!(lib_id, "Device:R");
+        let angle: f64 = get!(&node, "at", 2).unwrap();
+        assert_eq!(angle, 90.0);
+        let at: Array1<f64> = get!(&node, "at").unwrap();
+        assert_eq!(at, arr1(&[10.0, 20.5]));
+        let unit: usize = get!(&node, "unit", 0).unwrap();
+        assert_eq!(unit, 2);
+    }
+    #[test]
+    fn get_errors() {
+        let node = node("(kicad_sch (symbol (at 10 x) (pts (xy 1 y))))");
+        assert!(matches!(
+            get!(&node, "lib_id", 0) as Result<String, Error>,
+            Err(Error::KeyNotFound(path, key)) if path == "symbol" && key == "lib_id"
+        ));
+        assert!(matches!(
+            get!(&node, "at", 2) as Result<f64, Error>,
+            Err(Error::KeyNotFound(path, key)) if path == "symbol/at" && key == "2"
+        ));
+        assert!(matches!(
+            get!(&node, "at") as Result<Array1<f64>, Error>,
+            Err(Error::ValueParseError(path, value)) if path == "symbol/at" && value == "x"
+        ));
+        assert!(matches!(
+            get!(&node, "pts") as Result<Array2<f64>, Error>,
+            Err(Error::ValueParseError(path, value)) if path == "symbol/pts/xy" && value == "y"
+        ));
+        assert!(matches!(get!(&node, "effects") as Result<Effects, Error>, Err(Error::KeyNotFound(..))));
+        assert!(matches!(get!(&node, "stroke") as Result<Stroke, Error>, Err(Error::KeyNotFound(..))));
+    }
+    #[test]
+    fn get_optional() {
+        let node = node("(kicad_sch (symbol (at 10 20) (mirror x)))");
+        let angle: Option<f64> = get!(&node, "at", 2).unwrap();
+        assert_eq!(angle, None);
+        let mirror: Option<String> = optional(&node, "mirror", 0).unwrap();
+        assert_eq!(mirror, Some(String::from("x")));
+        let unit: Option<usize> = optional(&node, "unit", 0).unwrap();
+        assert_eq!(unit, None);
+        let effects: Option<Effects> = node.get("effects").unwrap();
+        assert_eq!(effects, None);
+        let mirror: Vec<&Sexp> = node.get("mirror").unwrap();
+        assert!(mirror[0].has(0));
+        assert!(!mirror[0].has(1));
+        assert!(node.contains(0));
+        assert!(!node.contains(5));
+    }
+    #[test]
+    fn get_effects() {
+        let text = node("(kicad_sch (text \"x\" (effects (font (face \"Arial\") (size 2.0 1.5) (thickness 0.3) bold italic (color 255 0 51 0.5) (line_spacing 1.2)) (justify left top) hide (href \"https://example.com\"))))");
+        let effects: Effects = get!(&text, "effects").unwrap();
+        assert_eq!(effects.font, "Arial");
+        assert_eq!(effects.size, 2.0);
+        assert_eq!(effects.width, 1.5);
+        assert_eq!(effects.thickness, 0.3);
+        assert!(effects.bold);
+        assert!(effects.italic);
+        assert_eq!(effects.color, Color { r: 1.0, g: 0.0, b: 0.2, a: 0.5 });
+        assert_eq!(effects.line_spacing, 1.2);
+        assert_eq!(effects.justify, vec![Justify::Left, Justify::Top]);
+        assert!(effects.hide);
+        assert_eq!(effects.href, Some(String::from("https://example.com")));
+        assert_eq!(effects.unset, Unset::default());
+
+        //KiCad 8 writes the flags as nodes
+        let text = node("(kicad_sch (text \"x\" (effects (font (size 1.27 1.27) (bold yes) (italic no)) (hide yes))))");
+        let effects: Effects = get!(&text, "effects").unwrap();
+        assert!(effects.bold);
+        assert!(!effects.italic);
+        assert!(effects.hide);
+        assert_eq!(effects.font, "default");
+        assert_eq!(effects.justify, vec![Justify::Center]);
+        assert_eq!(effects.href, None);
+        assert!(effects.unset.font && effects.unset.color && effects.unset.thickness && effects.unset.line_spacing);
+        assert!(!effects.unset.size && !effects.unset.width);
+    }
+    #[test]
+    fn get_stroke_and_fill() {
+        let rectangle = node("(kicad_sch (rectangle (stroke (width 0.254) (type dash) (color 0 255 0 1)) (fill (type color) (color 0 0 255 0.25))))");
+        let stroke: Stroke = get!(&rectangle, "stroke").unwrap();
+        assert_eq!(stroke.width, 0.254);
+        assert_eq!(stroke.line_type, LineType::Dash);
+        assert_eq!(stroke.color, Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 });
+        assert_eq!(stroke.fill, FillType::Color(Color { r: 0.0, g: 0.0, b: 1.0, a: 0.25 }));
+        assert_eq!(stroke.unset, Unset::default());
+
+        let polyline = node("(kicad_sch (polyline (stroke (width 0) (type default) (color 0 0 0 0)) (fill (type background))))");
+        let stroke: Stroke = get!(&polyline, "stroke").unwrap();
+        assert_eq!(stroke.color, Color { r: 0.0, g: 0.0, b: 0.0, a: 0.0 });
+        assert!(stroke.unset.color && stroke.unset.width);
+        assert_eq!(stroke.fill, FillType::Background);
+
+        let sheet = node("(kicad_sch (sheet (stroke (width 0) (type solid) (color 0 0 0 0)) (fill (color 255 255 0 0.5))))");
+        let stroke: Stroke = get!(&sheet, "stroke").unwrap();
+        assert_eq!(stroke.fill, FillType::Color(Color { r: 1.0, g: 1.0, b: 0.0, a: 0.5 }));
+    }
+}
@@ -11,7 +11,7 @@ pub fn libraries<'a>(sexp_parser: &'a SexpParser) -> Result<HashMap<String, &'a
        if let Sexp::Node(name, values) = element {
            if name == "lib_symbols" {
                for value in values {
-                   let name: String = value.get(0).unwrap();
+                   let name: String = value.get(0)?;
                    libraries.insert(name, value);
                }
            }
@@ -22,7 +22,7 @@ pub fn libraries<'a>(sexp_parser: &'a SexpParser) -> Result<HashMap<String, &'a
 
 /* pub fn iterate_units(node: &Sexp, libraries: HashMap<String, Sexp>, f: &dyn Fn(&Sexp) -> Result<(), Error>) {
 
-    let lib_id: String = get!(node, "lib_id", 0);
+    let lib_id: String = get!(node, "lib_id", 0).unwrap();
     let unit: usize = get_unit(node).unwrap();
     let library = libraries.get(&lib_id).unwrap();
     let syms: Vec<&Sexp> = library.get("symbol").unwrap();
@@ -34,15 +34,15 @@ pub fn libraries<'a>(sexp_parser: &'a SexpParser) -> Result<HashMap<String, &'a
     }
 } */
 
-pub fn iterate_unit_pins<'a>(node: &'a Sexp, libraries: &HashMap<String, &'a Sexp>) -> Vec<&'a Sexp> {
+pub fn iterate_unit_pins<'a>(node: &'a Sexp, libraries: &HashMap<String, &'a Sexp>) -> Result<Vec<&'a Sexp>, Error> {
 
     let mut items: Vec<&Sexp> = Vec::new();
-    let lib_id: String = get!(node, "lib_id", 0);
-    let unit: usize = get_unit(node).unwrap();
-    let library = libraries.get(&lib_id).unwrap();
-    let syms: Vec<&Sexp> = library.get("symbol").unwrap();
+    let lib_id: String = get!(node, "lib_id", 0)?;
+    let unit: usize = get_unit(node)?;
+    let library = libraries.get(&lib_id).ok_or_else(|| Error::LibraryNotFound(lib_id.clone()))?;
+    let syms: Vec<&Sexp> = library.get("symbol")?;
     for _unit in syms {
-        let unit_number = get_unit(_unit).unwrap();
+        let unit_number = get_unit(_unit)?;
         if unit_number == 0 || unit_number == unit {
             if let Sexp::Node(_, values) = _unit {
                 for el in values {
@@ -55,7 +55,7 @@ pub fn iterate_unit_pins<'a>(node: &'a Sexp, libraries: &HashMap<String, &'a Sex
             }
         }
     }
-    items
+    Ok(items)
 }
 
 
@@ -71,7 +71,7 @@ mod tests {
         doc.values().for_each(|node|{
             if let Sexp::Node(name, _) = node {
                 if name == "symbol" {
-                    count = iterate_unit_pins(node, &libraries).iter().map(|node| -> Result<&Sexp, Error> {
+                    count = iterate_unit_pins(node, &libraries).unwrap().iter().map(|node| -> Result<&Sexp, Error> {
                        Ok(node)
                     }).count();
                 }
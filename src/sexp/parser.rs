@@ -1,123 +1,283 @@
 use memmap2::MmapOptions;
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
 use std::fs::File;
-use core::slice::Iter;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
 
 use crate::Error;
 use crate::sexp::Sexp;
+use crate::sexp::query::Query;
+use crate::sexp::writer;
 
-#[derive(PartialEq)]
-enum State {
-    Symbol,
-    Values,
+/// The position of a node in the source, line and column start at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
 }
 
-fn parser(iter: &mut Iter<u8>) -> Sexp {
-    let mut name = String::new();
-    let mut values = Vec::new();
-    let mut state = State::Symbol;
-    let mut s = String::new();
-    while let Some(ch) = iter.next() {
-        match *ch as char {
-            '(' => {
-                values.push(parser(iter));
+/// Tokenizer for the KiCad s-expressions.
+///
+/// The lexer works on the decoded characters and tracks the position
+/// of the current character.
+struct Lexer<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str, content: &'a str) -> Self {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        Self {
+            source,
+            chars: content.char_indices().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, position: Position, message: &str) -> Error {
+        Error::SexpParseError(
+            self.source.to_string(),
+            position.line,
+            position.column,
+            message.to_string(),
+        )
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, ch)| *ch)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let (_, ch) = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace() {
+                self.next();
+            } else {
+                break;
             }
-            ')' => {
-                if !s.is_empty() {
-                    if state == State::Symbol {
-                        name = s.to_string();
-                    } else {
-                        values.push(Sexp::Value(s.to_string()));
-                        s.clear();
-                    }
-                }
+        }
+    }
+
+    /// Read an unquoted token.
+    fn atom(&mut self) -> String {
+        let mut atom = String::new();
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace() || ch == '(' || ch == ')' || ch == '"' {
                 break;
             }
-            '"' => {
-                let mut text = String::new();
-                let mut last_char = '\0';
-                loop {
-                    // collect the characters to the next quote
-                    if let Some(ch) = iter.next() {
-                        if *ch as char == '"' && last_char != '\\' {
-                            break;
-                        } else {
-                            text.push(*ch as char);
-                            last_char = *ch as char;
+            atom.push(ch);
+            self.next();
+        }
+        atom
+    }
+
+    /// Read a quoted string, the opening quote is already consumed.
+    fn string(&mut self, start: Position) -> Result<String, Error> {
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error(start, "unterminated string, expected '\"'")),
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let escape = self.position();
+                    match self.next() {
+                        None => return Err(self.error(start, "unterminated string, expected '\"'")),
+                        Some('n') => text.push('\n'),
+                        Some('r') => text.push('\r'),
+                        Some('t') => text.push('\t'),
+                        Some('a') => text.push('\u{07}'),
+                        Some('b') => text.push('\u{08}'),
+                        Some('f') => text.push('\u{0c}'),
+                        Some('v') => text.push('\u{0b}'),
+                        Some('x') => {
+                            let mut code = String::new();
+                            while code.len() < 2 && matches!(self.peek(), Some(c) if c.is_ascii_hexdigit()) {
+                                code.push(self.next().unwrap());
+                            }
+                            text.push(self.code(&code, 16, escape)?);
+                        }
+                        Some(ch) if ch.is_digit(8) => {
+                            let mut code = ch.to_string();
+                            while code.len() < 3 && matches!(self.peek(), Some(c) if c.is_digit(8)) {
+                                code.push(self.next().unwrap());
+                            }
+                            text.push(self.code(&code, 8, escape)?);
                         }
+                        //escaped quotes, backslashes and unknown escapes
+                        Some(ch) => text.push(ch),
                     }
                 }
-                values.push(Sexp::Text(text));
+                Some(ch) => text.push(ch),
             }
-            ' ' | '\n' => {
-                if state == State::Symbol {
-                    name = s.to_string();
-                    s.clear();
-                    state = State::Values;
-                } else if state == State::Values {
-                    if !s.is_empty() {
-                        //println!("{} {}", s.to_string(), s.len());
-                        values.push(Sexp::Value(s.to_string()));
-                        s.clear();
-                    }
-                }
+        }
+    }
+
+    fn code(&self, code: &str, radix: u32, position: Position) -> Result<char, Error> {
+        u32::from_str_radix(code, radix)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(position, "invalid escape sequence"))
+    }
+}
+
+/// Parse a node, the opening bracket is already consumed.
+fn node(lexer: &mut Lexer<'_>, start: Position, positions: &mut Vec<Position>) -> Result<Sexp, Error> {
+    positions.push(start);
+    lexer.skip_whitespace();
+    let name_position = lexer.position();
+    let name = lexer.atom();
+    if name.is_empty() {
+        return Err(lexer.error(name_position, "expected node name"));
+    }
+    let mut values = Vec::new();
+    loop {
+        lexer.skip_whitespace();
+        let position = lexer.position();
+        match lexer.peek() {
+            None => return Err(lexer.error(position, &format!("expected ')' to close '{}'", name))),
+            Some(')') => {
+                lexer.next();
+                return Ok(Sexp::Node(name, values));
             }
-            c => {
-                s.push(c);
+            Some('(') => {
+                lexer.next();
+                values.push(node(lexer, position, positions)?);
             }
-        };
+            Some('"') => {
+                lexer.next();
+                values.push(Sexp::Text(lexer.string(position)?));
+            }
+            Some(_) => values.push(Sexp::Value(lexer.atom())),
+        }
     }
-    Sexp::Node(name, values)
 }
 
+/// Parse the content of a KiCad file.
+///
+/// Returns the root node and the positions of all nodes in pre-order.
+fn parse(source: &str, content: &str) -> Result<(Sexp, Vec<Position>), Error> {
+    let mut lexer = Lexer::new(source, content);
+    let mut positions = Vec::new();
+    lexer.skip_whitespace();
+    let position = lexer.position();
+    if lexer.next() != Some('(') {
+        return Err(lexer.error(position, "expected '('"));
+    }
+    let root = node(&mut lexer, position, &mut positions)?;
+    lexer.skip_whitespace();
+    if lexer.peek().is_some() {
+        return Err(lexer.error(lexer.position(), "expected end of file"));
+    }
+    Ok((root, positions))
+}
+
+/// Decode the bytes as UTF-8, invalid bytes are reported with their position.
+fn decode<'a>(source: &str, bytes: &'a [u8]) -> Result<&'a str, Error> {
+    std::str::from_utf8(bytes).map_err(|err| {
+        let valid = std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap();
+        let line = valid.matches('\n').count() + 1;
+        let column = valid.rsplit('\n').next().unwrap().chars().count() + 1;
+        Error::SexpParseError(source.to_string(), line, column, String::from("invalid UTF-8"))
+    })
+}
+
+#[pyclass]
 pub struct SexpParser {
     nodes: Sexp,
+    positions: Vec<Position>,
 }
 
 impl SexpParser {
     pub fn new() -> Self {
-        Self { nodes: Sexp::Node(String::from("kicad_sch"), Vec::new()) }
+        Self {
+            nodes: Sexp::Node(String::from("kicad_sch"), Vec::new()),
+            positions: Vec::new(),
+        }
     }
     pub fn load(filename: &str) -> Result<Self, Error> {
         let file = File::open(filename)?;
         let mmap = unsafe { MmapOptions::new().map(&file)? };
-        let iter = &mut mmap.iter();
-        iter.find(|c| **c as char == '(');
-        Ok(Self {
-            nodes: parser(iter),
-        })
+        let (nodes, positions) = parse(filename, decode(filename, &mmap)?)?;
+        Ok(Self { nodes, positions })
     }
-    pub fn save(&self, writer: &mut dyn Write) -> Result<(), Error> {
-        self.write_node(&self.nodes, writer, 0)
-    }
-    fn write_node(&self, node: &Sexp, writer: &mut dyn Write, indent: usize) -> Result<(), Error> {
-        let prefix = &String::from("  ").repeat(indent);
-        match node {
-            Sexp::Node(name, values) => {
-                if indent == 0 {
-                    write!(writer, "({}", name)?;
-                } else {
-                    write!(writer, "\n{}({}", prefix, name)?;
+
+    /// Parse the content of a stream, like stdin or a network response.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        let (nodes, positions) = parse("<stream>", decode("<stream>", &content)?)?;
+        Ok(Self { nodes, positions })
+    }
+
+    /// Get the position of a node in the source file.
+    ///
+    /// Nodes that were added after loading have no position. The positions
+    /// are indexed by the pre-order of the nodes, they are dropped when the
+    /// document is edited with `query_mut`.
+    pub fn position(&self, node: &Sexp) -> Option<Position> {
+        fn find(current: &Sexp, node: &Sexp, index: &mut usize) -> Option<usize> {
+            if let Sexp::Node(_, values) = current {
+                if std::ptr::eq(current, node) {
+                    return Some(*index);
                 }
-                for n in values.iter() {
-                    self.write_node(n, writer, indent + 1)?;
+                *index += 1;
+                for value in values {
+                    if let Some(found) = find(value, node, index) {
+                        return Some(found);
+                    }
                 }
-                write!(writer, ")")?;
-            }
-            Sexp::Value(value) => {
-                write!(writer, " {}", value)?;
-            }
-            Sexp::Text(text) => {
-                write!(writer, " \"{}\"", text)?;
-            }
-            Sexp::Empty => {
-                return Err(Error::NotLoaded);
             }
+            None
         }
-        if indent == 0 {
-            write!(writer, "\n")?;
-        }
-        Ok(())
+        let mut index = 0;
+        find(&self.nodes, node, &mut index).and_then(|index| self.positions.get(index).copied())
+    }
+    /// Write the document in the KiCad file format.
+    pub fn save(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        writer::write(&self.nodes, writer)
+    }
+
+    /// The root node of the document.
+    pub fn root(&self) -> &Sexp {
+        &self.nodes
+    }
+
+    /// Select nodes with a query like `symbol[lib_id="Device:R"]/property[0="Value"]`.
+    pub fn query(&self, query: &str) -> Result<Vec<&Sexp>, Error> {
+        Ok(query.parse::<Query>()?.select(&self.nodes))
+    }
+
+    /// Select nodes for editing, see `Query::select_mut`.
+    ///
+    /// The nodes can be changed in any way, the source positions are dropped.
+    pub fn query_mut(&mut self, query: &str) -> Result<Vec<&mut Sexp>, Error> {
+        let query = query.parse::<Query>()?;
+        self.positions.clear();
+        Ok(query.select_mut(&mut self.nodes))
     }
 
     pub fn values(&self) -> impl Iterator<Item = &Sexp> {
@@ -125,6 +285,9 @@ impl SexpParser {
             values.into_iter()
         } else { panic!("nodes not set."); }
     }
+    /// Append a node to the document.
+    ///
+    /// The node is the last in pre-order, the positions of the other nodes stay valid.
     pub fn push(&mut self, node: Sexp) -> Result<(), Error> {
         if let Sexp::Node(_, ref mut values) = &mut self.nodes {
             values.push(node);
@@ -135,6 +298,101 @@ impl SexpParser {
     }
 }
 
+impl FromStr for SexpParser {
+    type Err = Error;
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let (nodes, positions) = parse("<string>", content)?;
+        Ok(Self { nodes, positions })
+    }
+}
+
+#[pymethods]
+impl SexpParser {
+    /// Load a KiCad file.
+    #[staticmethod]
+    #[pyo3(name = "load")]
+    fn py_load(filename: &str) -> Result<Self, Error> {
+        Self::load(filename)
+    }
+
+    /// Parse the content of a KiCad file, the content can be str or bytes.
+    #[staticmethod]
+    fn parse(content: &PyAny) -> PyResult<Self> {
+        if let Ok(content) = content.extract::<&str>() {
+            Ok(content.parse::<Self>()?)
+        } else if let Ok(content) = content.downcast::<PyBytes>() {
+            Ok(Self::from_reader(content.as_bytes())?)
+        } else {
+            Err(PyTypeError::new_err("content must be str or bytes"))
+        }
+    }
+
+    /// Write the document to a file.
+    #[pyo3(name = "save")]
+    fn py_save(&self, filename: &str) -> Result<(), Error> {
+        let mut file = File::create(filename)?;
+        self.save(&mut file)
+    }
+
+    /// Select nodes with a query, the nodes are returned in the KiCad file format.
+    #[pyo3(name = "query")]
+    fn py_query(&self, query: &str) -> Result<Vec<String>, Error> {
+        self.query(query)?
+            .into_iter()
+            .map(|node| {
+                let mut content: Vec<u8> = Vec::new();
+                writer::write(node, &mut content)?;
+                Ok(String::from_utf8_lossy(&content).trim_end().to_string())
+            })
+            .collect()
+    }
+
+    /// Get the values at the index of the selected nodes.
+    fn query_values(&self, query: &str, index: usize) -> Result<Vec<String>, Error> {
+        Ok(self
+            .query(query)?
+            .into_iter()
+            .filter_map(|node| match node {
+                Sexp::Node(_, values) => match values.get(index) {
+                    Some(Sexp::Value(value)) | Some(Sexp::Text(value)) => Some(value.to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Set the value at the index of the selected nodes, returns the number of changed nodes.
+    ///
+    /// Nested nodes are changed like the other selected nodes.
+    fn set_values(&mut self, query: &str, index: usize, value: &str) -> Result<usize, Error> {
+        let query = query.parse::<Query>()?;
+        self.positions.clear();
+        let mut count = 0;
+        query.for_each_mut(&mut self.nodes, |node| {
+            if let Sexp::Node(_, values) = node {
+                let new_value = match values.get(index) {
+                    Some(Sexp::Value(_)) => Sexp::Value(value.to_string()),
+                    _ => Sexp::Text(value.to_string()),
+                };
+                if index < values.len() {
+                    values[index] = new_value;
+                } else {
+                    values.push(new_value);
+                }
+                count += 1;
+            }
+        });
+        Ok(count)
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        let mut content: Vec<u8> = Vec::new();
+        self.save(&mut content)?;
+        Ok(String::from_utf8_lossy(&content).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,7 +505,7 @@ mod tests {
                                         let prop_name: String = get!(prop, 0).unwrap();
                                         if prop_name == "ki_description" {
                                             let prop_value: String = get!(prop, 1).unwrap();
-                                            assert_eq!(prop_value, "Power symbol creates a global label with name \\\"+15V\\\"");
+                                            assert_eq!(prop_value, "Power symbol creates a global label with name \"+15V\"");
                                         }
                                     }
                                     break;
@@ -291,7 +549,7 @@ mod tests {
             match n {
                 Sexp::Node(ref name, ref _values) if name == "symbol" => {
                     count += 1;
-                    let lib_id: String = get!(n, "lib_id", 0);
+                    let lib_id: String = get!(n, "lib_id", 0).unwrap();
                     assert_eq!(lib_id, "Device:R");
                     break;
                 }
@@ -300,4 +558,75 @@ mod tests {
         }
         assert_eq!(count, 1);
     }
+    #[test]
+    fn parse_utf8() {
+        let (node, _) = parse("test", "(property \"Description\" \"10kΩ 1% µ-Power Größe\")").unwrap();
+        let value: String = get!(&node, 1).unwrap();
+        assert_eq!(value, "10kΩ 1% µ-Power Größe");
+    }
+    #[test]
+    fn parse_escapes() {
+        let (node, _) = parse("test", r#"(text "say \"hi\"\nC:\\temp \x41\101")"#).unwrap();
+        let value: String = get!(&node, 0).unwrap();
+        assert_eq!(value, "say \"hi\"\nC:\\temp AA");
+    }
+    #[test]
+    fn parse_whitespace() {
+        let (node, _) = parse("test", "(wire\t(pts\r\n\t(xy 1 2)\t(xy 3 4)))\r\n").unwrap();
+        let pts: Array2<f64> = node.get("pts").unwrap();
+        assert_eq!(pts[[1, 0]], 3.0);
+    }
+    #[test]
+    fn parse_positions() {
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        assert_eq!(doc.position(&doc.nodes), Some(Position { line: 1, column: 1 }));
+        let label = doc.values().find(|n| matches!(n, Sexp::Node(name, _) if name == "label")).unwrap();
+        assert_eq!(doc.position(label), Some(Position { line: 449, column: 3 }));
+    }
+    #[test]
+    fn positions_after_edit() {
+        let mut doc: SexpParser = "(kicad_sch\n  (paper \"A4\")\n  (label \"IN\"))".parse().unwrap();
+        doc.push(Sexp::Node(String::from("wire"), vec![Sexp::Node(String::from("pts"), Vec::new())])).unwrap();
+        let position = |doc: &SexpParser, index: usize| doc.position(doc.values().nth(index).unwrap());
+        assert_eq!(position(&doc, 1), Some(Position { line: 3, column: 3 }));
+        assert_eq!(position(&doc, 2), None);
+
+        *doc.query_mut("paper").unwrap().remove(0) = Sexp::Node(String::from("paper"), Vec::new());
+        assert_eq!(position(&doc, 0), None);
+        assert_eq!(position(&doc, 1), None);
+        assert_eq!(doc.position(&doc.nodes), None);
+    }
+    #[test]
+    fn parse_errors() {
+        let error = |content: &str| match parse("test.kicad_sch", content) {
+            Err(Error::SexpParseError(file, line, column, _)) => (file, line, column),
+            other => panic!("expected parse error: {:?}", other.map(|n| n.0)),
+        };
+        assert_eq!(error("(kicad_sch\n  (text \"unterminated)\n"), (String::from("test.kicad_sch"), 2, 9));
+        assert_eq!(error("(kicad_sch\n  (version 1)"), (String::from("test.kicad_sch"), 2, 14));
+        assert_eq!(error("(kicad_sch ())"), (String::from("test.kicad_sch"), 1, 13));
+        assert_eq!(error("kicad_sch"), (String::from("test.kicad_sch"), 1, 1));
+        assert_eq!(error("(kicad_sch) (version 1)"), (String::from("test.kicad_sch"), 1, 13));
+        assert!(matches!(decode("test", b"(a\n (b \xff))"), Err(Error::SexpParseError(_, 2, 5, _))));
+    }
+    #[test]
+    fn parse_string() {
+        let doc: SexpParser = "(kicad_sch (version 20211123) (generator eeschema)\n  (paper \"A4\")\n)"
+            .parse()
+            .unwrap();
+        let paper: String = get!(doc.values().nth(2).unwrap(), 0).unwrap();
+        assert_eq!(paper, "A4");
+        assert!(matches!(
+            "(kicad_sch (version 1)".parse::<SexpParser>(),
+            Err(Error::SexpParseError(source, 1, 23, _)) if source == "<string>"
+        ));
+    }
+    #[test]
+    fn parse_reader() {
+        let file = File::open("samples/files/summe/summe.kicad_sch").unwrap();
+        let doc = SexpParser::from_reader(file).unwrap();
+        assert_eq!(doc.values().count(), 51);
+        let doc = SexpParser::from_reader("(kicad_sch (paper \"A4\"))".as_bytes()).unwrap();
+        assert_eq!(doc.values().count(), 1);
+    }
 }
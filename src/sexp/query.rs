@@ -0,0 +1,438 @@
+use crate::Error;
+use crate::sexp::Sexp;
+use regex::Regex;
+
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+/// The left side of a predicate: a child node or a value index.
+#[derive(Debug, Clone)]
+enum Operand {
+    Child(String),
+    Index(usize),
+}
+
+impl Operand {
+    /// The child node value or the value at the index.
+    fn value<'a>(&self, node: &'a Sexp) -> Option<&'a str> {
+        let values = if let Sexp::Node(_, values) = node { values } else { return None };
+        let value = match self {
+            Operand::Index(index) => values.get(*index),
+            Operand::Child(key) => match values
+                .iter()
+                .find(|value| matches!(value, Sexp::Node(name, _) if name == key))
+            {
+                Some(Sexp::Node(_, values)) => values.first(),
+                _ => None,
+            },
+        };
+        match value {
+            Some(Sexp::Value(value)) | Some(Sexp::Text(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn exists(&self, node: &Sexp) -> bool {
+        match (self, node) {
+            (Operand::Index(index), Sexp::Node(_, values)) => *index < values.len(),
+            (Operand::Child(key), Sexp::Node(_, values)) => values
+                .iter()
+                .any(|value| matches!(value, Sexp::Node(name, _) if name == key)),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Predicate {
+    Exists(Operand),
+    Equals(Operand, String),
+    NotEquals(Operand, String),
+    Matches(Operand, Regex),
+}
+
+impl Predicate {
+    fn matches(&self, node: &Sexp) -> bool {
+        match self {
+            Predicate::Exists(operand) => operand.exists(node),
+            Predicate::Equals(operand, value) => operand.value(node) == Some(value),
+            Predicate::NotEquals(operand, value) => operand.value(node) != Some(value),
+            Predicate::Matches(operand, regex) => matches!(operand.value(node), Some(value) if regex.is_match(value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    /// The step matches nodes at any depth, written as `//`.
+    descendant: bool,
+    /// The node name, `None` for the wildcard `*`.
+    name: Option<String>,
+    predicates: Vec<Predicate>,
+}
+
+impl Step {
+    fn matches(&self, node: &Sexp) -> bool {
+        if let Sexp::Node(name, _) = node {
+            (self.name.is_none() || self.name.as_ref() == Some(name))
+                && self.predicates.iter().all(|predicate| predicate.matches(node))
+        } else {
+            false
+        }
+    }
+}
+
+/// A selector for nodes in a `Sexp` tree.
+///
+/// The steps are separated by `/` for the child nodes and `//` for the nodes
+/// at any depth. A step is a node name or `*` followed by predicates in brackets:
+///
+/// * `[uuid]` the node has a child node `uuid`.
+/// * `[lib_id="Device:R"]` the first value of the child node equals the text.
+/// * `[0="Value"]` the value at the index equals the text.
+/// * `!=` checks for not equal and `~=` matches a regular expression.
+///
+/// `symbol[lib_id="Device:R"]/property[0="Value"]` selects the value properties
+/// of all resistors, `//pin[number="1"]` all pins with the number 1.
+#[derive(Debug, Clone)]
+pub struct Query {
+    steps: Vec<Step>,
+}
+
+struct QueryParser<'a> {
+    query: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> QueryParser<'a> {
+    fn error(&mut self, message: &str) -> Error {
+        let pos = self.chars.peek().map(|(pos, _)| *pos).unwrap_or(self.query.len());
+        Error::QueryError(self.query.to_string(), format!("{} at {}", message, pos + 1))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some((_, c)) if c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if matches!(self.chars.peek(), Some((_, c)) if *c == expected) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let mut identifier = String::new();
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_alphanumeric() || *c == '_' || *c == '-' || *c == '.' {
+                identifier.push(*c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        identifier
+    }
+
+    /// A quoted text or a bare value.
+    fn value(&mut self) -> Result<String, Error> {
+        let mut value = String::new();
+        if self.eat('"') {
+            loop {
+                match self.chars.next() {
+                    Some((_, '"')) => return Ok(value),
+                    //only quotes and backslashes are escaped, regular expressions keep `\d`
+                    Some((_, '\\')) => match self.chars.next() {
+                        Some((_, c)) if c == '"' || c == '\\' => value.push(c),
+                        Some((_, c)) => {
+                            value.push('\\');
+                            value.push(c);
+                        }
+                        None => return Err(self.error("unterminated string")),
+                    },
+                    Some((_, c)) => value.push(c),
+                    None => return Err(self.error("unterminated string")),
+                }
+            }
+        }
+        while let Some((_, c)) = self.chars.peek() {
+            if *c == ']' || c.is_whitespace() {
+                break;
+            }
+            value.push(*c);
+            self.chars.next();
+        }
+        if value.is_empty() {
+            Err(self.error("expected value"))
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Predicate, Error> {
+        self.skip_whitespace();
+        let operand = self.identifier();
+        if operand.is_empty() {
+            return Err(self.error("expected key or index"));
+        }
+        let operand = match operand.parse::<usize>() {
+            Ok(index) => Operand::Index(index),
+            Err(_) => Operand::Child(operand),
+        };
+        self.skip_whitespace();
+        let predicate = if matches!(self.chars.peek(), Some((_, ']'))) {
+            Predicate::Exists(operand)
+        } else if self.eat('=') {
+            self.skip_whitespace();
+            Predicate::Equals(operand, self.value()?)
+        } else if self.eat('!') {
+            if !self.eat('=') {
+                return Err(self.error("expected '='"));
+            }
+            self.skip_whitespace();
+            Predicate::NotEquals(operand, self.value()?)
+        } else if self.eat('~') {
+            if !self.eat('=') {
+                return Err(self.error("expected '='"));
+            }
+            self.skip_whitespace();
+            let pattern = self.value()?;
+            let regex = Regex::new(&pattern)
+                .map_err(|err| Error::QueryError(self.query.to_string(), err.to_string()))?;
+            Predicate::Matches(operand, regex)
+        } else {
+            return Err(self.error("expected operator"));
+        };
+        self.skip_whitespace();
+        if !self.eat(']') {
+            return Err(self.error("expected ']'"));
+        }
+        Ok(predicate)
+    }
+
+    fn parse(&mut self) -> Result<Query, Error> {
+        let mut steps = Vec::new();
+        self.skip_whitespace();
+        loop {
+            let mut descendant = false;
+            if self.eat('/') {
+                descendant = self.eat('/');
+            } else if !steps.is_empty() {
+                return Err(self.error("expected '/'"));
+            }
+            //the wildcard is only a whole step, not a part of a name
+            let name = if self.eat('*') {
+                None
+            } else {
+                match self.identifier() {
+                    name if name.is_empty() => return Err(self.error("expected node name")),
+                    name => Some(name),
+                }
+            };
+            let mut predicates = Vec::new();
+            while self.eat('[') {
+                predicates.push(self.predicate()?);
+            }
+            steps.push(Step { descendant, name, predicates });
+            self.skip_whitespace();
+            if self.chars.peek().is_none() {
+                break;
+            }
+        }
+        Ok(Query { steps })
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        QueryParser { query, chars: query.char_indices().peekable() }.parse()
+    }
+}
+
+impl Query {
+    fn walk(&self, node: &Sexp, step: usize, path: &mut Vec<usize>, result: &mut Vec<Vec<usize>>) {
+        if step == self.steps.len() {
+            result.push(path.clone());
+            return;
+        }
+        if let Sexp::Node(_, values) = node {
+            let current = &self.steps[step];
+            for (index, child) in values.iter().enumerate() {
+                path.push(index);
+                if current.matches(child) {
+                    self.walk(child, step + 1, path, result);
+                }
+                if current.descendant {
+                    self.walk(child, step, path, result);
+                }
+                path.pop();
+            }
+        }
+    }
+
+    /// The index paths of the selected nodes in document order.
+    pub fn paths(&self, node: &Sexp) -> Vec<Vec<usize>> {
+        let mut result = Vec::new();
+        self.walk(node, 0, &mut Vec::new(), &mut result);
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    /// Select the nodes below the node.
+    pub fn select<'a>(&self, node: &'a Sexp) -> Vec<&'a Sexp> {
+        self.paths(node)
+            .iter()
+            .filter_map(|path| {
+                path.iter().try_fold(node, |node, index| match node {
+                    Sexp::Node(_, values) => values.get(*index),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// Select the first node.
+    pub fn first<'a>(&self, node: &'a Sexp) -> Option<&'a Sexp> {
+        self.select(node).into_iter().next()
+    }
+
+    /// Select the nodes for editing.
+    ///
+    /// When a selected node contains other selected nodes only the outer node
+    /// is returned, the mutable references can not overlap. Use `for_each_mut`
+    /// to visit the same nodes as `select`.
+    pub fn select_mut<'a>(&self, node: &'a mut Sexp) -> Vec<&'a mut Sexp> {
+        fn resolve<'a>(node: &'a mut Sexp, paths: &[&Vec<usize>], depth: usize, result: &mut Vec<&'a mut Sexp>) {
+            if paths.iter().any(|path| path.len() == depth) {
+                result.push(node);
+            } else if let Sexp::Node(_, values) = node {
+                for (index, child) in values.iter_mut().enumerate() {
+                    let paths: Vec<&Vec<usize>> = paths.iter().copied().filter(|path| path[depth] == index).collect();
+                    if !paths.is_empty() {
+                        resolve(child, &paths, depth + 1, result);
+                    }
+                }
+            }
+        }
+        let paths = self.paths(node);
+        let mut result = Vec::new();
+        if !paths.is_empty() {
+            resolve(node, &paths.iter().collect::<Vec<&Vec<usize>>>(), 0, &mut result);
+        }
+        result
+    }
+
+    /// Call the function for every node that `select` returns, including nested nodes.
+    ///
+    /// The nodes are visited in reverse document order, inner nodes before the
+    /// outer nodes, so that a change does not move the nodes that are not yet visited.
+    /// Returns the number of visited nodes.
+    pub fn for_each_mut<F: FnMut(&mut Sexp)>(&self, node: &mut Sexp, mut f: F) -> usize {
+        let paths = self.paths(node);
+        let mut count = 0;
+        for path in paths.iter().rev() {
+            let found = path.iter().try_fold(&mut *node, |node, index| match node {
+                Sexp::Node(_, values) => values.get_mut(*index),
+                _ => None,
+            });
+            if let Some(found) = found {
+                f(found);
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sexp::parser::SexpParser;
+
+    fn query(doc: &SexpParser, query: &str) -> Vec<String> {
+        query
+            .parse::<Query>()
+            .unwrap()
+            .select(doc.root())
+            .iter()
+            .filter_map(|node| match node {
+                Sexp::Node(_, values) => match values.get(1) {
+                    Some(Sexp::Text(value)) | Some(Sexp::Value(value)) => Some(value.to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn select_steps() {
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        assert_eq!(
+            query(&doc, r#"symbol[lib_id="Device:R"]/property[0="Reference"]"#),
+            vec!["R5", "R3", "R4"]
+        );
+        assert_eq!(query(&doc, r#"symbol[lib_id="Device:R"]/property[0=Value]"#).len(), 3);
+        assert_eq!(query(&doc, r#"//symbol[0="Device:R"]/property[0="Reference"]"#), vec!["R"]);
+        assert_eq!(query(&doc, r#"symbol/property[0="Reference"][1~="^U\d"]"#), vec!["U1", "U1", "U1"]);
+        assert_eq!(
+            query(&doc, r#"symbol[lib_id!="Device:R"][lib_id~="^power:"]/property[0="Value"]"#).len(),
+            "symbol[lib_id~=^power:]".parse::<Query>().unwrap().select(doc.root()).len()
+        );
+        let labels = "*[shape]".parse::<Query>().unwrap().select(doc.root());
+        assert_eq!(labels.len(), 2);
+        assert_eq!("/wire".parse::<Query>().unwrap().select(doc.root()).len(), 14);
+        assert!("//no_connect".parse::<Query>().unwrap().first(doc.root()).is_some());
+        assert!("symbol[lib_id=\"Device:X\"]".parse::<Query>().unwrap().select(doc.root()).is_empty());
+    }
+    #[test]
+    fn select_mut() {
+        let mut doc: SexpParser = "(kicad_sch (symbol \"R\" (symbol \"R_0\" (pin 1)) (pin 2)) (pin 3))".parse().unwrap();
+        let query: Query = "//symbol".parse().unwrap();
+        assert_eq!(query.select(doc.root()).len(), 2);
+        //the nested symbol is part of the outer symbol
+        assert_eq!(doc.query_mut("//symbol").unwrap().len(), 1);
+        for pin in doc.query_mut("//pin").unwrap() {
+            if let Sexp::Node(_, values) = pin {
+                values.push(Sexp::Value(String::from("hide")));
+            }
+        }
+        assert_eq!(doc.query("//pin[1=hide]").unwrap().len(), 3);
+
+        //for_each_mut visits the nested symbol like select
+        let mut root = doc.root().clone();
+        let mut names = Vec::new();
+        let count = query.for_each_mut(&mut root, |node| {
+            if let Sexp::Node(_, values) = node {
+                if let Some(Sexp::Text(name)) = values.first() {
+                    names.push(name.clone());
+                }
+                values.push(Sexp::Value(String::from("edited")));
+            }
+        });
+        assert_eq!(count, 2);
+        assert_eq!(names, vec!["R_0", "R"]);
+        assert!(query
+            .select(&root)
+            .iter()
+            .all(|node| matches!(node, Sexp::Node(_, values) if values.last() == Some(&Sexp::Value(String::from("edited"))))));
+    }
+    #[test]
+    fn query_errors() {
+        assert!(matches!("".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("symbol[".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("symbol[lib_id=\"R]".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("symbol[lib_id<1]".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("symbol property".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("symbol[0~=\"(\"]".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("sym*bol".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("symbol/*property".parse::<Query>(), Err(Error::QueryError(..))));
+        assert!(matches!("symbol[lib*id=1]".parse::<Query>(), Err(Error::QueryError(..))));
+    }
+}
@@ -37,3 +37,23 @@ impl Test<&str> for Sexp {
         false
     }
 }
+/// Test for values and child nodes by index.
+impl Test<usize> for Sexp {
+    /// The node has a value or text at the index.
+    fn has(&self, index: usize) -> bool {
+        if let Sexp::Node(_, values) = &self {
+            matches!(values.get(index), Some(Sexp::Value(_)) | Some(Sexp::Text(_)))
+        } else {
+            false
+        }
+    }
+
+    /// The node has a child node at the index.
+    fn contains(&self, index: usize) -> bool {
+        if let Sexp::Node(_, values) = &self {
+            matches!(values.get(index), Some(Sexp::Node(_, _)))
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::io::Write;
+
+use crate::Error;
+use crate::sexp::Sexp;
+
+/// Format a number like KiCad, with at most four decimals and without trailing zeros.
+pub fn format_number(value: f64) -> String {
+    let mut number = format!("{:.4}", value);
+    if number.contains('.') {
+        number = number.trim_end_matches('0').trim_end_matches('.').to_string();
+    }
+    if number == "-0" {
+        number = String::from("0");
+    }
+    number
+}
+
+/// Quote and escape a text.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Values are written as they are, unless they can not be read back without quotes.
+fn value(value: &str) -> String {
+    if value.is_empty() || value.chars().any(|c| c.is_whitespace() || c == '(' || c == ')' || c == '"') {
+        quote(value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// The group of a top level element, KiCad separates the groups with an empty line.
+fn group(name: &str) -> &str {
+    match name {
+        "version" | "generator" => "header",
+        "bus" | "polyline" => "wire",
+        name => name,
+    }
+}
+
+/// Check if the child node is written on a new line.
+///
+/// The rules follow the KiCad file format, `path` are the names of the
+/// nodes from the root to the parent of the child.
+fn wrap(path: &[&str], child: &str) -> bool {
+    let library = path.contains(&"lib_symbols") || path[0] == "kicad_symbol_lib";
+    let parent = path[path.len() - 1];
+    let grandparent = if path.len() > 1 { path[path.len() - 2] } else { "" };
+    match parent {
+        "kicad_sch" | "kicad_symbol_lib" | "kicad_wks" if path.len() == 1 => !matches!(child, "version" | "generator"),
+        "symbol" if matches!(grandparent, "lib_symbols" | "kicad_symbol_lib") => matches!(child, "property" | "symbol"),
+        "symbol" if library => true,
+        "symbol" => matches!(child, "in_bom" | "uuid" | "property" | "pin" | "instances" | "default_instance"),
+        "title_block" | "lib_symbols" | "sheet_instances" | "symbol_instances" => true,
+        "path" if grandparent == "symbol_instances" => child == "reference",
+        "property" => child == "effects",
+        "polyline" | "bezier" | "pts" if library => true,
+        "rectangle" | "circle" | "arc" | "text" if library => matches!(child, "stroke" | "fill" | "effects"),
+        "pin" if library => matches!(child, "name" | "number" | "alternate"),
+        "pin" if grandparent == "sheet" => matches!(child, "effects" | "uuid"),
+        "junction" => child == "uuid",
+        "wire" | "bus" | "polyline" | "bus_entry" => matches!(child, "stroke" | "uuid"),
+        "label" | "global_label" | "hierarchical_label" | "text" => matches!(child, "effects" | "uuid" | "property"),
+        "sheet" => matches!(child, "stroke" | "fill" | "uuid" | "property" | "pin" | "instances"),
+        "image" => matches!(child, "uuid" | "data"),
+        _ => false,
+    }
+}
+
+fn write_node<'a>(node: &'a Sexp, path: &mut Vec<&'a str>, writer: &mut dyn Write) -> Result<(), Error> {
+    match node {
+        Sexp::Node(name, values) => {
+            write!(writer, "({}", name)?;
+            path.push(name);
+            let indent = "  ".repeat(path.len());
+            let mut wrapped = false;
+            let mut last_group: Option<&str> = None;
+            for child in values {
+                match child {
+                    Sexp::Node(child_name, _) if wrap(path, child_name) => {
+                        //empty line between the element groups of the root node
+                        if path.len() == 1 {
+                            let group = group(child_name);
+                            if last_group != Some(group) || group == "symbol" || group == "sheet" {
+                                writeln!(writer)?;
+                            }
+                            last_group = Some(group);
+                        }
+                        write!(writer, "\n{}", indent)?;
+                        wrapped = true;
+                    }
+                    Sexp::Node(child_name, _) => {
+                        if path.len() == 1 {
+                            last_group = Some(group(child_name));
+                        }
+                        write!(writer, " ")?;
+                    }
+                    //image data is written line by line
+                    _ if name == "data" => {
+                        write!(writer, "\n{}", indent)?;
+                        wrapped = true;
+                    }
+                    _ => write!(writer, " ")?,
+                }
+                write_node(child, path, writer)?;
+            }
+            path.pop();
+            if wrapped {
+                write!(writer, "\n{})", "  ".repeat(path.len()))?;
+            } else {
+                write!(writer, ")")?;
+            }
+        }
+        Sexp::Value(text) => write!(writer, "{}", value(text))?,
+        Sexp::Text(text) => write!(writer, "{}", quote(text))?,
+        Sexp::Empty => return Err(Error::NotLoaded),
+    }
+    Ok(())
+}
+
+/// Write the node in the KiCad file format.
+pub fn write(node: &Sexp, writer: &mut dyn Write) -> Result<(), Error> {
+    write_node(node, &mut Vec::new(), writer)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sexp::parser::SexpParser;
+
+    #[test]
+    fn round_trip() {
+        let content = std::fs::read("samples/files/summe/summe.kicad_sch").unwrap();
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        doc.save(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), String::from_utf8(content).unwrap());
+    }
+    #[test]
+    fn escape_text() {
+        let node = Sexp::Node(
+            String::from("text"),
+            vec![
+                Sexp::Text(String::from("say \"hi\"\nC:\\temp")),
+                Sexp::Value(String::from("two words")),
+                Sexp::Value(String::from("hide")),
+            ],
+        );
+        let mut out: Vec<u8> = Vec::new();
+        write(&node, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "(text \"say \\\"hi\\\"\\nC:\\\\temp\" \"two words\" hide)\n");
+    }
+    #[test]
+    fn numbers() {
+        assert_eq!(format_number(96.52000000000001), "96.52");
+        assert_eq!(format_number(0.0), "0");
+        assert_eq!(format_number(-0.00001), "0");
+        assert_eq!(format_number(25.0), "25");
+        assert_eq!(format_number(-1.27), "-1.27");
+        assert_eq!(format_number(106.5784), "106.5784");
+    }
+}
@@ -0,0 +1,287 @@
+//! Pure Rust SVG backend.
+//!
+//! The items are drawn with the `svg` crate, the coordinates of the document are
+//! in mm. The groups of the schematic elements keep their attributes as `data-`
+//! attributes.
+use std::io::Write;
+
+use ndarray::{arr1, Array1, Array2};
+use svg::node::element::path::Data;
+use svg::node::element::{Circle as SvgCircle, Element, Group, Image as SvgImage, Path, Rectangle as SvgRectangle, SVG};
+use svg::Node;
+
+use crate::geometry::ArcGeometry;
+use crate::plotter::display_list::{Node as DisplayNode, Page};
+use crate::plotter::{text_offset, Backend, DisplayList, PlotItem, Text};
+use crate::sexp::writer::format_number;
+use crate::sexp::{Color, LineType};
+use crate::Error;
+
+/// Backend for SVG files, the file contains the first page.
+pub struct SvgPlotter {}
+
+impl SvgPlotter {
+    pub fn new() -> Self {
+        SvgPlotter {}
+    }
+}
+
+impl Default for SvgPlotter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for SvgPlotter {
+    fn write(&self, display_list: &DisplayList, mut file: Box<dyn Write>, border: bool, scale: f64) -> Result<(), Error> {
+        let page = display_list.pages().first().ok_or_else(|| Error::ValueError(String::from("no page to plot")))?;
+        svg::write(&mut file, &document(page, display_list.background_color(), border, scale))?;
+        Ok(())
+    }
+    fn net_names(&self) -> bool {
+        true
+    }
+}
+
+/// Draw the page as SVG document.
+///
+/// The size of the document is the drawing area in mm multiplied by the scale.
+pub fn document(page: &Page, background: &Color, border: bool, scale: f64) -> SVG {
+    let (origin, width, height) = page.area(border);
+    let mut rect = SvgRectangle::new()
+        .set("x", format_number(origin[0]))
+        .set("y", format_number(origin[1]))
+        .set("width", format_number(width))
+        .set("height", format_number(height))
+        .set("stroke", "none");
+    fill(&mut rect, &Some(background.clone()));
+    let mut svg = SVG::new()
+        .set("width", format!("{}mm", format_number(width * scale)))
+        .set("height", format!("{}mm", format_number(height * scale)))
+        .set(
+            "viewBox",
+            format!("{} {} {} {}", format_number(origin[0]), format_number(origin[1]), format_number(width), format_number(height)),
+        )
+        .add(rect);
+    for node in page.layers() {
+        svg.append(node_element(node));
+    }
+    svg
+}
+
+/// Convert the node of the display list, groups get the class `element`.
+fn node_element(node: &DisplayNode) -> Element {
+    match node {
+        DisplayNode::Item(item) => element(item),
+        DisplayNode::Group(group) => {
+            let mut element = Group::new().set("class", "element");
+            for (key, value) in &group.attributes {
+                element.assign(format!("data-{}", key), escape(value));
+            }
+            for node in &group.nodes {
+                element.append(node_element(node));
+            }
+            element.get_inner().clone()
+        }
+    }
+}
+
+/// Escape the text for HTML and attribute values.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn rgb(color: &Color) -> String {
+    let channel = |value: f64| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    format!("rgb({},{},{})", channel(color.r), channel(color.g), channel(color.b))
+}
+
+/// Set the stroke attributes, the dashes are relative to the line width.
+fn stroke(node: &mut impl Node, color: &Color, width: f64, linetype: &LineType) {
+    node.assign("stroke", rgb(color));
+    node.assign("stroke-width", format_number(width));
+    if color.a < 1.0 {
+        node.assign("stroke-opacity", format_number(color.a));
+    }
+    let dashes: &[f64] = match linetype {
+        LineType::Dash => &[5.0, 3.0],
+        LineType::DashDot => &[5.0, 3.0, 1.0, 3.0],
+        LineType::DashDotDot => &[5.0, 3.0, 1.0, 3.0, 1.0, 3.0],
+        LineType::Dot => &[1.0, 3.0],
+        LineType::Default | LineType::Solid => &[],
+    };
+    if !dashes.is_empty() {
+        let dashes: Vec<String> = dashes.iter().map(|dash| format_number(dash * width)).collect();
+        node.assign("stroke-dasharray", dashes.join(" "));
+    }
+}
+
+fn fill(node: &mut impl Node, fill: &Option<Color>) {
+    match fill {
+        Some(color) => {
+            node.assign("fill", rgb(color));
+            if color.a < 1.0 {
+                node.assign("fill-opacity", format_number(color.a));
+            }
+        }
+        None => node.assign("fill", "none"),
+    }
+}
+
+fn polyline(pts: &Array2<f64>) -> Data {
+    let mut data = Data::new();
+    for (index, pt) in pts.rows().into_iter().enumerate() {
+        data = if index == 0 { data.move_to((pt[0], pt[1])) } else { data.line_to((pt[0], pt[1])) };
+    }
+    data
+}
+
+/// The text is drawn with the strokes of the built-in font.
+fn text(text: &Text) -> Path {
+    let strokes = text.strokes();
+    let offset = text_offset(text, &arr1(&[strokes.width, strokes.height]));
+    let mut data = Data::new();
+    for line in &strokes.lines {
+        for (index, pt) in line.rows().into_iter().enumerate() {
+            data = if index == 0 { data.move_to((pt[0], pt[1])) } else { data.line_to((pt[0], pt[1])) };
+        }
+    }
+    let mut path = Path::new()
+        .set("d", data)
+        .set(
+            "transform",
+            format!(
+                "translate({} {}) rotate({}) translate({} {})",
+                format_number(text.pos[0]),
+                format_number(text.pos[1]),
+                format_number(-text.angle),
+                format_number(offset[0]),
+                format_number(offset[1]),
+            ),
+        )
+        .set("stroke-linecap", "round")
+        .set("stroke-linejoin", "round");
+    stroke(&mut path, &text.color, strokes.line_width, &LineType::Solid);
+    fill(&mut path, &None);
+    path
+}
+
+fn point(pt: &Array1<f64>) -> (f64, f64) {
+    (pt[0], pt[1])
+}
+
+/// Convert the plot item to a SVG element.
+fn element(item: &PlotItem) -> Element {
+    match item {
+        PlotItem::LineItem(line) => {
+            let mut path = Path::new().set("d", polyline(&line.pts));
+            stroke(&mut path, &line.color, line.linewidth, &line.linetype);
+            fill(&mut path, &None);
+            path.get_inner().clone()
+        }
+        PlotItem::PolylineItem(line) => {
+            let mut path = Path::new().set("d", polyline(&line.pts));
+            stroke(&mut path, &line.color, line.linewidth, &line.linetype);
+            fill(&mut path, &line.fill);
+            path.get_inner().clone()
+        }
+        PlotItem::RectangleItem(rectangle) => {
+            let pts = &rectangle.pts;
+            let mut rect = SvgRectangle::new()
+                .set("x", format_number(pts[[0, 0]].min(pts[[1, 0]])))
+                .set("y", format_number(pts[[0, 1]].min(pts[[1, 1]])))
+                .set("width", format_number((pts[[1, 0]] - pts[[0, 0]]).abs()))
+                .set("height", format_number((pts[[1, 1]] - pts[[0, 1]]).abs()));
+            stroke(&mut rect, &rectangle.color, rectangle.linewidth, &rectangle.linetype);
+            fill(&mut rect, &rectangle.fill);
+            rect.get_inner().clone()
+        }
+        PlotItem::CircleItem(circle) => {
+            let mut node = SvgCircle::new()
+                .set("cx", format_number(circle.pos[0]))
+                .set("cy", format_number(circle.pos[1]))
+                .set("r", format_number(circle.radius));
+            stroke(&mut node, &circle.color, circle.linewidth, &circle.linetype);
+            fill(&mut node, &circle.fill);
+            node.get_inner().clone()
+        }
+        PlotItem::ArcItem(arc) => {
+            let data = match ArcGeometry::new(&arc.start, &arc.mid, &arc.end) {
+                Some(geometry) => {
+                    let large = if geometry.end_angle - geometry.start_angle > std::f64::consts::PI { 1 } else { 0 };
+                    let end = point(&geometry.point(geometry.end_angle));
+                    Data::new()
+                        .move_to(point(&geometry.point(geometry.start_angle)))
+                        .elliptical_arc_to((geometry.radius, geometry.radius, 0, large, 1, end.0, end.1))
+                }
+                None => Data::new().move_to(point(&arc.start)).line_to(point(&arc.end)),
+            };
+            let mut path = Path::new().set("d", data);
+            stroke(&mut path, &arc.color, arc.linewidth, &arc.linetype);
+            fill(&mut path, &arc.fill);
+            path.get_inner().clone()
+        }
+        PlotItem::TextItem(item) => text(item).get_inner().clone(),
+        PlotItem::ImageItem(image) => SvgImage::new()
+            .set("x", format_number(image.pos[0]))
+            .set("y", format_number(image.pos[1]))
+            .set("width", format_number(image.width))
+            .set("height", format_number(image.height))
+            .set("href", format!("data:image/png;base64,{}", base64::encode(&image.data)))
+            .get_inner()
+            .clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr1;
+
+    use super::*;
+    use crate::plot::plot_items;
+    use crate::plotter::Arc;
+    use crate::sexp::parser::SexpParser;
+    use crate::themes::Style;
+
+    #[test]
+    fn svg_document() {
+        let doc = SexpParser::load("samples/files/summe/summe.kicad_sch").unwrap();
+        let mut display_list = DisplayList::new();
+        plot_items(&mut display_list, &doc, false, &Style::new(), None).unwrap();
+        let page = &display_list.pages()[0];
+        let svg = document(page, display_list.background_color(), false, 2.0).to_string();
+        let (origin, width, _) = page.area(false);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(&format!("width=\"{}mm\"", format_number(width * 2.0))));
+        assert!(svg.contains(&format!("viewBox=\"{} {}", format_number(origin[0]), format_number(origin[1]))));
+        assert!(svg.contains(r#"fill="rgb(255,255,255)""#));
+        assert!(svg.contains(r#"data-type="symbol""#));
+        assert_eq!(svg.matches("<g").count(), svg.matches("</g>").count());
+        //the wires are drawn above the symbols
+        assert!(svg.rfind(r#"data-type="symbol""#) < svg.find(r#"data-type="wire""#));
+        assert!(svg.contains("<path"));
+    }
+
+    #[test]
+    fn svg_elements() {
+        assert_eq!(escape(r#"<"R&D">"#), "&lt;&quot;R&amp;D&quot;&gt;");
+        let arc = element(&PlotItem::ArcItem(Arc::new(
+            arr1(&[0.0, 0.0]),
+            arr1(&[1.0, 1.0]),
+            arr1(&[2.0, 0.0]),
+            0.25,
+            LineType::Dash,
+            Color { r: 1.0, g: 0.0, b: 0.0, a: 0.5 },
+            None,
+        )))
+        .to_string();
+        assert!(arc.contains(r#"stroke="rgb(255,0,0)""#));
+        assert!(arc.contains(r#"stroke-opacity="0.5""#));
+        assert!(arc.contains(r#"stroke-dasharray="1.25 0.75""#));
+        assert!(arc.contains("A1,1,0,0,1,"));
+    }
+}
@@ -1,273 +1,519 @@
-use crate::sexp::{Sexp, Color, Effects, Justify, LineType, Stroke, FillType};
+//! Colors and fonts for plotting the schematic.
+//!
+//! A theme has a color for every layer of the schematic. The colors are loaded from
+//! KiCad color theme files, the `elektron` section of a theme file sets the font, the
+//! line widths and the text sizes and can extend one of the built-in themes.
+//! `"force": true` in the `elektron` section uses the theme values instead of
+//! the ones from the file, the KiCad `override_item_colors` does this for the colors.
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use json::JsonValue;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::sexp::{Sexp, Color, Effects, Justify, LineType, Stroke, FillType, Unset};
 use crate::sexp::get::{get, Get};
 use crate::sexp::test::Test;
 use crate::Error;
 
+/// The names of the built-in themes.
+pub const THEMES: [&str; 3] = ["light", "dark", "monochrome"];
+
+const LIGHT: &str = include_str!("themes/light.json");
+const DARK: &str = include_str!("themes/dark.json");
+const MONOCHROME: &str = include_str!("themes/monochrome.json");
+
+lazy_static! {
+    static ref RE_COLOR: Regex =
+        Regex::new(r"^rgba?\(\s*(\d+)\s*,\s*(\d+)\s*,\s*(\d+)\s*(?:,\s*([0-9.]+)\s*)?\)$").unwrap();
+}
+
+/// The layers of the schematic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum StyleContext {
     SchemaSymbol,
     SchemaWire,
+    SchemaBus,
+    SchemaGraphic,
     SchemaProperty,
+    SchemaJunction,
+    SchemaNoConnect,
+    SchemaPin,
+    SchemaPinName,
+    SchemaPinNumber,
+    SchemaReference,
+    SchemaValue,
+    SchemaLabel,
+    SchemaGlobalLabel,
+    SchemaHierarchicalLabel,
+    SchemaSheet,
+    SchemaSheetName,
+    SchemaSheetFile,
+    SchemaSheetPin,
+    SchemaNote,
+    SchemaBorder,
+}
+
+impl StyleContext {
+    pub const ALL: [StyleContext; 21] = [
+        StyleContext::SchemaSymbol,
+        StyleContext::SchemaWire,
+        StyleContext::SchemaBus,
+        StyleContext::SchemaGraphic,
+        StyleContext::SchemaProperty,
+        StyleContext::SchemaJunction,
+        StyleContext::SchemaNoConnect,
+        StyleContext::SchemaPin,
+        StyleContext::SchemaPinName,
+        StyleContext::SchemaPinNumber,
+        StyleContext::SchemaReference,
+        StyleContext::SchemaValue,
+        StyleContext::SchemaLabel,
+        StyleContext::SchemaGlobalLabel,
+        StyleContext::SchemaHierarchicalLabel,
+        StyleContext::SchemaSheet,
+        StyleContext::SchemaSheetName,
+        StyleContext::SchemaSheetFile,
+        StyleContext::SchemaSheetPin,
+        StyleContext::SchemaNote,
+        StyleContext::SchemaBorder,
+    ];
+
+    /// The name of the layer in the KiCad color theme, graphic items use the note color.
+    pub fn key(&self) -> &'static str {
+        match self {
+            StyleContext::SchemaSymbol => "component_outline",
+            StyleContext::SchemaWire => "wire",
+            StyleContext::SchemaBus => "bus",
+            StyleContext::SchemaGraphic => "note",
+            StyleContext::SchemaProperty => "fields",
+            StyleContext::SchemaJunction => "junction",
+            StyleContext::SchemaNoConnect => "no_connect",
+            StyleContext::SchemaPin => "pin",
+            StyleContext::SchemaPinName => "pin_name",
+            StyleContext::SchemaPinNumber => "pin_number",
+            StyleContext::SchemaReference => "reference",
+            StyleContext::SchemaValue => "value",
+            StyleContext::SchemaLabel => "label_local",
+            StyleContext::SchemaGlobalLabel => "label_global",
+            StyleContext::SchemaHierarchicalLabel => "label_hier",
+            StyleContext::SchemaSheet => "sheet",
+            StyleContext::SchemaSheetName => "sheet_name",
+            StyleContext::SchemaSheetFile => "sheet_filename",
+            StyleContext::SchemaSheetPin => "sheet_label",
+            StyleContext::SchemaNote => "note",
+            StyleContext::SchemaBorder => "worksheet",
+        }
+    }
+}
+
+/// Parse a KiCad theme color like `rgb(0, 150, 0)` or `rgba(230, 9, 13, 0.800)`.
+pub fn parse_color(value: &str) -> Result<Color, Error> {
+    let captures = RE_COLOR
+        .captures(value.trim())
+        .ok_or_else(|| Error::ValueError(format!("invalid theme color: {}", value)))?;
+    let channel = |index: usize| -> Result<f64, Error> {
+        let value: f64 = captures[index]
+            .parse()
+            .map_err(|_| Error::ValueError(format!("invalid theme color: {}", value)))?;
+        Ok(value / 255.0)
+    };
+    let a = match captures.get(4) {
+        Some(alpha) => alpha
+            .as_str()
+            .parse()
+            .map_err(|_| Error::ValueError(format!("invalid theme color: {}", value)))?,
+        None => 1.0,
+    };
+    Ok(Color { r: channel(1)?, g: channel(2)?, b: channel(3)?, a })
 }
 
+#[derive(Debug, Clone)]
 pub struct Style {
-    property_effects: Effects,
-    label_effects: Effects,
-    schema_wire: Stroke,
-    schema_symbol: Stroke,
-    graphic: Stroke,
-    fill_outline: Color,
+    name: String,
+    font: String,
+    colors: HashMap<StyleContext, Color>,
+    widths: HashMap<StyleContext, f64>,
+    sizes: HashMap<StyleContext, f64>,
+    background: Color,
     fill_background: Color,
-    schema_border: Stroke,
-    schema_title_effects: Effects,
-    schema_effects: Effects,
+    force: bool,
+    override_colors: bool,
+    op_voltage_effects: Effects,
+    op_current_effects: Effects,
 }
 
 /// Access the nodes and values.
+///
+/// The values are taken from the file, the attributes that are unset in the
+/// file are taken from the theme, the theme itself falls back to the built-in
+/// defaults. When the theme is forced, its values replace the ones of the file.
 pub trait StyleTypes<S, T> {
     fn style(&self, node: &Sexp, index: S, context: StyleContext) -> Result<T, Error>;
 }
 
 impl StyleTypes<&str, Effects> for Style {
     fn style(&self, node: &Sexp, key: &str, context: StyleContext) -> Result<Effects, Error> {
-    
-        let overwrite = true;
-
-        let style_effects = self.effects(&context);
+        let theme = self.effects(&context);
         if !node.contains(key) {
-            return Ok(style_effects);
+            return Ok(theme);
         }
-        let effect: Effects = get!(node, key).unwrap();
-
-        let font = if effect.font != "" && !overwrite {
-            effect.font.clone()
-        } else {
-            style_effects.font.clone()
-        };
-        let size = if effect.size != 0.0 && !overwrite {
-            effect.size.clone()
+        let effects: Effects = get!(node, key)?;
+        let (size, width) = if self.keep(effects.unset.size) {
+            (effects.size, if effects.unset.width { effects.size } else { effects.width })
         } else {
-            style_effects.size.clone()
+            (theme.size, theme.width)
         };
-        let thickness = if effect.thickness != 0.0 && !overwrite {
-            effect.thickness.clone()
-        } else {
-            style_effects.thickness.clone()
-        };
-        let line_spacing = if effect.line_spacing != 0.0 && !overwrite {
-            effect.line_spacing
-        } else {
-            style_effects.line_spacing
-        };
-        let justify = if !effect.justify.is_empty() {
-            effect.justify
-        } else {
-            style_effects.justify.clone()
-        };
-        Ok(Effects::new(
-            font,
-            effect.color.clone(),
+        Ok(Effects {
+            font: if self.keep(effects.unset.font) { effects.font } else { theme.font },
+            color: if self.keep_color(effects.unset.color) { effects.color } else { theme.color },
             size,
-            thickness,
-            effect.bold,
-            effect.italic,
-            line_spacing,
-            justify,
-            effect.hide,
-        ))
+            width,
+            thickness: if self.keep(effects.unset.thickness) { effects.thickness } else { theme.thickness },
+            line_spacing: if self.keep(effects.unset.line_spacing) {
+                effects.line_spacing
+            } else {
+                theme.line_spacing
+            },
+            justify: if effects.justify.is_empty() { theme.justify } else { effects.justify },
+            ..effects
+        })
     }
 }
 
 impl StyleTypes<&str, Stroke> for Style {
-    fn style(
-        &self,
-        node: &Sexp,
-        key: &str,
-        context: StyleContext,
-    ) -> Result<Stroke, Error> {
-
-        let stroke: Result<Stroke, _> = get!(node, key);
-        let style_stroke = self.stroke(&context);
-
-        match stroke {
-            Ok(stroke) => Ok(Stroke {
-                width: if stroke.width != 0.0 {
-                    stroke.width
-                } else {
-                    style_stroke.width
-                },
-                line_type: stroke.line_type,
-                color: if stroke.color
-                    != (Color {
-                        r: 0.0,
-                        g: 0.0,
-                        b: 0.0,
-                        a: 0.0,
-                    }) {
-                    stroke.color.clone()
-                } else {
-                    style_stroke.color
-                },
-                fill: stroke.fill,
-            }),
-            _ => Ok(style_stroke.clone()),
+    fn style(&self, node: &Sexp, key: &str, context: StyleContext) -> Result<Stroke, Error> {
+        let theme = self.stroke(&context);
+        if !node.contains(key) {
+            return Ok(theme);
         }
+        let stroke: Stroke = get!(node, key)?;
+        Ok(Stroke {
+            width: if self.keep(stroke.unset.width) { stroke.width } else { theme.width },
+            color: if self.keep_color(stroke.unset.color) { stroke.color } else { theme.color },
+            ..stroke
+        })
     }
 }
 
 impl Style {
+    /// The default theme, the light built-in theme.
     pub fn new() -> Style {
-        Style {
-            property_effects: Effects::new(
-                "osifont".to_string(),
-                Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                2.0,
-                1.0,
-                false,
-                false,
-                1.0,
-                vec![Justify::Center],
-                false,
-            ),
-            label_effects: Effects::new(
-                "osifont".to_string(),
-                Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                2.0,
-                1.0,
-                false,
-                false,
-                1.0,
-                vec![Justify::Center],
-                false,
-            ),
-            schema_wire: Stroke {
-                width: 0.25,
-                line_type: LineType::Default,
-                color: Color {
-                    r: 0.0,
-                    g: 150.0 / 255.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                fill: FillType::None,
-            },
-            schema_symbol: Stroke {
-                width: 0.25,
-                line_type: LineType::Default,
-                color: Color {
-                    r: 0.0,
-                    g: 150.0 / 255.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                fill: FillType::None,
-            },
-            graphic: Stroke {
-                width: 0.2,
-                line_type: LineType::Default,
-                color: Color {
-                    r: 132.0 / 255.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                fill: FillType::None,
-            },
-            fill_outline: Color {
-                r: 132.0 / 255.0,
-                g: 0.0,
-                b: 0.0,
-                a: 1.0,
-            },
-            fill_background: Color {
-                r: 1.0,
-                g: 1.0,
-                b: 194.0 / 255.0,
-                a: 1.0,
-            },
-            schema_border: Stroke {
-                width: 0.25,
-                line_type: LineType::Default,
-                color: Color {
-                    r: 0.0,
-                    g: 150.0 / 255.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                fill: FillType::None,
+        Style::builtin("light").unwrap()
+    }
+
+    /// Get the built-in theme by name.
+    pub fn builtin(name: &str) -> Option<Style> {
+        let content = match name {
+            "light" => LIGHT,
+            "dark" => DARK,
+            "monochrome" => MONOCHROME,
+            _ => return None,
+        };
+        let mut style = Style::defaults();
+        style.apply(&json::parse(content).unwrap()).unwrap();
+        Some(style)
+    }
+
+    /// Load a KiCad color theme or an elektron theme file.
+    pub fn load(path: &Path) -> Result<Style, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Select the theme by the name of a built-in theme or the filename, the default is the light theme.
+    pub fn select(theme: Option<&str>) -> Result<Style, Error> {
+        match theme {
+            None => Ok(Style::new()),
+            Some(theme) => match Style::builtin(theme) {
+                Some(style) => Ok(style),
+                None => Style::load(Path::new(theme)),
             },
-            schema_effects: Effects::new(
+        }
+    }
+
+    /// The line widths and text sizes, all colors are black on white.
+    fn defaults() -> Style {
+        let black = Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+        let mut colors = HashMap::new();
+        let mut widths = HashMap::new();
+        let mut sizes = HashMap::new();
+        for context in StyleContext::ALL {
+            colors.insert(context, black.clone());
+            widths.insert(context, match context {
+                StyleContext::SchemaBus => 0.3,
+                StyleContext::SchemaGraphic | StyleContext::SchemaNote => 0.2,
+                _ => 0.25,
+            });
+            sizes.insert(context, match context {
+                StyleContext::SchemaPinName | StyleContext::SchemaPinNumber => 1.25,
+                StyleContext::SchemaBorder => 2.5,
+                _ => 2.0,
+            });
+        }
+        Style {
+            name: String::new(),
+            font: String::from("osifont"),
+            colors,
+            widths,
+            sizes,
+            background: Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 },
+            fill_background: Color { r: 1.0, g: 1.0, b: 194.0 / 255.0, a: 1.0 },
+            force: false,
+            override_colors: false,
+            op_voltage_effects: Effects::new(
                 "osifont".to_string(),
-                Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                2.5,
-                1.0,
+                Color { r: 0.0, g: 0.0, b: 194.0 / 255.0, a: 1.0 },
+                1.5,
+                0.0,
                 false,
                 false,
                 1.0,
-                vec![Justify::Left],
+                vec![Justify::Left, Justify::Bottom],
                 false,
             ),
-            schema_title_effects: Effects::new(
+            op_current_effects: Effects::new(
                 "osifont".to_string(),
-                Color {
-                    r: 0.0,
-                    g: 0.0,
-                    b: 0.0,
-                    a: 1.0,
-                },
-                5.0,
-                1.0,
+                Color { r: 194.0 / 255.0, g: 0.0, b: 0.0, a: 1.0 },
+                1.5,
+                0.0,
                 false,
                 false,
                 1.0,
-                vec![Justify::Left],
+                vec![Justify::Left, Justify::Top],
                 false,
             ),
         }
     }
 
+    /// Apply the colors of the `schematic` section and the `elektron` settings of the theme.
+    fn apply(&mut self, theme: &JsonValue) -> Result<(), Error> {
+        if let Some(name) = theme["meta"]["name"].as_str() {
+            self.name = name.to_string();
+        }
+        let schematic = &theme["schematic"];
+        let elektron = &theme["elektron"];
+        for context in StyleContext::ALL {
+            if let Some(color) = schematic[context.key()].as_str() {
+                self.colors.insert(context, parse_color(color)?);
+            }
+            if let Some(width) = elektron["widths"][context.key()].as_f64() {
+                self.widths.insert(context, width);
+            }
+            if let Some(size) = elektron["sizes"][context.key()].as_f64() {
+                self.sizes.insert(context, size);
+            }
+        }
+        if let Some(color) = schematic["background"].as_str() {
+            self.background = parse_color(color)?;
+        }
+        if let Some(color) = schematic["component_body"].as_str() {
+            self.fill_background = parse_color(color)?;
+        }
+        if let Some(font) = elektron["font"].as_str() {
+            self.font = font.to_string();
+            self.op_voltage_effects.font = font.to_string();
+            self.op_current_effects.font = font.to_string();
+        }
+        if let Some(color) = elektron["colors"]["op_voltage"].as_str() {
+            self.op_voltage_effects.color = parse_color(color)?;
+        }
+        if let Some(color) = elektron["colors"]["op_current"].as_str() {
+            self.op_current_effects.color = parse_color(color)?;
+        }
+        if let Some(override_colors) = schematic["override_item_colors"].as_bool() {
+            self.override_colors = override_colors;
+        }
+        if let Some(force) = elektron["force"].as_bool() {
+            self.force = force;
+        }
+        Ok(())
+    }
+
+    /// Use the values of the theme instead of the ones set in the file.
+    pub fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    /// Is the value from the file used.
+    fn keep(&self, unset: bool) -> bool {
+        !unset && !self.force
+    }
+
+    /// Is the color from the file used.
+    fn keep_color(&self, unset: bool) -> bool {
+        self.keep(unset) && !self.override_colors
+    }
+
+    /// The name of the theme.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The color of the page.
+    pub fn background(&self) -> Color {
+        self.background.clone()
+    }
+
     pub fn stroke(&self, context: &StyleContext) -> Stroke {
-        match context {
-            StyleContext::SchemaWire => self.schema_wire.clone(),
-            StyleContext::SchemaSymbol => self.schema_symbol.clone(),
-            _ => self.schema_symbol.clone(), //TODO
+        Stroke {
+            width: self.widths[context],
+            line_type: LineType::Default,
+            color: self.colors[context].clone(),
+            fill: FillType::None,
+            unset: Unset::default(),
         }
     }
     pub fn effects(&self, context: &StyleContext) -> Effects {
-        match context {
-            StyleContext::SchemaWire => self.label_effects.clone(),
-            StyleContext::SchemaSymbol => self.label_effects.clone(),
-            _ => self.label_effects.clone(),
-        }
+        let justify = if *context == StyleContext::SchemaBorder { Justify::Left } else { Justify::Center };
+        Effects::new(
+            self.font.clone(),
+            self.colors[context].clone(),
+            self.sizes[context],
+            0.0,
+            false,
+            false,
+            1.0,
+            vec![justify],
+            false,
+        )
     }
     pub fn color(&self, fill: &FillType) -> Option<Color> {
         match fill {
-            FillType::Outline => Option::from(self.fill_outline.clone()),
+            FillType::Outline => Option::from(self.colors[&StyleContext::SchemaSymbol].clone()),
             FillType::Background => Option::from(self.fill_background.clone()),
+            FillType::Color(color) => Option::from(color.clone()),
             _ => None,
         }
     }
     pub fn schema_border(&self) -> Stroke {
-        return self.schema_border.clone();
+        self.stroke(&StyleContext::SchemaBorder)
     }
     pub fn schema_effects(&self) -> Effects {
-        return self.schema_effects.clone();
+        self.effects(&StyleContext::SchemaBorder)
     }
     pub fn schema_title_effects(&self) -> Effects {
-        return self.schema_title_effects.clone();
+        Effects { size: self.sizes[&StyleContext::SchemaBorder] * 2.0, ..self.schema_effects() }
+    }
+    pub fn op_voltage_effects(&self) -> Effects {
+        self.op_voltage_effects.clone()
+    }
+    pub fn op_current_effects(&self) -> Effects {
+        self.op_current_effects.clone()
+    }
+}
+
+impl FromStr for Style {
+    type Err = Error;
+
+    /// Parse the theme, the colors that are not in the theme are taken from the base theme.
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        let theme = json::parse(content).map_err(|err| Error::ValueError(format!("can not parse theme: {}", err)))?;
+        let base = theme["elektron"]["base"].as_str().unwrap_or("light");
+        let mut style = Style::builtin(base)
+            .ok_or_else(|| Error::ValueError(format!("unknown base theme: {}", base)))?;
+        style.apply(&theme)?;
+        Ok(style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sexp::parser::SexpParser;
+
+    #[test]
+    fn theme_colors() {
+        assert_eq!(parse_color("rgb(0, 150, 0)").unwrap(), Color { r: 0.0, g: 150.0 / 255.0, b: 0.0, a: 1.0 });
+        assert_eq!(parse_color("rgba(255, 0, 0, 0.800)").unwrap(), Color { r: 1.0, g: 0.0, b: 0.0, a: 0.8 });
+        assert!(parse_color("#ff0000").is_err());
+    }
+
+    #[test]
+    fn builtin_themes() {
+        for name in THEMES {
+            let style = Style::builtin(name).unwrap();
+            assert_eq!(style.name(), name);
+        }
+        assert!(Style::builtin("solarized").is_none());
+        let light = Style::new();
+        assert_eq!(light.stroke(&StyleContext::SchemaWire).color, parse_color("rgb(0, 150, 0)").unwrap());
+        assert_eq!(light.effects(&StyleContext::SchemaPinNumber).size, 1.25);
+        let dark = Style::builtin("dark").unwrap();
+        assert_eq!(dark.background(), parse_color("rgb(31, 31, 31)").unwrap());
+        let monochrome = Style::builtin("monochrome").unwrap();
+        for context in StyleContext::ALL {
+            assert_eq!(monochrome.stroke(&context).color, Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 });
+        }
+        assert_eq!(monochrome.op_voltage_effects().color, Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 });
+        assert_eq!(monochrome.op_current_effects().color, Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 });
+    }
+
+    #[test]
+    fn load_theme() {
+        let style = Style::select(Some("samples/files/themes/blueprint.json")).unwrap();
+        let dark = Style::builtin("dark").unwrap();
+        assert_eq!(style.name(), "Blueprint");
+        assert_eq!(style.background(), parse_color("rgb(16, 52, 110)").unwrap());
+        assert_eq!(style.color(&FillType::Background).unwrap().a, 0.1);
+        let wire = style.stroke(&StyleContext::SchemaWire);
+        assert_eq!(wire.width, 0.3);
+        assert_eq!(wire.color, parse_color("rgb(200, 230, 255)").unwrap());
+        assert_eq!(style.effects(&StyleContext::SchemaReference).size, 1.5);
+        //the other layers are from the base theme
+        assert_eq!(style.stroke(&StyleContext::SchemaBus), dark.stroke(&StyleContext::SchemaBus));
+
+        assert!(Style::from_str(r#"{"elektron": {"base": "solarized"}}"#).is_err());
+        assert!(Style::from_str(r#"{"schematic": {"wire": "green"}}"#).is_err());
+        assert!(Style::select(Some("samples/files/themes/missing.json")).is_err());
+    }
+
+    #[test]
+    fn precedence() {
+        let doc: SexpParser = r#"(kicad_sch
+  (wire (pts (xy 0 0) (xy 10 0)) (stroke (width 0.5) (type dash) (color 255 0 0 1)))
+  (bus (pts (xy 0 0) (xy 10 0)) (stroke (width 0) (type default) (color 0 0 0 0)))
+  (text "Title" (at 0 0 0) (effects (font (face "Arial") (size 5.08 4) (color 0 0 255 1)) (justify left)))
+  (text "note" (at 0 0 0) (effects (font (size 1.27 1.27)))))"#
+            .parse()
+            .unwrap();
+        let node = doc.root();
+        let nodes = |name: &str| -> Vec<&Sexp> { node.get(name).unwrap() };
+        let mut style = Style::new();
+
+        //the values from the file
+        let wire: Stroke = style.style(nodes("wire")[0], "stroke", StyleContext::SchemaWire).unwrap();
+        assert_eq!((wire.width, wire.line_type), (0.5, LineType::Dash));
+        assert_eq!(wire.color, Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 });
+        let title: Effects = style.style(nodes("text")[0], "effects", StyleContext::SchemaNote).unwrap();
+        assert_eq!((title.font.as_str(), title.size, title.width), ("Arial", 5.08, 4.0));
+        assert_eq!(title.color, Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 });
+        assert_eq!(title.justify, vec![Justify::Left]);
+
+        //the unset values from the theme
+        let bus: Stroke = style.style(nodes("bus")[0], "stroke", StyleContext::SchemaBus).unwrap();
+        assert_eq!(bus, Stroke { unset: bus.unset.clone(), ..style.stroke(&StyleContext::SchemaBus) });
+        let note: Effects = style.style(nodes("text")[1], "effects", StyleContext::SchemaNote).unwrap();
+        assert_eq!(note.size, 1.27);
+        assert_eq!(note.font, style.effects(&StyleContext::SchemaNote).font);
+        assert_eq!(note.color, style.effects(&StyleContext::SchemaNote).color);
+        let missing: Stroke = style.style(nodes("text")[1], "stroke", StyleContext::SchemaGraphic).unwrap();
+        assert_eq!(missing, style.stroke(&StyleContext::SchemaGraphic));
+
+        //the theme overrides the colors
+        style.override_colors = true;
+        let wire: Stroke = style.style(nodes("wire")[0], "stroke", StyleContext::SchemaWire).unwrap();
+        assert_eq!(wire.width, 0.5);
+        assert_eq!(wire.color, style.stroke(&StyleContext::SchemaWire).color);
+
+        //the forced theme overrides all values
+        style.set_force(true);
+        let title: Effects = style.style(nodes("text")[0], "effects", StyleContext::SchemaNote).unwrap();
+        assert_eq!(title.size, style.effects(&StyleContext::SchemaNote).size);
+        assert_eq!(title.font, "osifont");
+        let wire: Stroke = style.style(nodes("wire")[0], "stroke", StyleContext::SchemaWire).unwrap();
+        assert_eq!(wire.width, 0.25);
+        assert!(Style::from_str(r#"{"elektron": {"force": true}}"#).unwrap().force);
     }
 }
@@ -0,0 +1,381 @@
+use crate::Error;
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// The engineering prefixes and exponents used for formatting.
+const PREFIXES: [(i32, &str); 11] = [
+    (12, "T"),
+    (9, "G"),
+    (6, "M"),
+    (3, "k"),
+    (0, ""),
+    (-3, "m"),
+    (-6, "u"),
+    (-9, "n"),
+    (-12, "p"),
+    (-15, "f"),
+    (-18, "a"),
+];
+
+/// The units that can follow a value.
+const UNITS: [&str; 10] = ["F", "H", "V", "A", "W", "Hz", "s", "Ω", "Ohm", "ohm"];
+
+/// A component value like `100k`, `4k7`, `47nF` or `1Meg`.
+///
+/// The value is parsed with the KiCad notation where the prefixes are case
+/// sensitive: `M` is mega and `m` is milli. `Value::from_spice` parses the
+/// SPICE notation where the prefixes are case insensitive and `M` is milli.
+/// In KiCad notation the prefix can be used as decimal point (`4k7`, `4R7`) and a unit can
+/// follow the prefix (`47nF`, `10kΩ`). Text that is not a known unit is
+/// rejected, `1N4148` is not a value.
+#[derive(Debug, Clone, Copy)]
+pub struct Value(f64);
+
+impl Value {
+    pub fn new(value: f64) -> Self {
+        Value(value)
+    }
+
+    /// Parse a value in SPICE notation.
+    pub fn from_spice(value: &str) -> Result<Self, Error> {
+        parse(value, true).map(|(value, _)| value)
+    }
+
+    /// Parse a value in KiCad notation and return the unit, `47nF` is `(47n, "F")`.
+    pub fn with_unit(value: &str) -> Result<(Self, &str), Error> {
+        parse(value, false)
+    }
+
+    /// The value as float.
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// Format the value for spice, mega is written as `Meg`.
+    pub fn to_spice(&self) -> String {
+        let (mantissa, prefix) = self.engineering();
+        let prefix = match prefix {
+            "M" => "Meg",
+            p => p,
+        };
+        format!("{}{}", mantissa, prefix)
+    }
+
+    /// Compare two values with a relative tolerance.
+    pub fn approx_eq(&self, other: &Value, tolerance: f64) -> bool {
+        (self.0 - other.0).abs() <= tolerance * self.0.abs().max(other.0.abs())
+    }
+
+    fn engineering(&self) -> (String, &'static str) {
+        if self.0 == 0.0 || !self.0.is_finite() {
+            return (self.0.to_string(), "");
+        }
+        let exponent = (self.0.abs().log10() / 3.0).floor() as i32 * 3;
+        let exponent = exponent.clamp(-18, 12);
+        let prefix = PREFIXES.iter().find(|(e, _)| *e == exponent).unwrap().1;
+        let mantissa = self.0 / 10f64.powi(exponent);
+        //remove the floating point noise and trailing zeros
+        let mut mantissa = format!("{:.6}", mantissa);
+        if mantissa.contains('.') {
+            mantissa = mantissa.trim_end_matches('0').trim_end_matches('.').to_string();
+        }
+        if mantissa == "1000" && exponent < 12 {
+            let prefix = PREFIXES.iter().find(|(e, _)| *e == exponent + 3).unwrap().1;
+            return (String::from("1"), prefix);
+        }
+        (mantissa, prefix)
+    }
+}
+
+/// Get the exponent of a prefix, returns the exponent and the length of the prefix.
+fn prefix(rest: &str, spice: bool) -> Option<(i32, usize)> {
+    let lower = rest.to_lowercase();
+    if lower.starts_with("meg") {
+        return Some((6, 3));
+    }
+    if lower.starts_with("mil") {
+        //spice: 25.4e-6, handled as a special case by the caller.
+        return None;
+    }
+    let ch = rest.chars().next()?;
+    let exponent = if spice {
+        match ch.to_ascii_lowercase() {
+            't' => 12,
+            'g' => 9,
+            'k' => 3,
+            'm' => -3,
+            'u' | 'µ' | 'μ' => -6,
+            'n' => -9,
+            'p' => -12,
+            'f' => -15,
+            'a' => -18,
+            _ => return None,
+        }
+    } else {
+        match ch {
+            'T' => 12,
+            'G' => 9,
+            'M' => 6,
+            'k' => 3,
+            'R' => 0,
+            'm' => -3,
+            'u' | 'µ' | 'μ' => -6,
+            'n' => -9,
+            'p' => -12,
+            'f' => -15,
+            'a' => -18,
+            _ => return None,
+        }
+    };
+    Some((exponent, ch.len_utf8()))
+}
+
+/// Test if the text is empty or a unit, the units are case insensitive in spice.
+fn is_unit(text: &str, spice: bool) -> bool {
+    text.is_empty()
+        || UNITS
+            .iter()
+            .any(|unit| if spice { unit.eq_ignore_ascii_case(text) } else { *unit == text })
+}
+
+fn parse(value: &str, spice: bool) -> Result<(Value, &str), Error> {
+    let error = || Error::ValueError(value.to_string());
+    let text = value.trim();
+    //the numeric part, with sign, decimal point and exponent
+    let mut end = 0;
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut i = 0;
+    if let Some((_, '+')) | Some((_, '-')) = chars.first() {
+        i += 1;
+    }
+    while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+        i += 1;
+        end = i;
+    }
+    if end == 0 {
+        return Err(error());
+    }
+    //scientific notation, but not the 'E' used as decimal point.
+    if i + 1 < chars.len() && (chars[i].1 == 'e' || chars[i].1 == 'E') {
+        let mut j = i + 1;
+        if chars[j].1 == '+' || chars[j].1 == '-' {
+            j += 1;
+        }
+        if j < chars.len() && chars[j].1.is_ascii_digit() {
+            while j < chars.len() && chars[j].1.is_ascii_digit() {
+                j += 1;
+            }
+            i = j;
+        }
+    }
+    let number_end = chars.get(i).map(|(pos, _)| *pos).unwrap_or(text.len());
+    let mut number = text[..number_end].to_string();
+    let rest = &text[number_end..];
+
+    let mut exponent = 0;
+    let mut unit = rest;
+    if rest.to_lowercase().starts_with("mil") && spice {
+        let value: f64 = number.parse().map_err(|_| error())?;
+        return Ok((Value(value * 25.4e-6), ""));
+    } else if let Some((exp, len)) = prefix(rest, spice) {
+        exponent = exp;
+        unit = &rest[len..];
+        //the prefix used as decimal point: 4k7, this is not spice notation
+        let digits: String = unit.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if !digits.is_empty() && !spice {
+            if number.contains('.') || number.contains('e') || number.contains('E') {
+                return Err(error());
+            }
+            number = format!("{}.{}", number, digits);
+            unit = &unit[digits.len()..];
+        }
+    }
+    //what remains must be a unit
+    if !is_unit(unit, spice) {
+        return Err(error());
+    }
+    //scale by the exponent in the text to keep the value exact: 4.7e3 and not 4.7 * 1000.0
+    if let Some(pos) = number.find(['e', 'E']) {
+        let number_exponent: i32 = number[pos + 1..].parse().map_err(|_| error())?;
+        exponent += number_exponent;
+        number.truncate(pos);
+    }
+    let value: f64 = format!("{}e{}", number, exponent).parse().map_err(|_| error())?;
+    Ok((Value(value), unit))
+}
+
+impl FromStr for Value {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse(value, false).map(|(value, _)| value)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (mantissa, prefix) = self.engineering();
+        write!(f, "{}{}", mantissa, prefix)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value(value)
+    }
+}
+
+/// The values are equal when they have the same order, `NaN` is equal to itself and `-0` is not `0`.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+macro_rules! operator {
+    ($trait:ident, $fn:ident, $op:tt) => {
+        impl $trait for Value {
+            type Output = Value;
+            fn $fn(self, other: Value) -> Value {
+                Value(self.0 $op other.0)
+            }
+        }
+        impl $trait<f64> for Value {
+            type Output = Value;
+            fn $fn(self, other: f64) -> Value {
+                Value(self.0 $op other)
+            }
+        }
+    };
+}
+operator!(Add, add, +);
+operator!(Sub, sub, -);
+operator!(Mul, mul, *);
+operator!(Div, div, /);
+
+impl Neg for Value {
+    type Output = Value;
+    fn neg(self) -> Value {
+        Value(-self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(v: &str) -> f64 {
+        v.parse::<Value>().unwrap().value()
+    }
+
+    #[test]
+    fn parse_kicad() {
+        assert_eq!(value("100k"), 100e3);
+        assert_eq!(value("47n"), 47e-9);
+        assert_eq!(value("1Meg"), 1e6);
+        assert_eq!(value("1M"), 1e6);
+        assert_eq!(value("10m"), 10e-3);
+        assert_eq!(value("2.2u"), 2.2e-6);
+        assert_eq!(value("2.2µ"), 2.2e-6);
+        assert_eq!(value("100p"), 100e-12);
+        assert_eq!(value("1f"), 1e-15);
+        assert_eq!(value("1a"), 1e-18);
+        assert_eq!(value("330"), 330.0);
+        assert_eq!(value("-5"), -5.0);
+        assert_eq!(value("1e-3"), 1e-3);
+    }
+    #[test]
+    fn parse_european() {
+        assert_eq!(value("4k7"), 4700.0);
+        assert_eq!(value("2u2"), 2.2e-6);
+        assert_eq!(value("4R7"), 4.7);
+        assert_eq!(value("1M5"), 1.5e6);
+    }
+    #[test]
+    fn parse_units() {
+        assert_eq!(value("47nF"), 47e-9);
+        assert_eq!(value("10kΩ"), 10e3);
+        assert_eq!(value("5V"), 5.0);
+        assert_eq!(value("4k7Ohm"), 4700.0);
+    }
+    #[test]
+    fn parse_spice() {
+        assert_eq!(Value::from_spice("1M").unwrap().value(), 1e-3);
+        assert_eq!(Value::from_spice("1MEG").unwrap().value(), 1e6);
+        assert_eq!(Value::from_spice("10K").unwrap().value(), 10e3);
+        assert_eq!(Value::from_spice("100F").unwrap().value(), 100e-15);
+        assert!(Value::from_spice("1mil").unwrap().approx_eq(&Value::new(25.4e-6), 1e-12));
+    }
+    #[test]
+    fn parse_errors() {
+        assert!("".parse::<Value>().is_err());
+        assert!("k47".parse::<Value>().is_err());
+        assert!("4.7k7".parse::<Value>().is_err());
+        assert!("10k 5%".parse::<Value>().is_err());
+        //part numbers and wrong case prefixes are not values
+        assert!("1N4148".parse::<Value>().is_err());
+        assert!("2N2222".parse::<Value>().is_err());
+        assert!("10mil".parse::<Value>().is_err());
+        assert!("10K".parse::<Value>().is_err());
+        assert!("47nX".parse::<Value>().is_err());
+        assert!(Value::from_spice("1N4148").is_err());
+        assert!(Value::from_spice("10kfoo").is_err());
+    }
+    #[test]
+    fn parse_with_unit() {
+        assert_eq!(Value::with_unit("10uF").unwrap(), (Value::new(10e-6), "F"));
+        assert_eq!(Value::with_unit("10uH").unwrap(), (Value::new(10e-6), "H"));
+        assert_eq!(Value::with_unit("4k7").unwrap(), (Value::new(4700.0), ""));
+        assert_eq!(Value::from_spice("10UF").unwrap().value(), 10e-6);
+    }
+    #[test]
+    fn format() {
+        assert_eq!(Value::new(4700.0).to_string(), "4.7k");
+        assert_eq!(Value::new(1e6).to_string(), "1M");
+        assert_eq!(Value::new(1e6).to_spice(), "1Meg");
+        assert_eq!(Value::new(47e-9).to_string(), "47n");
+        assert_eq!(Value::new(0.1e-6).to_string(), "100n");
+        assert_eq!(Value::new(330.0).to_string(), "330");
+        assert_eq!(Value::new(0.0).to_string(), "0");
+        assert_eq!(Value::new(999.9999999).to_string(), "1k");
+        assert_eq!("4k7".parse::<Value>().unwrap().to_string(), "4.7k");
+        //the formatted values can be parsed again
+        for v in [1e-18, 2.2e-15, 47e-9, 4.7e3, 1e12] {
+            assert_eq!(Value::new(v).to_string().parse::<Value>().unwrap(), Value::new(v));
+        }
+    }
+    #[test]
+    fn arithmetic() {
+        let r1: Value = "4k7".parse().unwrap();
+        let r2: Value = "5.3k".parse().unwrap();
+        assert_eq!((r1 + r2).to_string(), "10k");
+        assert_eq!((r2 - r1).to_string(), "600");
+        assert_eq!((r1 * 2.0).to_string(), "9.4k");
+        assert_eq!((r1 / 2.0).to_string(), "2.35k");
+        assert!(r1 < r2);
+        assert_eq!("4.7k".parse::<Value>().unwrap(), r1);
+    }
+    #[test]
+    fn compare() {
+        //equality agrees with the order
+        assert_eq!(Value::new(f64::NAN), Value::new(f64::NAN));
+        assert_ne!(Value::new(-0.0), Value::new(0.0));
+        assert!(Value::new(-0.0) < Value::new(0.0));
+    }
+}